@@ -0,0 +1,156 @@
+use clap::ArgMatches;
+use failure::{err_msg, Error, ResultExt};
+use serde::de::DeserializeSeed;
+use serde_json::Deserializer;
+use std::{
+    fs::File,
+    io::{stdin, stdout, BufRead, BufReader, Write},
+};
+use wirefilter::{ExecutionContext, Filter, Scheme};
+use {read_input, STDIN_NAME};
+
+/// Numbers of events by the outcome of the evaluation.
+#[derive(Debug, Default, PartialEq)]
+struct Summary {
+    matched: usize,
+    not_matched: usize,
+    invalid: usize,
+}
+
+/// Evaluates the filter against an event given as a JSON object with values
+/// of fields.
+fn eval_event(scheme: &Scheme, filter: &Filter<'_>, event: &str) -> Result<bool, Error> {
+    let mut ctx = ExecutionContext::new(scheme);
+
+    let mut deserializer = Deserializer::from_str(event);
+    ctx.deserialize(&mut deserializer)?;
+    deserializer.end()?;
+
+    Ok(filter.execute(&ctx)?)
+}
+
+/// Evaluates the filter against each non-empty line and writes the outcome
+/// prefixed with the line number.
+fn eval_events<'s>(
+    scheme: &'s Scheme,
+    filter: &Filter<'s>,
+    name: &str,
+    events: impl BufRead,
+    mut out: impl Write,
+) -> Result<Summary, Error> {
+    let mut summary = Summary::default();
+
+    for (i, line) in events.lines().enumerate() {
+        let line = line.with_context(|_| format!("could not read {}", name))?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match eval_event(scheme, filter, &line) {
+            Ok(true) => {
+                summary.matched += 1;
+                writeln!(out, "{}: match", i + 1)?;
+            }
+            Ok(false) => {
+                summary.not_matched += 1;
+                writeln!(out, "{}: no match", i + 1)?;
+            }
+            Err(err) => {
+                summary.invalid += 1;
+                writeln!(out, "{}: invalid event: {}", i + 1, err)?;
+            }
+        }
+    }
+
+    writeln!(
+        out,
+        "{} matched, {} not matched, {} invalid",
+        summary.matched, summary.not_matched, summary.invalid
+    )?;
+
+    Ok(summary)
+}
+
+/// Runs `wirefilter eval`.
+///
+/// Events are read from the given file or the standard input as JSON lines,
+/// each being an object that maps field names to values.
+///
+/// Exits with 1 if any of the events is invalid.
+pub fn run(scheme: &Scheme, matches: &ArgMatches<'_>) -> Result<i32, Error> {
+    let filter_path = matches.value_of("filter").unwrap();
+    let filter_source = read_input(Some(filter_path))?;
+
+    let filter = scheme
+        .parse(&filter_source)
+        .map_err(|err| err_msg(format!("{}: {}", filter_path, err.to_string().trim_end())))?
+        .compile();
+
+    let summary = match matches.value_of("events") {
+        Some(path) => {
+            let file = File::open(path).with_context(|_| format!("could not open {}", path))?;
+            eval_events(scheme, &filter, path, BufReader::new(file), stdout())?
+        }
+        None => eval_events(scheme, &filter, STDIN_NAME, stdin().lock(), stdout())?,
+    };
+
+    Ok(if summary.invalid > 0 { 1 } else { 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_events() {
+        let mut scheme: Scheme = ::serde_json::from_str(
+            r#"{ "host": "Bytes", "port": "Int", "tags": { "Array": "Bytes" } }"#,
+        )
+        .unwrap();
+        scheme.add_std_functions().unwrap();
+
+        let filter = scheme
+            .parse(r#"host == "❤.example" and (port == 443 or tags[*] == "a")"#)
+            .unwrap()
+            .compile();
+
+        let events = indoc!(
+            r#"
+            {"host": "\u2764.example", "port": 443}
+            {"host": "example.org", "port": 443, "tags": ["a"]}
+
+            {"host": "❤.example", "port": 80, "tags": ["b", "a"]}
+            {"host": "❤.example", "port": "80"}
+            {"host": "❤.example", "foo": 1}
+            "#
+        );
+
+        let mut out = Vec::new();
+        let summary =
+            eval_events(&scheme, &filter, STDIN_NAME, events.as_bytes(), &mut out).unwrap();
+
+        assert_eq!(
+            summary,
+            Summary {
+                matched: 2,
+                not_matched: 1,
+                invalid: 2,
+            }
+        );
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            indoc!(
+                r#"
+                1: match
+                2: no match
+                4: match
+                5: invalid event: invalid type: string "80", expected i64 at line 1 column 36
+                6: invalid event: unknown field "foo" at line 1 column 29
+                2 matched, 1 not matched, 2 invalid
+                "#
+            )
+        );
+    }
+}
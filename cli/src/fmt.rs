@@ -0,0 +1,156 @@
+use clap::ArgMatches;
+use failure::{Error, ResultExt};
+use std::fs;
+use wirefilter::Scheme;
+use {read_input, STDIN_NAME};
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Formatted,
+    Changed,
+    Invalid,
+}
+
+/// Formats a single filter, returning the canonical form if it parses.
+///
+/// Reports a parse error for the input with the given name on the standard
+/// error otherwise.
+fn format_filter(scheme: &Scheme, name: &str, input: &str) -> Option<String> {
+    match scheme.parse(input) {
+        Ok(ast) => Some(format!("{:#}\n", ast)),
+        Err(err) => {
+            eprint!("{}: {}", name, err);
+            None
+        }
+    }
+}
+
+fn format_input(scheme: &Scheme, path: Option<&str>, check: bool) -> Result<Outcome, Error> {
+    let name = path.unwrap_or(STDIN_NAME);
+    let input = read_input(path)?;
+
+    let formatted = match format_filter(scheme, name, &input) {
+        Some(formatted) => formatted,
+        None => return Ok(Outcome::Invalid),
+    };
+
+    if formatted == input {
+        if path.is_none() && !check {
+            print!("{}", formatted);
+        }
+        return Ok(Outcome::Formatted);
+    }
+
+    if check {
+        println!("{}", name);
+    } else {
+        match path {
+            Some(path) => {
+                fs::write(path, formatted).with_context(|_| format!("could not write {}", path))?
+            }
+            None => print!("{}", formatted),
+        }
+    }
+
+    Ok(Outcome::Changed)
+}
+
+/// Runs `wirefilter fmt`.
+///
+/// Files are rewritten in place, while the standard input is formatted to
+/// the standard output. With `--check` nothing is written, and names of
+/// inputs that aren't formatted are listed instead.
+///
+/// Exits with 1 if any of the inputs is invalid, or isn't formatted in the
+/// `--check` mode.
+pub fn run(scheme: &Scheme, matches: &ArgMatches<'_>) -> Result<i32, Error> {
+    let check = matches.is_present("check");
+
+    let outcomes = match matches.values_of("files") {
+        Some(paths) => paths
+            .map(|path| format_input(scheme, Some(path), check))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => vec![format_input(scheme, None, check)?],
+    };
+
+    let failed = outcomes.iter().any(|outcome| match outcome {
+        Outcome::Formatted => false,
+        Outcome::Changed => check,
+        Outcome::Invalid => true,
+    });
+
+    Ok(if failed { 1 } else { 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    fn scheme() -> Scheme {
+        let mut scheme: Scheme =
+            ::serde_json::from_str(r#"{ "host": "Bytes", "port": "Int", "ssl": "Bool" }"#).unwrap();
+        scheme.add_std_functions().unwrap();
+        scheme
+    }
+
+    #[test]
+    fn test_format_filter() {
+        let scheme = scheme();
+
+        let formatted = format_filter(
+            &scheme,
+            STDIN_NAME,
+            r#"ssl&&port in {80 443}||!(lower(host)eq "a"^^host ~ "b")"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            formatted,
+            indoc!(
+                r#"
+                ssl and port in {80 443}
+                or not (
+                    lower(host) == "a"
+                    xor host matches "b"
+                )
+                "#
+            )
+        );
+
+        assert_eq!(
+            format_filter(&scheme, STDIN_NAME, &formatted),
+            Some(formatted)
+        );
+
+        assert_eq!(format_filter(&scheme, STDIN_NAME, "port == "), None);
+    }
+
+    #[test]
+    fn test_format_file() {
+        let scheme = scheme();
+        let path = temp_dir().join(format!("wirefilter-fmt-{}.txt", ::std::process::id()));
+        let path_str = path.to_str().unwrap();
+
+        fs::write(&path, "ssl or port == 80").unwrap();
+
+        assert_eq!(
+            format_input(&scheme, Some(path_str), true).unwrap(),
+            Outcome::Changed
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "ssl or port == 80");
+
+        assert_eq!(
+            format_input(&scheme, Some(path_str), false).unwrap(),
+            Outcome::Changed
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "ssl\nor port == 80\n");
+
+        assert_eq!(
+            format_input(&scheme, Some(path_str), true).unwrap(),
+            Outcome::Formatted
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+//! The `wirefilter` command-line tool for working with filters outside of
+//! the applications that embed the engine.
+//!
+//! All subcommands take a scheme as a JSON file that maps field names to
+//! their types, e.g. `{"http.host": "Bytes", "tcp.port": "Int"}`, and
+//! register the standard functions in it.
+
+#[macro_use]
+extern crate clap;
+extern crate failure;
+#[cfg(test)]
+#[macro_use]
+extern crate indoc;
+extern crate serde;
+extern crate serde_json;
+extern crate wirefilter;
+
+mod eval;
+mod fmt;
+
+use clap::{App, AppSettings, Arg, SubCommand};
+use failure::{Error, ResultExt};
+use serde_json::from_reader;
+use std::{
+    fs::File,
+    io::{stdin, BufReader, Read},
+    process::exit,
+};
+use wirefilter::Scheme;
+
+/// The name under which the standard input is reported in messages.
+const STDIN_NAME: &str = "<stdin>";
+
+fn load_scheme(path: &str) -> Result<Scheme, Error> {
+    let file = File::open(path).with_context(|_| format!("could not open scheme {}", path))?;
+
+    let mut scheme: Scheme = from_reader(BufReader::new(file))
+        .with_context(|_| format!("could not parse scheme {}", path))?;
+
+    scheme.add_std_functions()?;
+
+    Ok(scheme)
+}
+
+/// Reads the whole file, or the standard input if the path is `None`.
+fn read_input(path: Option<&str>) -> Result<String, Error> {
+    let mut input = String::new();
+
+    match path {
+        Some(path) => {
+            File::open(path)
+                .and_then(|mut file| file.read_to_string(&mut input))
+                .with_context(|_| format!("could not read {}", path))?;
+        }
+        None => {
+            stdin()
+                .read_to_string(&mut input)
+                .context("could not read the standard input")?;
+        }
+    }
+
+    Ok(input)
+}
+
+fn scheme_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("scheme")
+        .long("scheme")
+        .value_name("FILE")
+        .help("JSON file with the field types")
+        .required(true)
+}
+
+fn run() -> Result<i32, Error> {
+    let matches = App::new("wirefilter")
+        .version(crate_version!())
+        .about("Tools for Wireshark-like filters")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("Formats filters in the canonical multi-line layout")
+                .arg(scheme_arg())
+                .arg(
+                    Arg::with_name("check")
+                        .long("check")
+                        .help("Lists unformatted inputs instead of rewriting them"),
+                )
+                .arg(
+                    Arg::with_name("files")
+                        .value_name("FILE")
+                        .help("Files to format in place, or the standard input if none")
+                        .multiple(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("eval")
+                .about("Evaluates a filter against events in JSON lines")
+                .arg(scheme_arg())
+                .arg(
+                    Arg::with_name("filter")
+                        .long("filter")
+                        .value_name("FILE")
+                        .help("File with the filter")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("events")
+                        .value_name("FILE")
+                        .help("File with a JSON object per line, or the standard input if none"),
+                ),
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        ("fmt", Some(matches)) => {
+            let scheme = load_scheme(matches.value_of("scheme").unwrap())?;
+            fmt::run(&scheme, matches)
+        }
+        ("eval", Some(matches)) => {
+            let scheme = load_scheme(matches.value_of("scheme").unwrap())?;
+            eval::run(&scheme, matches)
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn main() {
+    match run() {
+        Ok(code) => exit(code),
+        Err(err) => {
+            eprint!("error: {}", err);
+            for cause in err.iter_causes() {
+                eprint!(": {}", cause);
+            }
+            eprintln!();
+            exit(2);
+        }
+    }
+}
@@ -11,8 +11,12 @@ extern crate wirefilter;
 use criterion::{
     criterion_group, criterion_main, Bencher, Benchmark, Criterion, ParameterizedBenchmark,
 };
-use std::{fmt::Debug, net::IpAddr};
-use wirefilter::{ExecutionContext, GetType, LhsValue, Scheme};
+use std::{
+    fmt::Debug,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ops::RangeInclusive,
+};
+use wirefilter::{ExecutionContext, GetType, IpTrie, LhsValue, RangeSet, Scheme, TrieKey};
 
 struct FieldBench<'a, T: 'static> {
     field: &'static str,
@@ -42,21 +46,27 @@ impl<'a, T: 'static + Copy + Debug + Into<LhsValue<'static>>> FieldBench<'a, T>
                 filter
             };
 
+            let parsing_ty = ty.clone();
             c.bench(
                 "parsing",
                 Benchmark::new(name, move |b: &mut Bencher| {
                     let mut scheme = Scheme::default();
-                    scheme.add_field(field.to_owned(), ty).unwrap();
+                    scheme
+                        .add_field(field.to_owned(), parsing_ty.clone())
+                        .unwrap();
 
                     b.iter(|| scheme.parse(filter).unwrap());
                 }),
             );
 
+            let compilation_ty = ty.clone();
             c.bench(
                 "compilation",
                 Benchmark::new(name, move |b: &mut Bencher| {
                     let mut scheme = Scheme::default();
-                    scheme.add_field(field.to_owned(), ty).unwrap();
+                    scheme
+                        .add_field(field.to_owned(), compilation_ty.clone())
+                        .unwrap();
 
                     let filter = scheme.parse(filter).unwrap();
 
@@ -64,13 +74,16 @@ impl<'a, T: 'static + Copy + Debug + Into<LhsValue<'static>>> FieldBench<'a, T>
                 }),
             );
 
+            let execution_ty = ty.clone();
             c.bench(
                 "execution",
                 ParameterizedBenchmark::new(
                     name,
                     move |b: &mut Bencher, value: &T| {
                         let mut scheme = Scheme::default();
-                        scheme.add_field(field.to_owned(), ty).unwrap();
+                        scheme
+                            .add_field(field.to_owned(), execution_ty.clone())
+                            .unwrap();
 
                         let filter = scheme.parse(filter).unwrap();
 
@@ -146,6 +159,85 @@ fn bench_string_matches(c: &mut Criterion) {
     }.run(c)
 }
 
+/// A xorshift generator, so that the same addresses are used on each run.
+struct Addresses(u128);
+
+impl Iterator for Addresses {
+    type Item = u128;
+
+    fn next(&mut self) -> Option<u128> {
+        self.0 ^= self.0 << 35;
+        self.0 ^= self.0 >> 59;
+        self.0 ^= self.0 << 21;
+        Some(self.0)
+    }
+}
+
+/// Compares lookups in sets of different sizes of random CIDRs with the
+/// same prefix length, like the ones used for large lists of IPs.
+///
+/// Lists switch from a `RangeSet` to an `IpTrie` above `IP_TRIE_THRESHOLD`
+/// ranges, so sizes around it are measured as well.
+fn bench_ip_set<T: 'static + TrieKey + Debug>(
+    c: &mut Criterion,
+    name: &str,
+    prefix_len: u32,
+    addr: fn(u128) -> T,
+) {
+    let bits = T::BITS;
+    let host_bits = bits - prefix_len;
+
+    let ranges = move |size: usize| -> Vec<RangeInclusive<T>> {
+        Addresses(0x2545_F491_4F6C_DD1D)
+            .take(size)
+            .map(|x| {
+                let start = x >> (128 - bits) >> host_bits << host_bits;
+                addr(start)..=addr(start | ((1 << host_bits) - 1))
+            })
+            .collect()
+    };
+
+    // half of the addresses are in the set and half are random
+    let lookups = move |ranges: &[RangeInclusive<T>]| -> Vec<T> {
+        let step = (ranges.len() / 512).max(1);
+        ranges
+            .iter()
+            .step_by(step)
+            .map(|range| *range.end())
+            .chain(
+                Addresses(0x9E37_79B9_7F4A_7C15)
+                    .take(512)
+                    .map(|x| addr(x >> (128 - bits))),
+            )
+            .collect()
+    };
+
+    c.bench(
+        name,
+        ParameterizedBenchmark::new(
+            "RangeSet",
+            move |b: &mut Bencher, &size: &usize| {
+                let ranges = ranges(size);
+                let lookups = lookups(&ranges);
+                let set = RangeSet::from(ranges);
+                b.iter(|| lookups.iter().filter(|addr| set.contains(*addr)).count());
+            },
+            vec![64, 128, 256, 512, 1_024, 4_096, 100_000, 1_000_000],
+        )
+        .with_function("IpTrie", move |b: &mut Bencher, &size: &usize| {
+            let ranges = ranges(size);
+            let lookups = lookups(&ranges);
+            let set = IpTrie::from(ranges);
+            b.iter(|| lookups.iter().filter(|addr| set.contains(*addr)).count());
+        }),
+    );
+}
+
+fn bench_ip_sets(c: &mut Criterion) {
+    bench_ip_set(c, "ip_set_v4", 24, |x| Ipv4Addr::from(x as u32));
+    bench_ip_set(c, "ip_set_v6", 48, Ipv6Addr::from);
+}
+
 criterion_group! {
     name = field_benchmarks;
     config = Criterion::default();
@@ -154,6 +246,7 @@ criterion_group! {
         bench_int_comparisons,
         bench_string_comparisons,
         bench_string_matches,
+        bench_ip_sets,
 }
 
 criterion_main!(field_benchmarks);
@@ -1,7 +1,15 @@
-use super::{simple_expr::SimpleExpr, CompiledExpr, Expr};
+use super::{
+    simple_expr::SimpleExpr,
+    trace::{ExecutionTrace, TraceNode},
+    CompiledExpr, Expr,
+};
+use deserialize::{DeserializeError, DeserializeErrorKind, FromRawWith, JsonPath, RawValue};
+use execution_context::ExecutionContext;
+use filter_set::{SetExpr, SetLeaves};
 use lex::{skip_space, Lex, LexResult, LexWith};
 use scheme::{Field, Scheme};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
 
 lex_enum!(#[derive(PartialOrd, Ord)] CombiningOp {
     "or" | "||" => Or,
@@ -83,6 +91,154 @@ impl<'i, 's> LexWith<'i, &'s Scheme> for CombinedExpr<'s> {
     }
 }
 
+impl<'s> FromRawWith<&'s Scheme> for CombinedExpr<'s> {
+    fn from_raw_with(
+        value: &RawValue,
+        path: &JsonPath<'_>,
+        scheme: &'s Scheme,
+    ) -> Result<Self, DeserializeError> {
+        let object = value.as_object(path)?;
+
+        let raw_items = match object.get("items") {
+            Some(items) => items,
+            None => {
+                return Ok(CombinedExpr::Simple(SimpleExpr::from_raw_with(
+                    value, path, scheme,
+                )?))
+            }
+        };
+
+        object.check_keys(&["op", "items"], path)?;
+
+        let op = object.require("op", path)?.as_enum(&path.key("op"))?;
+
+        let items_path = path.key("items");
+        let raw_items = raw_items.as_array(&items_path)?;
+
+        if raw_items.is_empty() {
+            return Err(items_path.error(DeserializeErrorKind::Expected("a non-empty array")));
+        }
+
+        let items = raw_items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let item = CombinedExpr::from_raw_with(item, &items_path.index(i), scheme)?;
+
+                Ok(match item {
+                    // parentheses are not serialized, so restore them where
+                    // the parser would need them
+                    CombinedExpr::Combining { op: item_op, .. } if item_op <= op => {
+                        CombinedExpr::Simple(SimpleExpr::Parenthesized(Box::new(item)))
+                    }
+                    item => item,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(CombinedExpr::Combining { op, items })
+    }
+}
+
+impl CombiningOp {
+    fn as_str(self) -> &'static str {
+        match self {
+            CombiningOp::Or => "or",
+            CombiningOp::Xor => "xor",
+            CombiningOp::And => "and",
+        }
+    }
+}
+
+const INDENT: &str = "    ";
+
+fn write_newline(f: &mut Formatter<'_>, indent: usize) -> fmt::Result {
+    f.write_str("\n")?;
+    for _ in 0..indent {
+        f.write_str(INDENT)?;
+    }
+    Ok(())
+}
+
+/// Writes a combination in parentheses, with its items on separate indented
+/// lines.
+pub(crate) fn fmt_multiline_parens(
+    expr: &CombinedExpr<'_>,
+    f: &mut Formatter<'_>,
+    indent: usize,
+) -> fmt::Result {
+    f.write_str("(")?;
+    write_newline(f, indent + 1)?;
+    expr.fmt_multiline(f, indent + 1)?;
+    write_newline(f, indent)?;
+    f.write_str(")")
+}
+
+impl<'s> CombinedExpr<'s> {
+    /// Writes the expression with each item of the combination on a separate
+    /// line starting with the operator.
+    ///
+    /// Parenthesized combinations are laid out in the same way with an extra
+    /// indentation, while nested combinations of a higher precedence are kept
+    /// on the line of their first item.
+    pub(crate) fn fmt_multiline(&self, f: &mut Formatter<'_>, indent: usize) -> fmt::Result {
+        self.fmt_items(f, indent, true)
+    }
+
+    fn fmt_items(&self, f: &mut Formatter<'_>, indent: usize, break_lines: bool) -> fmt::Result {
+        match self {
+            CombinedExpr::Simple(op) => op.fmt_multiline(f, indent),
+            CombinedExpr::Combining { op, items } => {
+                for (i, item) in items.iter().enumerate() {
+                    if i != 0 {
+                        if break_lines {
+                            write_newline(f, indent)?;
+                        } else {
+                            f.write_str(" ")?;
+                        }
+                        write!(f, "{} ", op.as_str())?;
+                    }
+
+                    match item {
+                        CombinedExpr::Combining { op: item_op, .. } if item_op <= op => {
+                            fmt_multiline_parens(item, f, indent)?
+                        }
+                        _ => item.fmt_items(f, indent, false)?,
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<'s> Display for CombinedExpr<'s> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CombinedExpr::Simple(op) => Display::fmt(op, f),
+            CombinedExpr::Combining { op, items } => {
+                for (i, item) in items.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " {} ", op.as_str())?;
+                    }
+
+                    match item {
+                        // parsed filters never have such items, but they
+                        // need parentheses to preserve the precedence
+                        CombinedExpr::Combining { op: item_op, .. } if item_op <= op => {
+                            write!(f, "({})", item)?
+                        }
+                        _ => Display::fmt(item, f)?,
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
 impl<'s> Expr<'s> for CombinedExpr<'s> {
     fn uses(&self, field: Field<'s>) -> bool {
         match self {
@@ -91,6 +247,61 @@ impl<'s> Expr<'s> for CombinedExpr<'s> {
         }
     }
 
+    fn lower(self, leaves: &mut SetLeaves<'s>) -> SetExpr {
+        match self {
+            CombinedExpr::Simple(op) => op.lower(leaves),
+            CombinedExpr::Combining { op, items } => {
+                let items = items
+                    .into_iter()
+                    .map(|item| item.lower(leaves))
+                    .collect::<Vec<_>>()
+                    .into_boxed_slice();
+
+                match op {
+                    CombiningOp::And => SetExpr::And(items),
+                    CombiningOp::Or => SetExpr::Or(items),
+                    CombiningOp::Xor => SetExpr::Xor(items),
+                }
+            }
+        }
+    }
+
+    fn trace<'a>(&'a self, ctx: Option<&'a ExecutionContext<'_>>) -> ExecutionTrace<'a> {
+        match self {
+            CombinedExpr::Simple(op) => op.trace(ctx),
+            CombinedExpr::Combining { op, items } => {
+                let mut ctx = ctx;
+                let mut result = ctx.map(|_| *op == CombiningOp::And);
+
+                let items = items
+                    .iter()
+                    .map(|item| {
+                        let item = item.trace(ctx);
+
+                        if let (Some(acc), Some(item_result)) = (result, item.result()) {
+                            result = Some(match op {
+                                CombiningOp::And => acc && item_result,
+                                CombiningOp::Or => acc || item_result,
+                                CombiningOp::Xor => acc ^ item_result,
+                            });
+
+                            // the rest of the items is skipped as soon as
+                            // the result is known
+                            match (op, item_result) {
+                                (CombiningOp::And, false) | (CombiningOp::Or, true) => ctx = None,
+                                _ => {}
+                            }
+                        }
+
+                        item
+                    })
+                    .collect();
+
+                ExecutionTrace::new(TraceNode::Combining { op: *op, items }, result)
+            }
+        }
+    }
+
     fn compile(self) -> CompiledExpr<'s> {
         match self {
             CombinedExpr::Simple(op) => op.compile(),
@@ -103,15 +314,15 @@ impl<'s> Expr<'s> for CombinedExpr<'s> {
 
                 match op {
                     CombiningOp::And => {
-                        CompiledExpr::new(move |ctx| items.iter().all(|item| item.execute(ctx)))
+                        CompiledExpr::new(move |ctx| items.iter().all(|item| item.execute_in(ctx)))
                     }
                     CombiningOp::Or => {
-                        CompiledExpr::new(move |ctx| items.iter().any(|item| item.execute(ctx)))
+                        CompiledExpr::new(move |ctx| items.iter().any(|item| item.execute_in(ctx)))
                     }
                     CombiningOp::Xor => CompiledExpr::new(move |ctx| {
                         items
                             .iter()
-                            .fold(false, |acc, item| acc ^ item.execute(ctx))
+                            .fold(false, |acc, item| acc ^ item.execute_in(ctx))
                     }),
                 }
             }
@@ -1,16 +1,26 @@
-use super::{CompiledExpr, Expr};
-use fnv::FnvBuildHasher;
+use super::{
+    index_expr::IndexExpr,
+    trace::{ExecutionTrace, TraceNode},
+    CompiledExpr, Expr,
+};
+use deserialize::{
+    DeserializeError, DeserializeErrorKind, FromRaw, FromRawWith, JsonPath, RawValue,
+};
+use execution_context::ExecutionContext;
+use filter_set::{SetExpr, SetLeaves};
 use heap_searcher::HeapSearcher;
-use indexmap::IndexSet;
 use lex::{skip_space, span, Lex, LexErrorKind, LexResult, LexWith};
+use lists::ValueSet;
 use memmem::Searcher;
-use range_set::RangeSet;
-use rhs_types::{Bytes, ExplicitIpRange, Regex};
-use scheme::{Field, Scheme};
-use serde::{Serialize, Serializer};
-use std::{cmp::Ordering, net::IpAddr};
+use rhs_types::{Bytes, Regex};
+use scheme::{Field, ListRef, Scheme};
+use serde::{Deserialize, Serialize, Serializer};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
+};
 use strict_partial_ord::StrictPartialOrd;
-use types::{GetType, LhsValue, RhsValue, RhsValues, Type};
+use types::{GetType, LhsValue, RhsValue, RhsValues, Type, TypeMismatchError};
 
 const LESS: u8 = 0b001;
 const GREATER: u8 = 0b010;
@@ -63,7 +73,7 @@ lex_enum!(ComparisonOp {
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize)]
 #[serde(untagged)]
-enum FieldOp {
+enum FieldOp<'s> {
     #[serde(serialize_with = "serialize_is_true")]
     IsTrue,
 
@@ -74,7 +84,7 @@ enum FieldOp {
 
     Int {
         op: IntOp,
-        rhs: i32,
+        rhs: i64,
     },
 
     #[serde(serialize_with = "serialize_contains")]
@@ -85,6 +95,9 @@ enum FieldOp {
 
     #[serde(serialize_with = "serialize_one_of")]
     OneOf(RhsValues),
+
+    #[serde(serialize_with = "serialize_in_list")]
+    InList(ListRef<'s>),
 }
 
 fn serialize_op_rhs<T: Serialize, S: Serializer>(
@@ -120,20 +133,39 @@ fn serialize_one_of<S: Serializer>(rhs: &RhsValues, ser: S) -> Result<S::Ok, S::
     serialize_op_rhs("OneOf", rhs, ser)
 }
 
+fn serialize_in_list<S: Serializer>(rhs: &ListRef<'_>, ser: S) -> Result<S::Ok, S::Error> {
+    serialize_op_rhs("InList", rhs, ser)
+}
+
+/// Checks that a list can be used with a field of the given type.
+fn check_list_type(list: ListRef<'_>, field_type: &Type) -> Result<(), LexErrorKind> {
+    let list_type = list.get_type();
+
+    if list_type == *field_type {
+        Ok(())
+    } else {
+        Err(LexErrorKind::InvalidListType(TypeMismatchError {
+            expected: field_type.clone(),
+            actual: list_type,
+        }))
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize)]
 pub struct FieldExpr<'s> {
-    field: Field<'s>,
+    #[serde(flatten)]
+    lhs: IndexExpr<'s>,
 
     #[serde(flatten)]
-    op: FieldOp,
+    op: FieldOp<'s>,
 }
 
 impl<'i, 's> LexWith<'i, &'s Scheme> for FieldExpr<'s> {
     fn lex_with(input: &'i str, scheme: &'s Scheme) -> LexResult<'i, Self> {
         let initial_input = input;
 
-        let (field, input) = Field::lex_with(input, scheme)?;
-        let field_type = field.get_type();
+        let (lhs, input) = IndexExpr::lex_with(input, scheme)?;
+        let field_type = lhs.get_type();
 
         let (op, input) = if field_type == Type::Bool {
             (FieldOp::IsTrue, input)
@@ -144,17 +176,32 @@ impl<'i, 's> LexWith<'i, &'s Scheme> for FieldExpr<'s> {
 
             let input = skip_space(input);
 
-            match (field_type, op) {
+            let unsupported_op = |field_type| {
+                (
+                    LexErrorKind::UnsupportedOp { field_type },
+                    span(initial_input, input_after_op),
+                )
+            };
+
+            match (&field_type, op) {
+                (Type::Array(_), _) | (Type::Map(_), _) => {
+                    return Err(unsupported_op(field_type.clone()));
+                }
+                (_, ComparisonOp::In) if input.starts_with('$') => {
+                    let (list, rest) = ListRef::lex_with(input, scheme)?;
+                    check_list_type(list, &field_type).map_err(|kind| (kind, span(input, rest)))?;
+                    (FieldOp::InList(list), rest)
+                }
                 (_, ComparisonOp::In) => {
-                    let (rhs, input) = RhsValues::lex_with(input, field_type)?;
+                    let (rhs, input) = RhsValues::lex_with(input, &field_type)?;
                     (FieldOp::OneOf(rhs), input)
                 }
                 (_, ComparisonOp::Ordering(op)) => {
-                    let (rhs, input) = RhsValue::lex_with(input, field_type)?;
+                    let (rhs, input) = RhsValue::lex_with(input, &field_type)?;
                     (FieldOp::Ordering { op, rhs }, input)
                 }
                 (Type::Int, ComparisonOp::Int(op)) => {
-                    let (rhs, input) = i32::lex(input)?;
+                    let (rhs, input) = i64::lex(input)?;
                     (FieldOp::Int { op, rhs }, input)
                 }
                 (Type::Bytes, ComparisonOp::Bytes(op)) => match op {
@@ -168,84 +215,216 @@ impl<'i, 's> LexWith<'i, &'s Scheme> for FieldExpr<'s> {
                     }
                 },
                 _ => {
-                    return Err((
-                        LexErrorKind::UnsupportedOp { field_type },
-                        span(initial_input, input_after_op),
-                    ));
+                    return Err(unsupported_op(field_type.clone()));
+                }
+            }
+        };
+
+        Ok((FieldExpr { lhs, op }, input))
+    }
+}
+
+impl<'s> FromRawWith<&'s Scheme> for FieldExpr<'s> {
+    fn from_raw_with(
+        value: &RawValue,
+        path: &JsonPath<'_>,
+        scheme: &'s Scheme,
+    ) -> Result<Self, DeserializeError> {
+        let object = value.as_object(path)?;
+        object.check_keys(
+            &["field", "function", "indexes", "quantifier", "op", "rhs"],
+            path,
+        )?;
+
+        let lhs = IndexExpr::from_raw_with(value, path, scheme)?;
+        let field_type = lhs.get_type();
+
+        let op_path = path.key("op");
+        let op = object.require("op", path)?;
+        let op_name = op.as_str(&op_path)?;
+
+        let unsupported_op = || {
+            op_path.error(LexErrorKind::UnsupportedOp {
+                field_type: field_type.clone(),
+            })
+        };
+
+        let op = if op_name == "IsTrue" {
+            if field_type != Type::Bool {
+                return Err(unsupported_op());
+            }
+
+            if object.get("rhs").is_some() {
+                return Err(path
+                    .key("rhs")
+                    .error(DeserializeErrorKind::UnknownProperty("rhs".to_owned())));
+            }
+
+            FieldOp::IsTrue
+        } else {
+            // names of operations as they are serialized
+            let op = match op_name {
+                "OneOf" | "InList" => ComparisonOp::In,
+                "BitwiseAnd" => ComparisonOp::Int(IntOp::BitwiseAnd),
+                "Contains" => ComparisonOp::Bytes(BytesOp::Contains),
+                "Matches" => ComparisonOp::Bytes(BytesOp::Matches),
+                _ => ComparisonOp::Ordering(op.as_enum(&op_path)?),
+            };
+
+            let rhs_path = path.key("rhs");
+            let rhs = object.require("rhs", path)?;
+
+            match (&field_type, op) {
+                (Type::Bool, _) | (Type::Array(_), _) | (Type::Map(_), _) => {
+                    return Err(unsupported_op());
+                }
+                (_, ComparisonOp::In) if op_name == "InList" => {
+                    let list = scheme
+                        .get_list_ref(rhs.as_str(&rhs_path)?)
+                        .map_err(|err| rhs_path.error(LexErrorKind::UnknownList(err)))?;
+                    check_list_type(list, &field_type).map_err(|kind| rhs_path.error(kind))?;
+                    FieldOp::InList(list)
+                }
+                (_, ComparisonOp::In) => {
+                    FieldOp::OneOf(RhsValues::from_raw_with(rhs, &rhs_path, &field_type)?)
+                }
+                (_, ComparisonOp::Ordering(op)) => FieldOp::Ordering {
+                    op,
+                    rhs: RhsValue::from_raw_with(rhs, &rhs_path, &field_type)?,
+                },
+                (Type::Int, ComparisonOp::Int(op)) => FieldOp::Int {
+                    op,
+                    rhs: i64::from_raw(rhs, &rhs_path)?,
+                },
+                (Type::Bytes, ComparisonOp::Bytes(BytesOp::Contains)) => {
+                    FieldOp::Contains(Bytes::from_raw(rhs, &rhs_path)?)
+                }
+                (Type::Bytes, ComparisonOp::Bytes(BytesOp::Matches)) => {
+                    FieldOp::Matches(Regex::from_raw(rhs, &rhs_path)?)
+                }
+                _ => {
+                    return Err(unsupported_op());
                 }
             }
         };
 
-        Ok((FieldExpr { field, op }, input))
+        Ok(FieldExpr { lhs, op })
+    }
+}
+
+impl<'s> Display for FieldExpr<'s> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.lhs, f)?;
+
+        match &self.op {
+            FieldOp::IsTrue => Ok(()),
+            FieldOp::Ordering { op, rhs } => {
+                let op = match op {
+                    OrderingOp::Equal => "==",
+                    OrderingOp::NotEqual => "!=",
+                    OrderingOp::GreaterThanEqual => ">=",
+                    OrderingOp::LessThanEqual => "<=",
+                    OrderingOp::GreaterThan => ">",
+                    OrderingOp::LessThan => "<",
+                };
+                write!(f, " {} {}", op, rhs)
+            }
+            FieldOp::Int {
+                op: IntOp::BitwiseAnd,
+                rhs,
+            } => write!(f, " & {}", rhs),
+            FieldOp::Contains(bytes) => write!(f, " contains {}", bytes),
+            FieldOp::Matches(regex) => write!(f, " matches {}", regex),
+            FieldOp::OneOf(values) => write!(f, " in {}", values),
+            FieldOp::InList(list) => write!(f, " in {}", list),
+        }
+    }
+}
+
+impl<'s> FieldExpr<'s> {
+    /// Returns the LHS and the needle of a `contains` comparison of a single
+    /// value, so that needles of several expressions can be searched for at
+    /// once.
+    pub(crate) fn as_contains(&self) -> Option<(&IndexExpr<'s>, &[u8])> {
+        match &self.op {
+            FieldOp::Contains(bytes) if self.lhs.is_single_value() => Some((&self.lhs, bytes)),
+            _ => None,
+        }
+    }
+
+    /// Returns the LHS and the regex of a `matches` comparison of a single
+    /// value, so that regexes of several expressions can be matched at once.
+    #[cfg(feature = "regex")]
+    pub(crate) fn as_matches(&self) -> Option<(&IndexExpr<'s>, &Regex)> {
+        match &self.op {
+            FieldOp::Matches(regex) if self.lhs.is_single_value() => Some((&self.lhs, regex)),
+            _ => None,
+        }
     }
 }
 
 impl<'s> Expr<'s> for FieldExpr<'s> {
     fn uses(&self, field: Field<'s>) -> bool {
-        self.field == field
+        self.lhs.uses(field)
+    }
+
+    fn lower(self, leaves: &mut SetLeaves<'s>) -> SetExpr {
+        SetExpr::Leaf(leaves.insert(self))
+    }
+
+    fn trace<'a>(&'a self, ctx: Option<&'a ExecutionContext<'_>>) -> ExecutionTrace<'a> {
+        let (value, result) = match ctx {
+            Some(ctx) => (
+                self.lhs.execute_until_each(ctx),
+                // compile a copy to get exactly the same semantics as a
+                // filter would have
+                Some(self.clone().compile().execute(ctx)),
+            ),
+            None => (None, None),
+        };
+
+        ExecutionTrace::new(TraceNode::Field { expr: self, value }, result)
     }
 
     fn compile(self) -> CompiledExpr<'s> {
-        let field = self.field;
+        let FieldExpr { lhs, op } = self;
 
-        macro_rules! cast_field {
-            ($ctx:ident, $ty:ident) => {
-                match $ctx.get_field_value_unchecked(field) {
+        macro_rules! cast_value {
+            ($value:ident, $ty:ident) => {
+                match $value {
                     LhsValue::$ty(value) => value,
                     _ => unreachable!(),
                 }
             };
         }
 
-        match self.op {
-            FieldOp::IsTrue => CompiledExpr::new(move |ctx| *cast_field!(ctx, Bool)),
-            FieldOp::Ordering { op, rhs } => CompiledExpr::new(move |ctx| {
-                op.matches_opt(
-                    ctx.get_field_value_unchecked(field)
-                        .strict_partial_cmp(&rhs),
-                )
-            }),
+        match op {
+            FieldOp::IsTrue => lhs.compile_with(|x, _| *cast_value!(x, Bool)),
+            FieldOp::Ordering { op, rhs } => {
+                lhs.compile_with(move |x, _| op.matches_opt(x.strict_partial_cmp(&rhs)))
+            }
             FieldOp::Int {
                 op: IntOp::BitwiseAnd,
                 rhs,
-            } => CompiledExpr::new(move |ctx| cast_field!(ctx, Int) & rhs != 0),
+            } => lhs.compile_with(move |x, _| cast_value!(x, Int) & rhs != 0),
             FieldOp::Contains(bytes) => {
                 let searcher = HeapSearcher::from(bytes);
 
-                CompiledExpr::new(move |ctx| searcher.search_in(cast_field!(ctx, Bytes)).is_some())
+                lhs.compile_with(move |x, _| searcher.search_in(cast_value!(x, Bytes)).is_some())
             }
             FieldOp::Matches(regex) => {
-                CompiledExpr::new(move |ctx| regex.is_match(cast_field!(ctx, Bytes)))
-            }
-            FieldOp::OneOf(values) => match values {
-                RhsValues::Ip(ranges) => {
-                    let mut v4 = Vec::new();
-                    let mut v6 = Vec::new();
-                    for range in ranges {
-                        match range.clone().into() {
-                            ExplicitIpRange::V4(range) => v4.push(range),
-                            ExplicitIpRange::V6(range) => v6.push(range),
-                        }
-                    }
-                    let v4 = RangeSet::from(v4);
-                    let v6 = RangeSet::from(v6);
-                    CompiledExpr::new(move |ctx| match cast_field!(ctx, Ip) {
-                        IpAddr::V4(addr) => v4.contains(addr),
-                        IpAddr::V6(addr) => v6.contains(addr),
-                    })
-                }
-                RhsValues::Int(values) => {
-                    let values: RangeSet<_> = values.iter().cloned().collect();
-                    CompiledExpr::new(move |ctx| values.contains(cast_field!(ctx, Int)))
-                }
-                RhsValues::Bytes(values) => {
-                    let values: IndexSet<Box<[u8]>, FnvBuildHasher> =
-                        values.into_iter().map(|value| value.into()).collect();
+                lhs.compile_with(move |x, _| regex.is_match(cast_value!(x, Bytes)))
+            }
+            FieldOp::OneOf(values) => {
+                let values = ValueSet::from(values);
 
-                    CompiledExpr::new(move |ctx| values.contains(cast_field!(ctx, Bytes) as &[u8]))
-                }
-                RhsValues::Bool(_) => unreachable!(),
-            },
+                lhs.compile_with(move |x, _| values.contains(x))
+            }
+            FieldOp::InList(list) => {
+                let list = list.get();
+
+                lhs.compile_with(move |x, ctx| list.contains(x, ctx.lists()))
+            }
         }
     }
 }
@@ -255,21 +434,100 @@ mod tests {
     use super::*;
     use cidr::{Cidr, IpCidr};
     use execution_context::ExecutionContext;
+    use functions::{Function, FunctionArgKind, FunctionArgs, FunctionImpl, FunctionParam};
     use lazy_static::lazy_static;
-    use rhs_types::IpRange;
+    use lex::complete;
+    use lhs_types::{Array, Map};
+    use lists::List;
+    use rhs_types::{Duration, ExplicitIpRange, Float, IpRange, Mac, MacRange, Timestamp};
+    use scheme::UnknownListError;
     use std::net::IpAddr;
 
+    fn len_function<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+        match args.next()? {
+            LhsValue::Bytes(bytes) => Some(LhsValue::Int(bytes.len() as i64)),
+            _ => unreachable!(),
+        }
+    }
+
+    // Declared to return an integer, but returns its argument as-is.
+    fn broken_len_function<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+        args.next()
+    }
+
     lazy_static! {
-        static ref SCHEME: Scheme = Scheme! {
-            http.host: Bytes,
-            ip.addr: Ip,
-            ssl: Bool,
-            tcp.port: Int,
+        static ref SCHEME: Scheme = {
+            let mut scheme = Scheme! {
+                cache.ttl: Duration,
+                eth.src: Mac,
+                http.cookies: Array(Bytes),
+                http.content_length: Int,
+                http.headers: Map(Bytes),
+                http.host: Bytes,
+                http.timestamp: Timestamp,
+                ip.addr: Ip,
+                ssl: Bool,
+                tcp.port: Int,
+                tls.score: Float,
+            };
+            scheme
+                .add_function(
+                    "len".into(),
+                    Function {
+                        params: vec![FunctionParam {
+                            arg_kind: FunctionArgKind::Field,
+                            val_type: Type::Bytes,
+                        }],
+                        opt_params: vec![],
+                        return_type: Type::Int,
+                        implementation: FunctionImpl::new(len_function),
+                    },
+                )
+                .unwrap();
+            scheme
+                .add_function(
+                    "broken_len".into(),
+                    Function {
+                        params: vec![FunctionParam {
+                            arg_kind: FunctionArgKind::Field,
+                            val_type: Type::Bytes,
+                        }],
+                        opt_params: vec![],
+                        return_type: Type::Int,
+                        implementation: FunctionImpl::new(broken_len_function),
+                    },
+                )
+                .unwrap();
+            scheme
+                .add_list(
+                    "blocklist".into(),
+                    List::parse(Type::Ip, "{ 10.0.0.0/8 ::1 }").unwrap(),
+                )
+                .unwrap();
+            scheme
+                .add_list(
+                    "hosts".into(),
+                    List::parse(Type::Bytes, r#"{ "example.org" }"#).unwrap(),
+                )
+                .unwrap();
+            scheme
         };
     }
 
-    fn field(name: &'static str) -> Field<'static> {
-        SCHEME.get_field_index(name).unwrap()
+    fn index_expr(input: &'static str) -> IndexExpr<'static> {
+        complete(IndexExpr::lex_with(input, &SCHEME)).unwrap()
+    }
+
+    fn cookies(values: &[&'static str]) -> Array<'static> {
+        let mut array = Array::new(Type::Bytes);
+        for &value in values {
+            array.push(value).unwrap();
+        }
+        array
+    }
+
+    fn field(name: &'static str) -> IndexExpr<'static> {
+        SCHEME.get_field(name).unwrap().into()
     }
 
     #[test]
@@ -277,7 +535,7 @@ mod tests {
         let expr = assert_ok!(
             FieldExpr::lex_with("ssl", &SCHEME),
             FieldExpr {
-                field: field("ssl"),
+                lhs: field("ssl"),
                 op: FieldOp::IsTrue
             }
         );
@@ -305,7 +563,7 @@ mod tests {
         let expr = assert_ok!(
             FieldExpr::lex_with("ip.addr <= 10:20:30:40:50:60:70:80", &SCHEME),
             FieldExpr {
-                field: field("ip.addr"),
+                lhs: field("ip.addr"),
                 op: FieldOp::Ordering {
                     op: OrderingOp::LessThanEqual,
                     rhs: RhsValue::Ip(IpAddr::from([
@@ -357,7 +615,7 @@ mod tests {
             let expr = assert_ok!(
                 FieldExpr::lex_with("http.host >= 10:20:30:40:50:60:70:80", &SCHEME),
                 FieldExpr {
-                    field: field("http.host"),
+                    lhs: field("http.host"),
                     op: FieldOp::Ordering {
                         op: OrderingOp::GreaterThanEqual,
                         rhs: RhsValue::Bytes(
@@ -382,7 +640,7 @@ mod tests {
             let expr = assert_ok!(
                 FieldExpr::lex_with(r#"http.host < 12"#, &SCHEME),
                 FieldExpr {
-                    field: field("http.host"),
+                    lhs: field("http.host"),
                     op: FieldOp::Ordering {
                         op: OrderingOp::LessThan,
                         rhs: RhsValue::Bytes(vec![0x12].into()),
@@ -403,7 +661,7 @@ mod tests {
         let expr = assert_ok!(
             FieldExpr::lex_with(r#"http.host == "example.org""#, &SCHEME),
             FieldExpr {
-                field: field("http.host"),
+                lhs: field("http.host"),
                 op: FieldOp::Ordering {
                     op: OrderingOp::Equal,
                     rhs: RhsValue::Bytes("example.org".to_owned().into())
@@ -435,7 +693,7 @@ mod tests {
         let expr = assert_ok!(
             FieldExpr::lex_with("tcp.port & 1", &SCHEME),
             FieldExpr {
-                field: field("tcp.port"),
+                lhs: field("tcp.port"),
                 op: FieldOp::Int {
                     op: IntOp::BitwiseAnd,
                     rhs: 1,
@@ -462,12 +720,64 @@ mod tests {
         assert_eq!(expr.execute(ctx), true);
     }
 
+    #[test]
+    fn test_int64() {
+        let expr = assert_ok!(
+            FieldExpr::lex_with("http.content_length & 0x100000000", &SCHEME),
+            FieldExpr {
+                lhs: field("http.content_length"),
+                op: FieldOp::Int {
+                    op: IntOp::BitwiseAnd,
+                    rhs: 1 << 32,
+                }
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "field": "http.content_length",
+                "op": "BitwiseAnd",
+                "rhs": 4_294_967_296i64
+            }
+        );
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value("http.content_length", 0x1_0000_0001i64)
+            .unwrap();
+        assert!(expr.execute(ctx));
+
+        ctx.set_field_value("http.content_length", u32::MAX)
+            .unwrap();
+        assert!(!expr.execute(ctx));
+
+        let expr = FieldExpr::lex_with(
+            "http.content_length in { 2147483648..9223372036854775807 }",
+            &SCHEME,
+        )
+        .unwrap()
+        .0
+        .compile();
+
+        assert!(expr.execute(ctx));
+
+        ctx.set_field_value("http.content_length", i64::MAX)
+            .unwrap();
+        assert!(expr.execute(ctx));
+
+        ctx.set_field_value("http.content_length", i32::MAX)
+            .unwrap();
+        assert!(!expr.execute(ctx));
+    }
+
     #[test]
     fn test_int_in() {
         let expr = assert_ok!(
             FieldExpr::lex_with(r#"tcp.port in { 80 443 2082..2083 }"#, &SCHEME),
             FieldExpr {
-                field: field("tcp.port"),
+                lhs: field("tcp.port"),
                 op: FieldOp::OneOf(RhsValues::Int(vec![80..=80, 443..=443, 2082..=2083])),
             }
         );
@@ -510,12 +820,105 @@ mod tests {
         assert_eq!(expr.execute(ctx), false);
     }
 
+    #[test]
+    fn test_float_in() {
+        let expr = assert_ok!(
+            FieldExpr::lex_with(r#"tls.score in { -0.0..0.5 2e3 }"#, &SCHEME),
+            FieldExpr {
+                lhs: field("tls.score"),
+                op: FieldOp::OneOf(RhsValues::Float(vec![
+                    Float(0.0)..=Float(0.5),
+                    Float(2000.0)..=Float(2000.0)
+                ])),
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "field": "tls.score",
+                "op": "OneOf",
+                "rhs": [
+                    { "start": 0.0, "end": 0.5 },
+                    { "start": 2000.0, "end": 2000.0 },
+                ]
+            }
+        );
+
+        assert_eq!(expr.to_string(), "tls.score in {0.0..0.5 2000.0}");
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value("tls.score", 0.25).unwrap();
+        assert!(expr.execute(ctx));
+
+        ctx.set_field_value("tls.score", -0.0).unwrap();
+        assert!(expr.execute(ctx));
+
+        ctx.set_field_value("tls.score", 0.5000001).unwrap();
+        assert!(!expr.execute(ctx));
+
+        ctx.set_field_value("tls.score", 2000.0).unwrap();
+        assert!(expr.execute(ctx));
+
+        ctx.set_field_value("tls.score", f64::NAN).unwrap();
+        assert!(!expr.execute(ctx));
+    }
+
+    #[test]
+    fn test_duration_in() {
+        let expr = assert_ok!(
+            FieldExpr::lex_with(r#"cache.ttl in { 0s..1m30s 1d }"#, &SCHEME),
+            FieldExpr {
+                lhs: field("cache.ttl"),
+                op: FieldOp::OneOf(RhsValues::Duration(vec![
+                    Duration::from_secs(0)..=Duration::from_secs(90),
+                    Duration::from_secs(86_400)..=Duration::from_secs(86_400)
+                ])),
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "field": "cache.ttl",
+                "op": "OneOf",
+                "rhs": [
+                    { "start": "0s", "end": "1m30s" },
+                    { "start": "1d", "end": "1d" },
+                ]
+            }
+        );
+
+        assert_eq!(expr.to_string(), "cache.ttl in {0s..1m30s 1d}");
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value("cache.ttl", Duration::from_secs(90))
+            .unwrap();
+        assert!(expr.execute(ctx));
+
+        ctx.set_field_value("cache.ttl", Duration::from_nanos(90_000_000_001))
+            .unwrap();
+        assert!(!expr.execute(ctx));
+
+        ctx.set_field_value("cache.ttl", Duration::from_secs(-1))
+            .unwrap();
+        assert!(!expr.execute(ctx));
+
+        ctx.set_field_value("cache.ttl", Duration::from_secs(86_400))
+            .unwrap();
+        assert!(expr.execute(ctx));
+    }
+
     #[test]
     fn test_bytes_in() {
         let expr = assert_ok!(
             FieldExpr::lex_with(r#"http.host in { "example.org" "example.com" }"#, &SCHEME),
             FieldExpr {
-                field: field("http.host"),
+                lhs: field("http.host"),
                 op: FieldOp::OneOf(RhsValues::Bytes(
                     ["example.org", "example.com",]
                         .iter()
@@ -558,7 +961,7 @@ mod tests {
                 &SCHEME
             ),
             FieldExpr {
-                field: field("ip.addr"),
+                lhs: field("ip.addr"),
                 op: FieldOp::OneOf(RhsValues::Ip(vec![
                     IpRange::Cidr(IpCidr::new([127, 0, 0, 0].into(), 8).unwrap()),
                     IpRange::Cidr(IpCidr::new_host([0, 0, 0, 0, 0, 0, 0, 1].into())),
@@ -606,12 +1009,91 @@ mod tests {
         assert_eq!(expr.execute(ctx), false);
     }
 
+    #[test]
+    fn test_in_list() {
+        let expr = assert_ok!(
+            FieldExpr::lex_with("ip.addr in $blocklist", &SCHEME),
+            FieldExpr {
+                lhs: field("ip.addr"),
+                op: FieldOp::InList(SCHEME.get_list_ref("blocklist").unwrap()),
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "field": "ip.addr",
+                "op": "InList",
+                "rhs": "blocklist"
+            }
+        );
+
+        assert_eq!(expr.to_string(), "ip.addr in $blocklist");
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value("ip.addr", IpAddr::from([10, 1, 2, 3]))
+            .unwrap();
+        assert!(expr.execute(ctx));
+
+        ctx.set_field_value("ip.addr", IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]))
+            .unwrap();
+        assert!(expr.execute(ctx));
+
+        ctx.set_field_value("ip.addr", IpAddr::from([127, 0, 0, 1]))
+            .unwrap();
+        assert!(!expr.execute(ctx));
+
+        assert_err!(
+            FieldExpr::lex_with("ip.addr in $allowlist", &SCHEME),
+            LexErrorKind::UnknownList(UnknownListError),
+            "allowlist"
+        );
+
+        assert_err!(
+            FieldExpr::lex_with("ip.addr in $hosts", &SCHEME),
+            LexErrorKind::InvalidListType(TypeMismatchError {
+                expected: Type::Ip,
+                actual: Type::Bytes,
+            }),
+            "$hosts"
+        );
+
+        assert_eq!(
+            FieldExpr::from_raw_with(
+                &serde_json::from_str(
+                    r#"{ "field": "http.host", "op": "InList", "rhs": "hosts" }"#
+                )
+                .unwrap(),
+                &JsonPath::Root,
+                &SCHEME
+            ),
+            Ok(FieldExpr {
+                lhs: field("http.host"),
+                op: FieldOp::InList(SCHEME.get_list_ref("hosts").unwrap()),
+            })
+        );
+
+        assert_eq!(
+            FieldExpr::from_raw_with(
+                &serde_json::from_str(r#"{ "field": "tcp.port", "op": "InList", "rhs": "hosts" }"#)
+                    .unwrap(),
+                &JsonPath::Root,
+                &SCHEME
+            )
+            .unwrap_err()
+            .to_string(),
+            "invalid type of list: expected value of type Int, but got Bytes at $.rhs"
+        );
+    }
+
     #[test]
     fn test_contains_bytes() {
         let expr = assert_ok!(
             FieldExpr::lex_with(r#"http.host contains "abc""#, &SCHEME),
             FieldExpr {
-                field: field("http.host"),
+                lhs: field("http.host"),
                 op: FieldOp::Contains("abc".to_owned().into())
             }
         );
@@ -640,7 +1122,7 @@ mod tests {
         let expr = assert_ok!(
             FieldExpr::lex_with(r#"http.host contains 6F:72:67"#, &SCHEME),
             FieldExpr {
-                field: field("http.host"),
+                lhs: field("http.host"),
                 op: FieldOp::Contains(vec![0x6F, 0x72, 0x67].into()),
             }
         );
@@ -669,7 +1151,7 @@ mod tests {
         let expr = assert_ok!(
             FieldExpr::lex_with(r#"tcp.port < 8000"#, &SCHEME),
             FieldExpr {
-                field: field("tcp.port"),
+                lhs: field("tcp.port"),
                 op: FieldOp::Ordering {
                     op: OrderingOp::LessThan,
                     rhs: RhsValue::Int(8000)
@@ -695,4 +1177,522 @@ mod tests {
         ctx.set_field_value("tcp.port", 8080).unwrap();
         assert_eq!(expr.execute(ctx), false);
     }
+
+    #[test]
+    fn test_float_compare() {
+        let expr = assert_ok!(
+            FieldExpr::lex_with(r#"tls.score > 0.5"#, &SCHEME),
+            FieldExpr {
+                lhs: field("tls.score"),
+                op: FieldOp::Ordering {
+                    op: OrderingOp::GreaterThan,
+                    rhs: RhsValue::Float(Float(0.5))
+                },
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "field": "tls.score",
+                "op": "GreaterThan",
+                "rhs": 0.5,
+            }
+        );
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value("tls.score", 0.75).unwrap();
+        assert!(expr.execute(ctx));
+
+        ctx.set_field_value("tls.score", 0.25).unwrap();
+        assert!(!expr.execute(ctx));
+
+        ctx.set_field_value("tls.score", f64::NAN).unwrap();
+        assert!(!expr.execute(ctx));
+
+        // NaN is not equal to anything, including itself
+        let expr = FieldExpr::lex_with("tls.score != 1e-3", &SCHEME)
+            .unwrap()
+            .0
+            .compile();
+        assert!(expr.execute(ctx));
+
+        // integer literals are accepted in JSON too
+        assert_eq!(
+            FieldExpr::from_raw_with(
+                &serde_json::from_str(r#"{ "field": "tls.score", "op": "LessThan", "rhs": 1 }"#)
+                    .unwrap(),
+                &JsonPath::Root,
+                &SCHEME
+            ),
+            Ok(FieldExpr {
+                lhs: field("tls.score"),
+                op: FieldOp::Ordering {
+                    op: OrderingOp::LessThan,
+                    rhs: RhsValue::Float(Float(1.0))
+                },
+            })
+        );
+
+        assert_err!(
+            FieldExpr::lex_with("tls.score & 1", &SCHEME),
+            LexErrorKind::UnsupportedOp {
+                field_type: Type::Float
+            },
+            "tls.score &"
+        );
+    }
+
+    #[test]
+    fn test_timestamp_compare() {
+        let expr = assert_ok!(
+            FieldExpr::lex_with(r#"http.timestamp < 2019-04-01T14:30:00+02:00"#, &SCHEME),
+            FieldExpr {
+                lhs: field("http.timestamp"),
+                op: FieldOp::Ordering {
+                    op: OrderingOp::LessThan,
+                    rhs: RhsValue::Timestamp(Timestamp::from_unix_secs(1_554_121_800))
+                },
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "field": "http.timestamp",
+                "op": "LessThan",
+                "rhs": "2019-04-01T12:30:00Z",
+            }
+        );
+
+        assert_eq!(expr.to_string(), "http.timestamp < 2019-04-01T12:30:00Z");
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value("http.timestamp", Timestamp::from_unix_secs(1_554_121_799))
+            .unwrap();
+        assert!(expr.execute(ctx));
+
+        ctx.set_field_value("http.timestamp", Timestamp::from_unix_secs(1_554_121_800))
+            .unwrap();
+        assert!(!expr.execute(ctx));
+
+        assert_err!(
+            FieldExpr::lex_with("http.timestamp < 2019-04-31T00:00:00Z", &SCHEME),
+            LexErrorKind::InvalidTimestamp,
+            "31"
+        );
+
+        assert_eq!(
+            FieldExpr::from_raw_with(
+                &serde_json::from_str(
+                    r#"{ "field": "http.timestamp", "op": "Equal", "rhs": "2019-04-01T12:30:00" }"#
+                )
+                .unwrap(),
+                &JsonPath::Root,
+                &SCHEME
+            )
+            .unwrap_err()
+            .to_string(),
+            "expected time zone offset at $.rhs"
+        );
+    }
+
+    #[test]
+    fn test_mac_in() {
+        let oui = Mac::Eui48([0x00, 0x00, 0x5e, 0, 0, 0]);
+
+        let expr = assert_ok!(
+            FieldExpr::lex_with(
+                r#"eth.src in { 00-00-5E-00-00-00/24 02:00:00:00:00:01..02:00:00:00:00:ff }"#,
+                &SCHEME
+            ),
+            FieldExpr {
+                lhs: field("eth.src"),
+                op: FieldOp::OneOf(RhsValues::Mac(vec![
+                    MacRange::Prefix(oui, 24),
+                    MacRange::Explicit(
+                        Mac::Eui48([2, 0, 0, 0, 0, 1])..=Mac::Eui48([2, 0, 0, 0, 0, 0xff])
+                    ),
+                ])),
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "field": "eth.src",
+                "op": "OneOf",
+                "rhs": [
+                    "00:00:5e:00:00:00/24",
+                    { "start": "02:00:00:00:00:01", "end": "02:00:00:00:00:ff" },
+                ]
+            }
+        );
+
+        assert_eq!(
+            expr.to_string(),
+            "eth.src in {00:00:5e:00:00:00/24 02:00:00:00:00:01..02:00:00:00:00:ff}"
+        );
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value("eth.src", Mac::from([0x00, 0x00, 0x5e, 0x00, 0x53, 0x01]))
+            .unwrap();
+        assert!(expr.execute(ctx));
+
+        ctx.set_field_value("eth.src", Mac::from([0x00, 0x00, 0x5f, 0, 0, 0]))
+            .unwrap();
+        assert!(!expr.execute(ctx));
+
+        ctx.set_field_value("eth.src", Mac::from([2, 0, 0, 0, 0, 0x10]))
+            .unwrap();
+        assert!(expr.execute(ctx));
+
+        // EUI-64 addresses are never in EUI-48 ranges
+        ctx.set_field_value(
+            "eth.src",
+            Mac::from([0x00, 0x00, 0x5e, 0xff, 0xfe, 0, 0, 1]),
+        )
+        .unwrap();
+        assert!(!expr.execute(ctx));
+
+        assert_err!(
+            FieldExpr::lex_with("eth.src in { 00:00:5e:00:00:01/24 }", &SCHEME),
+            LexErrorKind::InvalidMacPrefix,
+            "00:00:5e:00:00:01"
+        );
+    }
+
+    #[test]
+    fn test_mac_compare() {
+        let expr = assert_ok!(
+            FieldExpr::lex_with("eth.src == 00:1A:2B:3C:4D:5E", &SCHEME),
+            FieldExpr {
+                lhs: field("eth.src"),
+                op: FieldOp::Ordering {
+                    op: OrderingOp::Equal,
+                    rhs: RhsValue::Mac(Mac::Eui48([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]))
+                },
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "field": "eth.src",
+                "op": "Equal",
+                "rhs": "00:1a:2b:3c:4d:5e",
+            }
+        );
+
+        assert_eq!(expr.to_string(), "eth.src == 00:1a:2b:3c:4d:5e");
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value("eth.src", Mac::from([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]))
+            .unwrap();
+        assert!(expr.execute(ctx));
+
+        ctx.set_field_value("eth.src", Mac::from([0x00, 0x1a, 0x2b, 0, 0, 0, 0, 0]))
+            .unwrap();
+        assert!(!expr.execute(ctx));
+
+        // addresses of different formats are not ordered
+        let expr = FieldExpr::lex_with("eth.src < ff:ff:ff:ff:ff:ff", &SCHEME)
+            .unwrap()
+            .0
+            .compile();
+        assert!(!expr.execute(ctx));
+
+        // unlike bytes, MAC addresses must have a valid length
+        assert_err!(
+            FieldExpr::lex_with("eth.src == 00:1a:2b:3c:4d", &SCHEME),
+            LexErrorKind::InvalidMacLength(5),
+            "00:1a:2b:3c:4d"
+        );
+
+        assert_eq!(
+            FieldExpr::from_raw_with(
+                &serde_json::from_str(
+                    r#"{ "field": "eth.src", "op": "Equal", "rhs": "00:1a:2b" }"#
+                )
+                .unwrap(),
+                &JsonPath::Root,
+                &SCHEME
+            )
+            .unwrap_err()
+            .to_string(),
+            "expected 6 or 8 octets in a MAC address, but found 3 at $.rhs"
+        );
+    }
+
+    #[test]
+    fn test_array_each_contains() {
+        let expr = assert_ok!(
+            FieldExpr::lex_with(r#"http.cookies[*] contains "abc""#, &SCHEME),
+            FieldExpr {
+                lhs: index_expr("http.cookies[*]"),
+                op: FieldOp::Contains("abc".to_owned().into()),
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "field": "http.cookies",
+                "indexes": ["Each"],
+                "op": "Contains",
+                "rhs": "abc",
+            }
+        );
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value("http.cookies", cookies(&["a=1", "xabcx=2"]))
+            .unwrap();
+        assert_eq!(expr.execute(ctx), true);
+
+        ctx.set_field_value("http.cookies", cookies(&["a=1", "b=2"]))
+            .unwrap();
+        assert_eq!(expr.execute(ctx), false);
+
+        ctx.set_field_value("http.cookies", cookies(&[])).unwrap();
+        assert_eq!(expr.execute(ctx), false);
+    }
+
+    #[test]
+    fn test_array_all_contains() {
+        let expr = assert_ok!(
+            FieldExpr::lex_with(r#"all http.cookies[*] contains "=""#, &SCHEME),
+            FieldExpr {
+                lhs: index_expr("all http.cookies[*]"),
+                op: FieldOp::Contains("=".to_owned().into()),
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "field": "http.cookies",
+                "indexes": ["Each"],
+                "quantifier": "All",
+                "op": "Contains",
+                "rhs": "=",
+            }
+        );
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value("http.cookies", cookies(&["a=1", "b=2"]))
+            .unwrap();
+        assert_eq!(expr.execute(ctx), true);
+
+        ctx.set_field_value("http.cookies", cookies(&["a=1", "b"]))
+            .unwrap();
+        assert_eq!(expr.execute(ctx), false);
+
+        ctx.set_field_value("http.cookies", cookies(&[])).unwrap();
+        assert_eq!(expr.execute(ctx), false);
+    }
+
+    #[test]
+    fn test_array_index_compare() {
+        let expr = assert_ok!(
+            FieldExpr::lex_with(r#"http.cookies[1] == "b=2""#, &SCHEME),
+            FieldExpr {
+                lhs: index_expr("http.cookies[1]"),
+                op: FieldOp::Ordering {
+                    op: OrderingOp::Equal,
+                    rhs: RhsValue::Bytes("b=2".to_owned().into()),
+                },
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "field": "http.cookies",
+                "indexes": [{ "ArrayIndex": 1 }],
+                "op": "Equal",
+                "rhs": "b=2",
+            }
+        );
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value("http.cookies", cookies(&["a=1", "b=2"]))
+            .unwrap();
+        assert_eq!(expr.execute(ctx), true);
+
+        ctx.set_field_value("http.cookies", cookies(&["b=2", "a=1"]))
+            .unwrap();
+        assert_eq!(expr.execute(ctx), false);
+
+        // out of bounds access doesn't match anything, including `!=`
+        ctx.set_field_value("http.cookies", cookies(&["b=2"]))
+            .unwrap();
+        assert_eq!(expr.execute(ctx), false);
+    }
+
+    #[test]
+    fn test_map_key_contains() {
+        let expr = assert_ok!(
+            FieldExpr::lex_with(r#"http.headers["user-agent"] contains "curl""#, &SCHEME),
+            FieldExpr {
+                lhs: index_expr(r#"http.headers["user-agent"]"#),
+                op: FieldOp::Contains("curl".to_owned().into()),
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "field": "http.headers",
+                "indexes": [{ "MapKey": "user-agent" }],
+                "op": "Contains",
+                "rhs": "curl",
+            }
+        );
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        let mut headers = Map::new(Type::Bytes);
+        headers.insert(b"user-agent", "curl/7.61.0").unwrap();
+        ctx.set_field_value("http.headers", headers).unwrap();
+        assert_eq!(expr.execute(ctx), true);
+
+        let mut headers = Map::new(Type::Bytes);
+        headers.insert(b"user-agent", "Mozilla/5.0").unwrap();
+        headers.insert(b"x-client", "curl").unwrap();
+        ctx.set_field_value("http.headers", headers).unwrap();
+        assert_eq!(expr.execute(ctx), false);
+
+        // missing keys don't match anything
+        ctx.set_field_value("http.headers", Map::new(Type::Bytes))
+            .unwrap();
+        assert_eq!(expr.execute(ctx), false);
+    }
+
+    #[test]
+    fn test_map_each_compare() {
+        let expr = assert_ok!(
+            FieldExpr::lex_with(r#"all http.headers[*] != """#, &SCHEME),
+            FieldExpr {
+                lhs: index_expr("all http.headers[*]"),
+                op: FieldOp::Ordering {
+                    op: OrderingOp::NotEqual,
+                    rhs: RhsValue::Bytes("".to_owned().into()),
+                },
+            }
+        );
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        let mut headers = Map::new(Type::Bytes);
+        headers.insert(b"host", "example.org").unwrap();
+        headers.insert(b"accept", "*/*").unwrap();
+        ctx.set_field_value("http.headers", headers.clone())
+            .unwrap();
+        assert_eq!(expr.execute(ctx), true);
+
+        headers.insert(b"cookie", "").unwrap();
+        ctx.set_field_value("http.headers", headers).unwrap();
+        assert_eq!(expr.execute(ctx), false);
+    }
+
+    #[test]
+    fn test_function_call_compare() {
+        let expr = assert_ok!(
+            FieldExpr::lex_with("len(http.cookies[0]) > 3", &SCHEME),
+            FieldExpr {
+                lhs: index_expr("len(http.cookies[0])"),
+                op: FieldOp::Ordering {
+                    op: OrderingOp::GreaterThan,
+                    rhs: RhsValue::Int(3),
+                },
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "function": {
+                    "name": "len",
+                    "args": [
+                        {
+                            "field": "http.cookies",
+                            "indexes": [{ "ArrayIndex": 0 }]
+                        }
+                    ]
+                },
+                "op": "GreaterThan",
+                "rhs": 3,
+            }
+        );
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value("http.cookies", cookies(&["a=10", "b=2"]))
+            .unwrap();
+        assert_eq!(expr.execute(ctx), true);
+
+        ctx.set_field_value("http.cookies", cookies(&["a=1", "b=20"]))
+            .unwrap();
+        assert_eq!(expr.execute(ctx), false);
+
+        // calls with undefined arguments don't match anything
+        ctx.set_field_value("http.cookies", cookies(&[])).unwrap();
+        assert_eq!(expr.execute(ctx), false);
+    }
+
+    #[test]
+    fn test_function_call_wrong_type() {
+        let expr = complete(FieldExpr::lex_with("broken_len(http.host) & 1", &SCHEME))
+            .unwrap()
+            .compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        // results of a type other than the declared one are undefined
+        ctx.set_field_value("http.host", "example.org").unwrap();
+        assert_eq!(expr.execute(ctx), false);
+    }
+
+    #[test]
+    fn test_array_unsupported_op() {
+        assert_err!(
+            FieldExpr::lex_with(r#"http.cookies == "a=1""#, &SCHEME),
+            LexErrorKind::UnsupportedOp {
+                field_type: Type::Array(Box::new(Type::Bytes))
+            },
+            "http.cookies =="
+        );
+
+        assert_err!(
+            FieldExpr::lex_with(r#"http.cookies[*] & 1"#, &SCHEME),
+            LexErrorKind::UnsupportedOp {
+                field_type: Type::Bytes
+            },
+            "http.cookies[*] &"
+        );
+
+        assert_err!(
+            FieldExpr::lex_with(r#"http.headers contains "a""#, &SCHEME),
+            LexErrorKind::UnsupportedOp {
+                field_type: Type::Map(Box::new(Type::Bytes))
+            },
+            "http.headers contains"
+        );
+    }
 }
@@ -0,0 +1,554 @@
+use super::index_expr::IndexExpr;
+use deserialize::{DeserializeError, FromRawWith, JsonPath, RawValue};
+use execution_context::ExecutionContext;
+use functions::FunctionArgKind;
+use lex::{expect, skip_space, span, LexErrorKind, LexResult, LexWith};
+use rhs_types::fmt_bytes;
+use scheme::{Field, FunctionRef, Scheme};
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+use types::{GetType, LhsValue, RhsValue, Type, TypeMismatchError};
+
+/// An argument of a function call: either a field expression or a literal.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[serde(untagged)]
+pub enum FunctionCallArgExpr<'s> {
+    IndexExpr(IndexExpr<'s>),
+    Literal(LhsValue<'static>),
+}
+
+impl<'s> FunctionCallArgExpr<'s> {
+    fn lex_with_kind<'i>(
+        input: &'i str,
+        scheme: &'s Scheme,
+        arg_kind: FunctionArgKind,
+        val_type: &Type,
+    ) -> LexResult<'i, Self> {
+        match arg_kind {
+            FunctionArgKind::Field => {
+                let (expr, input) = IndexExpr::lex_unquantified(input, scheme, false)?;
+                Ok((FunctionCallArgExpr::IndexExpr(expr), input))
+            }
+            FunctionArgKind::Literal => {
+                let (value, input) = RhsValue::lex_with(input, val_type)?;
+                Ok((FunctionCallArgExpr::Literal(value.into()), input))
+            }
+        }
+    }
+
+    fn from_raw_with_kind(
+        value: &RawValue,
+        path: &JsonPath<'_>,
+        scheme: &'s Scheme,
+        arg_kind: FunctionArgKind,
+        val_type: &Type,
+    ) -> Result<Self, DeserializeError> {
+        match arg_kind {
+            FunctionArgKind::Field => {
+                value
+                    .as_object(path)?
+                    .check_keys(&["field", "function", "indexes"], path)?;
+                let expr = IndexExpr::from_raw_unquantified(value, path, scheme, false)?;
+                Ok(FunctionCallArgExpr::IndexExpr(expr))
+            }
+            FunctionArgKind::Literal => {
+                let value = RhsValue::from_raw_with(value, path, val_type)?;
+                Ok(FunctionCallArgExpr::Literal(value.into()))
+            }
+        }
+    }
+
+    fn uses(&self, field: Field<'s>) -> bool {
+        match self {
+            FunctionCallArgExpr::IndexExpr(expr) => expr.uses(field),
+            FunctionCallArgExpr::Literal(_) => false,
+        }
+    }
+
+    fn execute<'a>(&'a self, ctx: &'a ExecutionContext<'_>) -> Option<LhsValue<'a>> {
+        match self {
+            FunctionCallArgExpr::IndexExpr(expr) => expr.execute(ctx),
+            FunctionCallArgExpr::Literal(value) => Some(value.as_ref()),
+        }
+    }
+}
+
+impl<'s> GetType for FunctionCallArgExpr<'s> {
+    fn get_type(&self) -> Type {
+        match self {
+            FunctionCallArgExpr::IndexExpr(expr) => expr.get_type(),
+            FunctionCallArgExpr::Literal(value) => value.get_type(),
+        }
+    }
+}
+
+impl<'s> Display for FunctionCallArgExpr<'s> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FunctionCallArgExpr::IndexExpr(expr) => Display::fmt(expr, f),
+            // literals can only be created from RHS values
+            FunctionCallArgExpr::Literal(value) => match value {
+                LhsValue::Ip(ip) => Display::fmt(ip, f),
+                LhsValue::Bytes(bytes) => fmt_bytes(bytes, f),
+                LhsValue::Int(num) => Display::fmt(num, f),
+                LhsValue::Float(num) => Display::fmt(num, f),
+                LhsValue::Timestamp(ts) => Display::fmt(ts, f),
+                LhsValue::Duration(duration) => Display::fmt(duration, f),
+                LhsValue::Mac(mac) => Display::fmt(mac, f),
+                LhsValue::Bool(_) | LhsValue::Array(_) | LhsValue::Map(_) => unreachable!(),
+            },
+        }
+    }
+}
+
+/// A call of a function registered in the [`Scheme`](struct@Scheme), e.g.
+/// `lower(http.host)`.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct FunctionCallExpr<'s> {
+    #[serde(rename = "name")]
+    function: FunctionRef<'s>,
+    args: Vec<FunctionCallArgExpr<'s>>,
+}
+
+impl<'i, 's> LexWith<'i, &'s Scheme> for FunctionCallExpr<'s> {
+    fn lex_with(input: &'i str, scheme: &'s Scheme) -> LexResult<'i, Self> {
+        let initial_input = input;
+
+        let (function, input) = FunctionRef::lex_with(input, scheme)?;
+        let definition = function.get_definition();
+
+        let expected_min = definition.params.len();
+        let expected_max = expected_min + definition.opt_params.len();
+
+        let invalid_count = |input| {
+            (
+                LexErrorKind::InvalidArgumentsCount {
+                    expected_min,
+                    expected_max,
+                },
+                span(initial_input, input),
+            )
+        };
+
+        let mut input = skip_space(expect(input, "(")?);
+        let mut args = Vec::new();
+
+        if let Ok(rest) = expect(input, ")") {
+            input = rest;
+        } else {
+            loop {
+                let (arg_kind, val_type) = definition
+                    .get_param(args.len())
+                    .ok_or_else(|| invalid_count(input))?;
+
+                let (arg, rest) =
+                    FunctionCallArgExpr::lex_with_kind(input, scheme, arg_kind, &val_type)?;
+
+                let arg_type = arg.get_type();
+
+                if arg_type != val_type {
+                    return Err((
+                        LexErrorKind::InvalidArgumentType {
+                            index: args.len(),
+                            mismatch: TypeMismatchError {
+                                expected: val_type,
+                                actual: arg_type,
+                            },
+                        },
+                        span(input, rest),
+                    ));
+                }
+
+                args.push(arg);
+                input = skip_space(rest);
+
+                match expect(input, ",") {
+                    Ok(rest) => input = skip_space(rest),
+                    Err(_) => {
+                        input = expect(input, ")")?;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if args.len() < expected_min {
+            return Err(invalid_count(input));
+        }
+
+        Ok((FunctionCallExpr { function, args }, input))
+    }
+}
+
+impl<'s> FromRawWith<&'s Scheme> for FunctionCallExpr<'s> {
+    fn from_raw_with(
+        value: &RawValue,
+        path: &JsonPath<'_>,
+        scheme: &'s Scheme,
+    ) -> Result<Self, DeserializeError> {
+        let object = value.as_object(path)?;
+        object.check_keys(&["name", "args"], path)?;
+
+        let name_path = path.key("name");
+        let function = scheme
+            .get_function(object.require("name", path)?.as_str(&name_path)?)
+            .map_err(|err| name_path.error(LexErrorKind::UnknownFunction(err)))?;
+        let definition = function.get_definition();
+
+        let args_path = path.key("args");
+        let raw_args = object.require("args", path)?.as_array(&args_path)?;
+
+        let expected_min = definition.params.len();
+        let expected_max = expected_min + definition.opt_params.len();
+
+        if raw_args.len() < expected_min || raw_args.len() > expected_max {
+            return Err(args_path.error(LexErrorKind::InvalidArgumentsCount {
+                expected_min,
+                expected_max,
+            }));
+        }
+
+        let args = raw_args
+            .iter()
+            .enumerate()
+            .map(|(index, arg)| {
+                let path = args_path.index(index);
+                let (arg_kind, val_type) = definition.get_param(index).unwrap();
+
+                let arg = FunctionCallArgExpr::from_raw_with_kind(
+                    arg, &path, scheme, arg_kind, &val_type,
+                )?;
+
+                let arg_type = arg.get_type();
+
+                if arg_type != val_type {
+                    return Err(path.error(LexErrorKind::InvalidArgumentType {
+                        index,
+                        mismatch: TypeMismatchError {
+                            expected: val_type,
+                            actual: arg_type,
+                        },
+                    }));
+                }
+
+                Ok(arg)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(FunctionCallExpr { function, args })
+    }
+}
+
+impl<'s> Display for FunctionCallExpr<'s> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(", self.function.name())?;
+
+        for (i, arg) in self.args.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            Display::fmt(arg, f)?;
+        }
+
+        f.write_str(")")
+    }
+}
+
+/// Maximum number of arguments that are resolved without an allocation.
+const INLINE_ARGS: usize = 4;
+
+impl<'s> FunctionCallExpr<'s> {
+    pub fn uses(&self, field: Field<'s>) -> bool {
+        self.args.iter().any(|arg| arg.uses(field))
+    }
+
+    /// Calls the function with values of arguments resolved from the
+    /// execution context.
+    ///
+    /// If any of the arguments is undefined, the result is undefined too.
+    pub fn execute<'a>(&'a self, ctx: &'a ExecutionContext<'_>) -> Option<LhsValue<'a>> {
+        let definition = self.function.get_definition();
+        let len = definition.params.len() + definition.opt_params.len();
+
+        // avoid allocating on each call for functions with few arguments
+        if len <= INLINE_ARGS {
+            let mut values: [Option<LhsValue<'a>>; INLINE_ARGS] = Default::default();
+            self.execute_in(ctx, &mut values[..len])
+        } else {
+            self.execute_in(ctx, &mut vec![None; len])
+        }
+    }
+
+    // Resolves arguments into `values`, which has a slot for each parameter,
+    // and calls the function with them.
+    fn execute_in<'a>(
+        &'a self,
+        ctx: &'a ExecutionContext<'_>,
+        values: &mut [Option<LhsValue<'a>>],
+    ) -> Option<LhsValue<'a>> {
+        let definition = self.function.get_definition();
+        let (provided, omitted) = values.split_at_mut(self.args.len());
+
+        for (value, arg) in provided.iter_mut().zip(&self.args) {
+            *value = Some(arg.execute(ctx)?);
+        }
+
+        // fill in default values of omitted optional arguments
+        let provided_opt_params = self.args.len() - definition.params.len();
+
+        for (value, param) in omitted
+            .iter_mut()
+            .zip(&definition.opt_params[provided_opt_params..])
+        {
+            *value = Some(param.default_value.as_ref());
+        }
+
+        definition
+            .implementation
+            .execute_with_clock(
+                || ctx.now(),
+                &mut values.iter_mut().map(|value| value.take().unwrap()),
+            )
+            // a value of a wrong type is treated as undefined
+            .filter(|value| value.get_type() == definition.return_type)
+    }
+}
+
+impl<'s> GetType for FunctionCallExpr<'s> {
+    fn get_type(&self) -> Type {
+        self.function.get_definition().return_type.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::FieldIndex;
+    use functions::{Function, FunctionArgs, FunctionImpl, FunctionOptParam, FunctionParam};
+    use lazy_static::lazy_static;
+    use lex::complete;
+    use lhs_types::Array;
+    use scheme::UnknownFunctionError;
+    use std::borrow::Cow;
+
+    fn echo_function<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+        args.next()
+    }
+
+    fn truncate_function<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+        match (args.next()?, args.next()?) {
+            (LhsValue::Bytes(Cow::Borrowed(bytes)), LhsValue::Int(len)) => Some(LhsValue::Bytes(
+                Cow::Borrowed(&bytes[..(len as usize).min(bytes.len())]),
+            )),
+            (LhsValue::Bytes(Cow::Owned(mut bytes)), LhsValue::Int(len)) => {
+                bytes.truncate(len as usize);
+                Some(LhsValue::Bytes(Cow::Owned(bytes)))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    lazy_static! {
+        static ref SCHEME: Scheme = {
+            let mut scheme = Scheme! {
+                http.cookies: Array(Bytes),
+                http.host: Bytes,
+                tcp.port: Int,
+            };
+            scheme
+                .add_function(
+                    "echo".into(),
+                    Function {
+                        params: vec![FunctionParam {
+                            arg_kind: FunctionArgKind::Field,
+                            val_type: Type::Bytes,
+                        }],
+                        opt_params: vec![],
+                        return_type: Type::Bytes,
+                        implementation: FunctionImpl::new(echo_function),
+                    },
+                )
+                .unwrap();
+            scheme
+                .add_function(
+                    "truncate".into(),
+                    Function {
+                        params: vec![FunctionParam {
+                            arg_kind: FunctionArgKind::Field,
+                            val_type: Type::Bytes,
+                        }],
+                        opt_params: vec![FunctionOptParam {
+                            arg_kind: FunctionArgKind::Literal,
+                            default_value: LhsValue::Int(3),
+                        }],
+                        return_type: Type::Bytes,
+                        implementation: FunctionImpl::new(truncate_function),
+                    },
+                )
+                .unwrap();
+            scheme
+        };
+    }
+
+    fn index_expr(input: &'static str) -> IndexExpr<'static> {
+        complete(IndexExpr::lex_unquantified(input, &SCHEME, false)).unwrap()
+    }
+
+    fn function(name: &'static str) -> FunctionRef<'static> {
+        SCHEME.get_function(name).unwrap()
+    }
+
+    #[test]
+    fn test_lex_function_call() {
+        let expr = assert_ok!(
+            FunctionCallExpr::lex_with("echo( http.host ) == ", &SCHEME),
+            FunctionCallExpr {
+                function: function("echo"),
+                args: vec![FunctionCallArgExpr::IndexExpr(index_expr("http.host"))],
+            },
+            " == "
+        );
+
+        assert_eq!(expr.get_type(), Type::Bytes);
+
+        assert_json!(
+            expr,
+            {
+                "name": "echo",
+                "args": [
+                    { "field": "http.host" }
+                ]
+            }
+        );
+
+        let expr = assert_ok!(
+            FunctionCallExpr::lex_with("truncate(echo(http.host), 5)", &SCHEME),
+            FunctionCallExpr {
+                function: function("truncate"),
+                args: vec![
+                    FunctionCallArgExpr::IndexExpr(index_expr("echo(http.host)")),
+                    FunctionCallArgExpr::Literal(LhsValue::Int(5)),
+                ],
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "name": "truncate",
+                "args": [
+                    {
+                        "function": {
+                            "name": "echo",
+                            "args": [
+                                { "field": "http.host" }
+                            ]
+                        }
+                    },
+                    5
+                ]
+            }
+        );
+
+        assert!(expr.uses(SCHEME.get_field("http.host").unwrap()));
+        assert!(!expr.uses(SCHEME.get_field("tcp.port").unwrap()));
+
+        assert_ok!(
+            FunctionCallExpr::lex_with("echo(http.cookies[0])", &SCHEME),
+            FunctionCallExpr {
+                function: function("echo"),
+                args: vec![FunctionCallArgExpr::IndexExpr(index_expr(
+                    "http.cookies[0]"
+                ))],
+            }
+        );
+    }
+
+    #[test]
+    fn test_lex_function_call_errors() {
+        assert_err!(
+            FunctionCallExpr::lex_with("unknown(http.host)", &SCHEME),
+            LexErrorKind::UnknownFunction(UnknownFunctionError),
+            "unknown"
+        );
+
+        assert_err!(
+            FunctionCallExpr::lex_with("echo()", &SCHEME),
+            LexErrorKind::InvalidArgumentsCount {
+                expected_min: 1,
+                expected_max: 1,
+            },
+            "echo()"
+        );
+
+        assert_err!(
+            FunctionCallExpr::lex_with("truncate(http.host, 1, 2)", &SCHEME),
+            LexErrorKind::InvalidArgumentsCount {
+                expected_min: 1,
+                expected_max: 2,
+            },
+            "truncate(http.host, 1, "
+        );
+
+        assert_err!(
+            FunctionCallExpr::lex_with("echo(tcp.port)", &SCHEME),
+            LexErrorKind::InvalidArgumentType {
+                index: 0,
+                mismatch: TypeMismatchError {
+                    expected: Type::Bytes,
+                    actual: Type::Int,
+                },
+            },
+            "tcp.port"
+        );
+
+        // literals are not accepted where fields are expected and vice versa
+        assert_err!(
+            FunctionCallExpr::lex_with(r#"echo("a")"#, &SCHEME),
+            LexErrorKind::ExpectedName("identifier character"),
+            r#""a")"#
+        );
+
+        assert_err!(
+            FunctionCallExpr::lex_with("truncate(http.host, tcp.port)", &SCHEME),
+            LexErrorKind::ExpectedName("digit"),
+            "tcp.port)"
+        );
+
+        // arguments must resolve to a single value
+        assert_err!(
+            FunctionCallExpr::lex_with("echo(http.cookies[*])", &SCHEME),
+            LexErrorKind::InvalidIndexAccess {
+                index: FieldIndex::Each,
+                actual: Type::Array(Box::new(Type::Bytes)),
+            },
+            "[*]"
+        );
+    }
+
+    #[test]
+    fn test_execute() {
+        use execution_context::ExecutionContext;
+
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+        ctx.set_field_value("http.host", "example.org").unwrap();
+
+        let expr = complete(FunctionCallExpr::lex_with("truncate(http.host)", &SCHEME)).unwrap();
+        assert_eq!(expr.execute(ctx), Some(LhsValue::from("exa")));
+
+        let expr = complete(FunctionCallExpr::lex_with(
+            "truncate(echo(http.host), 7)",
+            &SCHEME,
+        ))
+        .unwrap();
+        assert_eq!(expr.execute(ctx), Some(LhsValue::from("example")));
+
+        // undefined arguments make the whole call undefined
+        let mut cookies = Array::new(Type::Bytes);
+        cookies.push("a=1").unwrap();
+        ctx.set_field_value("http.cookies", cookies).unwrap();
+
+        let expr = complete(FunctionCallExpr::lex_with("echo(http.cookies[1])", &SCHEME)).unwrap();
+        assert_eq!(expr.execute(ctx), None);
+    }
+}
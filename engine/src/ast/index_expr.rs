@@ -0,0 +1,684 @@
+use super::{function_expr::FunctionCallExpr, CompiledExpr};
+use deserialize::{
+    DeserializeError, DeserializeErrorKind, FromRaw, FromRawWith, JsonPath, RawValue,
+};
+use execution_context::ExecutionContext;
+use filter::Execution;
+use lex::{expect, skip_space, span, take_while, Lex, LexErrorKind, LexResult, LexWith};
+use rhs_types::Bytes;
+use scheme::{lex_name, Field, Scheme};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use types::{GetType, LhsValue, Type};
+
+lex_enum!(Quantifier {
+    "any" => Any,
+    "all" => All,
+});
+
+impl Quantifier {
+    fn is_any(&self) -> bool {
+        *self == Quantifier::Any
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub enum FieldIndex {
+    ArrayIndex(u32),
+    MapKey(Bytes),
+    Each,
+}
+
+impl<'i> Lex<'i> for FieldIndex {
+    fn lex(input: &'i str) -> LexResult<'i, Self> {
+        let input = skip_space(expect(input, "[")?);
+
+        let (index, input) = if let Ok(input) = expect(input, "*") {
+            (FieldIndex::Each, input)
+        } else if input.starts_with('"') {
+            let (key, input) = Bytes::lex(input)?;
+            (FieldIndex::MapKey(key), input)
+        } else {
+            let (digits, input) = take_while(input, "digit", |c| c.is_ascii_digit())?;
+            match digits.parse() {
+                Ok(index) => (FieldIndex::ArrayIndex(index), input),
+                Err(err) => return Err((LexErrorKind::ParseInt { err, radix: 10 }, digits)),
+            }
+        };
+
+        let input = expect(skip_space(input), "]")?;
+
+        Ok((index, input))
+    }
+}
+
+impl Display for FieldIndex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldIndex::ArrayIndex(index) => write!(f, "[{}]", index),
+            FieldIndex::MapKey(key) => write!(f, "[{}]", key),
+            FieldIndex::Each => write!(f, "[*]"),
+        }
+    }
+}
+
+// Reads an index in the externally tagged form produced by `Serialize`.
+impl FromRaw for FieldIndex {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        match value {
+            RawValue::String(s) if s == "Each" => return Ok(FieldIndex::Each),
+            RawValue::Object(object) => {
+                if let Some(index) = object.get("ArrayIndex") {
+                    object.check_keys(&["ArrayIndex"], path)?;
+                    let index = index.as_int(&path.key("ArrayIndex"), "an array index")?;
+                    return Ok(FieldIndex::ArrayIndex(index));
+                }
+                if let Some(key) = object.get("MapKey") {
+                    object.check_keys(&["MapKey"], path)?;
+                    let key = Bytes::from_raw(key, &path.key("MapKey"))?;
+                    return Ok(FieldIndex::MapKey(key));
+                }
+            }
+            _ => {}
+        }
+        Err(path.error(DeserializeErrorKind::Expected(
+            r#""Each", {"ArrayIndex": ...} or {"MapKey": ...}"#,
+        )))
+    }
+}
+
+impl FieldIndex {
+    fn get_item_type(&self, ty: &Type) -> Option<Type> {
+        match (self, ty) {
+            (FieldIndex::ArrayIndex(_), Type::Array(item_type))
+            | (FieldIndex::MapKey(_), Type::Map(item_type))
+            | (FieldIndex::Each, Type::Array(item_type))
+            | (FieldIndex::Each, Type::Map(item_type)) => Some((**item_type).clone()),
+            _ => None,
+        }
+    }
+}
+
+/// A base of the left-hand side of a field expression: either a field, or a
+/// result of a function call.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub enum LhsFieldExpr<'s> {
+    #[serde(rename = "field")]
+    Field(Field<'s>),
+
+    #[serde(rename = "function")]
+    FunctionCall(FunctionCallExpr<'s>),
+}
+
+impl<'i, 's> LexWith<'i, &'s Scheme> for LhsFieldExpr<'s> {
+    fn lex_with(input: &'i str, scheme: &'s Scheme) -> LexResult<'i, Self> {
+        let (_, rest) = lex_name(input)?;
+
+        Ok(if rest.starts_with('(') {
+            let (call, input) = FunctionCallExpr::lex_with(input, scheme)?;
+            (LhsFieldExpr::FunctionCall(call), input)
+        } else {
+            let (field, input) = Field::lex_with(input, scheme)?;
+            (LhsFieldExpr::Field(field), input)
+        })
+    }
+}
+
+impl<'s> Display for LhsFieldExpr<'s> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LhsFieldExpr::Field(field) => f.write_str(field.name()),
+            LhsFieldExpr::FunctionCall(call) => Display::fmt(call, f),
+        }
+    }
+}
+
+impl<'s> LhsFieldExpr<'s> {
+    pub fn uses(&self, field: Field<'s>) -> bool {
+        match self {
+            LhsFieldExpr::Field(f) => *f == field,
+            LhsFieldExpr::FunctionCall(call) => call.uses(field),
+        }
+    }
+}
+
+impl<'s> GetType for LhsFieldExpr<'s> {
+    fn get_type(&self) -> Type {
+        match self {
+            LhsFieldExpr::Field(field) => field.get_type(),
+            LhsFieldExpr::FunctionCall(call) => call.get_type(),
+        }
+    }
+}
+
+/// Left-hand side of a field expression: a field or a function call with an
+/// optional chain of indexes into compound values.
+///
+/// If any of the indexes is `[*]`, the expression resolves to multiple values
+/// and the quantifier defines how results of comparisons with each of them
+/// are combined.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct IndexExpr<'s> {
+    #[serde(flatten)]
+    lhs: LhsFieldExpr<'s>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    indexes: Vec<FieldIndex>,
+
+    #[serde(skip_serializing_if = "Quantifier::is_any")]
+    quantifier: Quantifier,
+}
+
+impl<'s> From<Field<'s>> for IndexExpr<'s> {
+    fn from(field: Field<'s>) -> Self {
+        IndexExpr {
+            lhs: LhsFieldExpr::Field(field),
+            indexes: Vec::new(),
+            quantifier: Quantifier::Any,
+        }
+    }
+}
+
+impl<'s> Display for IndexExpr<'s> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.quantifier == Quantifier::All {
+            f.write_str("all ")?;
+        }
+
+        Display::fmt(&self.lhs, f)?;
+
+        for index in &self.indexes {
+            Display::fmt(index, f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'s> IndexExpr<'s> {
+    /// Lexes an expression without a quantifier.
+    ///
+    /// `[*]` is accepted only if `allow_each` is set, as otherwise the
+    /// expression must resolve to a single value.
+    pub(crate) fn lex_unquantified<'i>(
+        input: &'i str,
+        scheme: &'s Scheme,
+        allow_each: bool,
+    ) -> LexResult<'i, Self> {
+        let (lhs, mut input) = LhsFieldExpr::lex_with(input, scheme)?;
+        let mut ty = lhs.get_type();
+        let mut indexes = Vec::new();
+
+        while input.starts_with('[') {
+            let (index, rest) = FieldIndex::lex(input)?;
+
+            ty = match index.get_item_type(&ty) {
+                Some(ref item_type) if allow_each || index != FieldIndex::Each => item_type.clone(),
+                _ => {
+                    return Err((
+                        LexErrorKind::InvalidIndexAccess { index, actual: ty },
+                        span(input, rest),
+                    ));
+                }
+            };
+
+            indexes.push(index);
+            input = rest;
+        }
+
+        Ok((
+            IndexExpr {
+                lhs,
+                indexes,
+                quantifier: Quantifier::Any,
+            },
+            input,
+        ))
+    }
+
+    /// Reads an expression without a quantifier from the `field` or
+    /// `function` and `indexes` properties of an object.
+    ///
+    /// `[*]` is accepted only if `allow_each` is set, as in `lex_unquantified`.
+    pub(crate) fn from_raw_unquantified(
+        value: &RawValue,
+        path: &JsonPath<'_>,
+        scheme: &'s Scheme,
+        allow_each: bool,
+    ) -> Result<Self, DeserializeError> {
+        let object = value.as_object(path)?;
+
+        let lhs = match (object.get("field"), object.get("function")) {
+            (Some(name), None) => {
+                let path = path.key("field");
+                let field = scheme
+                    .get_field(name.as_str(&path)?)
+                    .map_err(|err| path.error(LexErrorKind::UnknownField(err)))?;
+                LhsFieldExpr::Field(field)
+            }
+            (None, Some(call)) => LhsFieldExpr::FunctionCall(FunctionCallExpr::from_raw_with(
+                call,
+                &path.key("function"),
+                scheme,
+            )?),
+            (None, None) => {
+                return Err(path.error(DeserializeErrorKind::MissingProperty("field")));
+            }
+            (Some(_), Some(_)) => {
+                return Err(path
+                    .key("function")
+                    .error(DeserializeErrorKind::UnknownProperty("function".to_owned())));
+            }
+        };
+
+        let mut ty = lhs.get_type();
+        let mut indexes = Vec::new();
+
+        if let Some(raw_indexes) = object.get("indexes") {
+            let path = path.key("indexes");
+
+            for (i, index) in raw_indexes.as_array(&path)?.iter().enumerate() {
+                let path = path.index(i);
+                let index = FieldIndex::from_raw(index, &path)?;
+
+                ty = match index.get_item_type(&ty) {
+                    Some(ref item_type) if allow_each || index != FieldIndex::Each => {
+                        item_type.clone()
+                    }
+                    _ => {
+                        return Err(
+                            path.error(LexErrorKind::InvalidIndexAccess { index, actual: ty })
+                        );
+                    }
+                };
+
+                indexes.push(index);
+            }
+        }
+
+        Ok(IndexExpr {
+            lhs,
+            indexes,
+            quantifier: Quantifier::Any,
+        })
+    }
+
+    pub fn uses(&self, field: Field<'s>) -> bool {
+        self.lhs.uses(field)
+    }
+
+    /// Returns whether the expression resolves to a single value, i.e. it
+    /// doesn't contain any `[*]` indexes.
+    pub(crate) fn is_single_value(&self) -> bool {
+        !self.indexes.contains(&FieldIndex::Each)
+    }
+
+    /// Resolves a single value of an expression without `[*]` indexes.
+    ///
+    /// Returns `None` if the value is undefined, e.g. if the field is not set
+    /// or an index is out of bounds.
+    pub(crate) fn execute<'a>(&'a self, ctx: &'a ExecutionContext<'_>) -> Option<LhsValue<'a>> {
+        self.resolve(ctx, &self.indexes)
+    }
+
+    /// Resolves a value by indexes up to the first `[*]`.
+    ///
+    /// For expressions without `[*]` this is the same as `execute`, and
+    /// otherwise it returns the compound value the first `[*]` iterates over.
+    pub(crate) fn execute_until_each<'a>(
+        &'a self,
+        ctx: &'a ExecutionContext<'_>,
+    ) -> Option<LhsValue<'a>> {
+        let end = self
+            .indexes
+            .iter()
+            .position(|index| *index == FieldIndex::Each)
+            .unwrap_or(self.indexes.len());
+
+        self.resolve(ctx, &self.indexes[..end])
+    }
+
+    fn resolve<'a>(
+        &'a self,
+        ctx: &'a ExecutionContext<'_>,
+        indexes: &[FieldIndex],
+    ) -> Option<LhsValue<'a>> {
+        match &self.lhs {
+            LhsFieldExpr::Field(field) => {
+                get(ctx.get_field_value_unchecked(*field)?, indexes).map(LhsValue::as_ref)
+            }
+            LhsFieldExpr::FunctionCall(call) => {
+                let value = call.execute(ctx)?;
+                if indexes.is_empty() {
+                    Some(value)
+                } else {
+                    get(&value, indexes).cloned()
+                }
+            }
+        }
+    }
+
+    /// Compiles an expression that applies a given comparison to the
+    /// resolved value(s) and combines results according to the quantifier.
+    ///
+    /// If the field is not set or indexes don't resolve to any value, the
+    /// result is `false`.
+    ///
+    /// The comparison also receives the ongoing execution, e.g. to look up
+    /// values in lists.
+    pub fn compile_with<F>(self, func: F) -> CompiledExpr<'s>
+    where
+        F: 's + Fn(&LhsValue<'_>, &Execution<'_, '_>) -> bool,
+    {
+        let IndexExpr {
+            lhs,
+            indexes,
+            quantifier,
+        } = self;
+
+        let indexes = indexes.into_boxed_slice();
+
+        match lhs {
+            LhsFieldExpr::Field(field) => {
+                if indexes.is_empty() {
+                    CompiledExpr::new(move |ctx| match ctx.get_field_value_unchecked(field) {
+                        Some(value) => func(value, ctx),
+                        None => false,
+                    })
+                } else {
+                    CompiledExpr::new(move |ctx| match ctx.get_field_value_unchecked(field) {
+                        Some(value) => quantify(value, &indexes, quantifier, &|x| func(x, ctx)),
+                        None => false,
+                    })
+                }
+            }
+            LhsFieldExpr::FunctionCall(call) => {
+                CompiledExpr::new(move |ctx| match call.execute(ctx) {
+                    Some(value) => quantify(&value, &indexes, quantifier, &|x| func(x, ctx)),
+                    None => false,
+                })
+            }
+        }
+    }
+}
+
+// Applies `func` to all values resolved by indexes and combines results
+// according to the quantifier.
+fn quantify<F: Fn(&LhsValue<'_>) -> bool>(
+    value: &LhsValue<'_>,
+    indexes: &[FieldIndex],
+    quantifier: Quantifier,
+    func: &F,
+) -> bool {
+    match quantifier {
+        Quantifier::Any => {
+            let mut result = false;
+            visit(value, indexes, &mut |value| {
+                result = func(value);
+                !result
+            });
+            result
+        }
+        Quantifier::All => {
+            let mut visited = false;
+            let result = visit(value, indexes, &mut |value| {
+                visited = true;
+                func(value)
+            });
+            visited && result
+        }
+    }
+}
+
+// Resolves a single value by indexes that don't contain `[*]`.
+fn get<'v, 'a>(value: &'v LhsValue<'a>, indexes: &[FieldIndex]) -> Option<&'v LhsValue<'a>> {
+    indexes
+        .iter()
+        .try_fold(value, |value, index| match (index, value) {
+            (FieldIndex::ArrayIndex(i), LhsValue::Array(array)) => array.get(*i as usize),
+            (FieldIndex::MapKey(key), LhsValue::Map(map)) => map.get(key),
+            _ => unreachable!(),
+        })
+}
+
+// Calls `func` for each value resolved by indexes until it returns `false`.
+//
+// Returns `false` if iteration was stopped early, and `true` otherwise.
+fn visit<'a, F: FnMut(&LhsValue<'a>) -> bool>(
+    value: &LhsValue<'a>,
+    indexes: &[FieldIndex],
+    func: &mut F,
+) -> bool {
+    match indexes.split_first() {
+        None => func(value),
+        Some((index, rest)) => match (index, value) {
+            (FieldIndex::ArrayIndex(i), LhsValue::Array(array)) => match array.get(*i as usize) {
+                Some(item) => visit(item, rest, func),
+                None => true,
+            },
+            (FieldIndex::MapKey(key), LhsValue::Map(map)) => match map.get(key) {
+                Some(item) => visit(item, rest, func),
+                None => true,
+            },
+            (FieldIndex::Each, LhsValue::Array(array)) => {
+                array.iter().all(|item| visit(item, rest, func))
+            }
+            (FieldIndex::Each, LhsValue::Map(map)) => {
+                map.values().all(|item| visit(item, rest, func))
+            }
+            _ => unreachable!(),
+        },
+    }
+}
+
+impl<'i, 's> LexWith<'i, &'s Scheme> for IndexExpr<'s> {
+    fn lex_with(input: &'i str, scheme: &'s Scheme) -> LexResult<'i, Self> {
+        // A quantifier is a keyword followed by a space, unless there is a
+        // field with the same name (which we prefer for compatibility).
+        if let Ok((quantifier, rest)) = Quantifier::lex(input) {
+            let keyword = span(input, rest);
+            let rest_after_space = skip_space(rest);
+
+            if rest_after_space.len() < rest.len() && scheme.get_field(keyword).is_err() {
+                let (mut expr, rest) = Self::lex_unquantified(rest_after_space, scheme, true)?;
+
+                if !expr.indexes.contains(&FieldIndex::Each) {
+                    return Err((LexErrorKind::MissingEachIndex, span(input, rest)));
+                }
+
+                expr.quantifier = quantifier;
+                return Ok((expr, rest));
+            }
+        }
+
+        Self::lex_unquantified(input, scheme, true)
+    }
+}
+
+impl<'s> FromRawWith<&'s Scheme> for IndexExpr<'s> {
+    fn from_raw_with(
+        value: &RawValue,
+        path: &JsonPath<'_>,
+        scheme: &'s Scheme,
+    ) -> Result<Self, DeserializeError> {
+        let mut expr = Self::from_raw_unquantified(value, path, scheme, true)?;
+
+        if let Some(quantifier) = value.as_object(path)?.get("quantifier") {
+            let path = path.key("quantifier");
+            expr.quantifier = quantifier.as_enum(&path)?;
+
+            if expr.quantifier == Quantifier::All && !expr.indexes.contains(&FieldIndex::Each) {
+                return Err(path.error(LexErrorKind::MissingEachIndex));
+            }
+        }
+
+        Ok(expr)
+    }
+}
+
+impl<'s> GetType for IndexExpr<'s> {
+    fn get_type(&self) -> Type {
+        self.indexes.iter().fold(self.lhs.get_type(), |ty, index| {
+            index.get_item_type(&ty).unwrap()
+        })
+    }
+}
+
+#[test]
+fn test_lex() {
+    let scheme = &Scheme! {
+        any: Bool,
+        str: Bytes,
+        arr: Array(Bytes),
+        nested: Array(Array(Int)),
+        map: Map(Array(Bytes)),
+    };
+
+    let field = |name| scheme.get_field(name).unwrap();
+
+    assert_ok!(
+        IndexExpr::lex_with("str", scheme),
+        IndexExpr::from(field("str"))
+    );
+
+    let expr = assert_ok!(
+        IndexExpr::lex_with("arr[1]", scheme),
+        IndexExpr {
+            lhs: LhsFieldExpr::Field(field("arr")),
+            indexes: vec![FieldIndex::ArrayIndex(1)],
+            quantifier: Quantifier::Any,
+        }
+    );
+
+    assert_eq!(expr.get_type(), Type::Bytes);
+
+    assert_json!(
+        expr,
+        {
+            "field": "arr",
+            "indexes": [{ "ArrayIndex": 1 }]
+        }
+    );
+
+    let expr = assert_ok!(
+        IndexExpr::lex_with("all nested[ * ][0] ==", scheme),
+        IndexExpr {
+            lhs: LhsFieldExpr::Field(field("nested")),
+            indexes: vec![FieldIndex::Each, FieldIndex::ArrayIndex(0)],
+            quantifier: Quantifier::All,
+        },
+        " =="
+    );
+
+    assert_eq!(expr.get_type(), Type::Int);
+
+    assert_json!(
+        expr,
+        {
+            "field": "nested",
+            "indexes": ["Each", { "ArrayIndex": 0 }],
+            "quantifier": "All"
+        }
+    );
+
+    assert_ok!(
+        IndexExpr::lex_with("nested[0]", scheme),
+        IndexExpr {
+            lhs: LhsFieldExpr::Field(field("nested")),
+            indexes: vec![FieldIndex::ArrayIndex(0)],
+            quantifier: Quantifier::Any,
+        }
+    );
+
+    let expr = assert_ok!(
+        IndexExpr::lex_with(r#"map["a\"b"][*]"#, scheme),
+        IndexExpr {
+            lhs: LhsFieldExpr::Field(field("map")),
+            indexes: vec![
+                FieldIndex::MapKey("a\"b".to_owned().into()),
+                FieldIndex::Each
+            ],
+            quantifier: Quantifier::Any,
+        }
+    );
+
+    assert_eq!(expr.get_type(), Type::Bytes);
+
+    assert_json!(
+        expr,
+        {
+            "field": "map",
+            "indexes": [{ "MapKey": "a\"b" }, "Each"]
+        }
+    );
+
+    assert_ok!(
+        IndexExpr::lex_with("all map[*][0]", scheme),
+        IndexExpr {
+            lhs: LhsFieldExpr::Field(field("map")),
+            indexes: vec![FieldIndex::Each, FieldIndex::ArrayIndex(0)],
+            quantifier: Quantifier::All,
+        }
+    );
+
+    // a field with the same name as a quantifier takes precedence
+    assert_ok!(
+        IndexExpr::lex_with("any and", scheme),
+        IndexExpr::from(field("any")),
+        " and"
+    );
+
+    assert_err!(
+        IndexExpr::lex_with("str[0]", scheme),
+        LexErrorKind::InvalidIndexAccess {
+            index: FieldIndex::ArrayIndex(0),
+            actual: Type::Bytes,
+        },
+        "[0]"
+    );
+
+    assert_err!(
+        IndexExpr::lex_with("arr[0][*]", scheme),
+        LexErrorKind::InvalidIndexAccess {
+            index: FieldIndex::Each,
+            actual: Type::Bytes,
+        },
+        "[*]"
+    );
+
+    assert_err!(
+        IndexExpr::lex_with("map[0]", scheme),
+        LexErrorKind::InvalidIndexAccess {
+            index: FieldIndex::ArrayIndex(0),
+            actual: Type::Map(Box::new(Type::Array(Box::new(Type::Bytes)))),
+        },
+        "[0]"
+    );
+
+    assert_err!(
+        IndexExpr::lex_with(r#"arr["a"]"#, scheme),
+        LexErrorKind::InvalidIndexAccess {
+            index: FieldIndex::MapKey("a".to_owned().into()),
+            actual: Type::Array(Box::new(Type::Bytes)),
+        },
+        r#"["a"]"#
+    );
+
+    assert_err!(
+        IndexExpr::lex_with("arr[x]", scheme),
+        LexErrorKind::ExpectedName("digit"),
+        "x]"
+    );
+
+    assert_err!(
+        IndexExpr::lex_with("arr[0", scheme),
+        LexErrorKind::ExpectedLiteral("]"),
+        ""
+    );
+
+    assert_err!(
+        IndexExpr::lex_with("all arr[0]", scheme),
+        LexErrorKind::MissingEachIndex,
+        "all arr[0]"
+    );
+}
@@ -1,17 +1,42 @@
 mod combined_expr;
 mod field_expr;
+mod function_expr;
+mod index_expr;
 mod simple_expr;
+mod trace;
+
+pub use self::{index_expr::FieldIndex, trace::ExecutionTrace};
+
+pub(crate) use self::{field_expr::FieldExpr, index_expr::IndexExpr};
 
 use self::combined_expr::CombinedExpr;
-use filter::{CompiledExpr, Filter};
+use deserialize::{DeserializeError, FromRawWith, JsonPath, RawValue};
+use execution_context::ExecutionContext;
+use filter::{CompiledExpr, Filter, SchemeMismatchError};
+use filter_set::{SetExpr, SetLeaves};
 use lex::{LexResult, LexWith};
 use scheme::{Field, Scheme, UnknownFieldError};
-use serde::Serialize;
-use std::fmt::{self, Debug};
+use serde::{
+    de::{self, DeserializeSeed, Deserializer},
+    Deserialize, Serialize,
+};
+use std::fmt::{self, Debug, Display};
 
-trait Expr<'s>: Sized + Eq + Debug + for<'i> LexWith<'i, &'s Scheme> + Serialize {
+pub(crate) trait Expr<'s>:
+    Sized + Eq + Debug + for<'i> LexWith<'i, &'s Scheme> + FromRawWith<&'s Scheme> + Serialize
+{
     fn uses(&self, field: Field<'s>) -> bool;
+
+    /// Converts an expression into a tree of combinators over leaves shared
+    /// between all filters of a [`FilterSet`](::FilterSet).
+    fn lower(self, leaves: &mut SetLeaves<'s>) -> SetExpr;
+
     fn compile(self) -> CompiledExpr<'s>;
+
+    /// Executes an expression recording results of all nested expressions.
+    ///
+    /// If `ctx` is `None`, the expression is only recorded as not evaluated.
+    fn trace<'a>(&'a self, ctx: Option<&'a ExecutionContext<'_>>) -> ExecutionTrace<'a>;
 }
 
 /// A parsed filter AST.
@@ -30,7 +55,23 @@ pub struct FilterAst<'s> {
 
 impl<'s> Debug for FilterAst<'s> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.op.fmt(f)
+        Debug::fmt(&self.op, f)
+    }
+}
+
+/// Formats the filter back into the canonical filter syntax, which parses
+/// into the same [`FilterAst`].
+///
+/// The alternate form (`{:#}`) puts each item of the outermost `and`/`or`/`xor`
+/// on a separate line and breaks up parenthesized groups in the same way with
+/// four spaces of indentation.
+impl<'s> Display for FilterAst<'s> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            self.op.fmt_multiline(f, 0)
+        } else {
+            Display::fmt(&self.op, f)
+        }
     }
 }
 
@@ -41,18 +82,566 @@ impl<'i, 's> LexWith<'i, &'s Scheme> for FilterAst<'s> {
     }
 }
 
+impl<'s> FromRawWith<&'s Scheme> for FilterAst<'s> {
+    fn from_raw_with(
+        value: &RawValue,
+        path: &JsonPath<'_>,
+        scheme: &'s Scheme,
+    ) -> Result<Self, DeserializeError> {
+        let op = CombinedExpr::from_raw_with(value, path, scheme)?;
+        Ok(FilterAst { scheme, op })
+    }
+}
+
 impl<'s> FilterAst<'s> {
     /// Recursively checks whether a [`FilterAst`] uses a given field name.
     ///
     /// This is useful to lazily initialise expensive fields only if necessary.
     pub fn uses(&self, field_name: &str) -> Result<bool, UnknownFieldError> {
         self.scheme
-            .get_field_index(field_name)
+            .get_field(field_name)
             .map(|field| self.op.uses(field))
     }
 
     /// Compiles a [`FilterAst`] into a [`Filter`].
     pub fn compile(self) -> Filter<'s> {
-        Filter::new(self.op.compile(), self.scheme)
+        let fields = self
+            .scheme
+            .fields()
+            .filter(|&field| self.op.uses(field))
+            .collect();
+
+        Filter::new(self.op.compile(), self.scheme, fields)
+    }
+
+    /// Executes a filter against a provided context with values, recording
+    /// which expressions were evaluated, their results and compared values.
+    ///
+    /// The result of the root expression is the same as would be returned by
+    /// [`Filter::execute`](::Filter::execute), but this method is much slower
+    /// and is meant only for debugging filters.
+    pub fn execute_traced<'a>(
+        &'a self,
+        ctx: &'a ExecutionContext<'_>,
+    ) -> Result<ExecutionTrace<'a>, SchemeMismatchError> {
+        if self.scheme == ctx.scheme() {
+            Ok(self.op.trace(Some(ctx)))
+        } else {
+            Err(SchemeMismatchError)
+        }
+    }
+
+    pub(crate) fn scheme(&self) -> &'s Scheme {
+        self.scheme
+    }
+
+    pub(crate) fn lower(self, leaves: &mut SetLeaves<'s>) -> SetExpr {
+        self.op.lower(leaves)
+    }
+}
+
+/// A [`DeserializeSeed`] that deserializes a [`FilterAst`] from the form
+/// produced by its `Serialize` implementation.
+///
+/// Field and function names are resolved in the given
+/// [`Scheme`](struct@Scheme), and the filter is validated in the same way as
+/// by [`Scheme::parse`](struct@Scheme#method.parse). Error messages contain
+/// the path to the invalid value; use
+/// [`Scheme::deserialize_filter`](struct@Scheme#method.deserialize_filter)
+/// to get them as a [`DeserializeError`](::DeserializeError) instead.
+#[derive(Clone, Copy)]
+pub struct FilterAstSeed<'s> {
+    scheme: &'s Scheme,
+}
+
+impl<'s> FilterAstSeed<'s> {
+    /// Creates a seed for filters of a given scheme.
+    pub fn new(scheme: &'s Scheme) -> Self {
+        FilterAstSeed { scheme }
+    }
+}
+
+impl<'de, 's> DeserializeSeed<'de> for FilterAstSeed<'s> {
+    type Value = FilterAst<'s>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let value = RawValue::deserialize(deserializer)?;
+        FilterAst::from_raw_with(&value, &JsonPath::Root, self.scheme).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FilterAstSeed;
+    use indoc::indoc;
+    use lazy_static::lazy_static;
+    use proptest::{collection::vec, prelude::*, sample::select};
+    use scheme::Scheme;
+    use serde::de::DeserializeSeed;
+    use serde_json::{json, Value};
+
+    lazy_static! {
+        static ref SCHEME: Scheme = {
+            let mut scheme = Scheme! {
+                ip: Ip,
+                host: Bytes,
+                port: Int,
+                score: Float,
+                time: Timestamp,
+                ttl: Duration,
+                mac: Mac,
+                ssl: Bool,
+                tags: Array(Bytes),
+                headers: Map(Bytes),
+            };
+            scheme.add_std_functions().unwrap();
+            scheme
+        };
+    }
+
+    fn assert_format(input: &str, expected: &str) {
+        let ast = SCHEME.parse(input).unwrap();
+        assert_eq!(ast.to_string(), expected);
+        assert_eq!(SCHEME.parse(expected), Ok(ast));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_format(
+            r#"ip in { 10.0.0.0/8 127.0.0.1..127.0.0.5 } && !ssl || port eq 80"#,
+            "ip in {10.0.0.0/8 127.0.0.1..127.0.0.5} and not ssl or port == 80",
+        );
+        assert_format(
+            r#"(ssl or port in {443 8000..8080}) and host ~ "^\d+[\"]\.com$""#,
+            r#"(ssl or port in {443 8000..8080}) and host matches "^\d+[\"]\.com$""#,
+        );
+        assert_format(
+            r#"not (ssl ^^ all tags[*] contains "a\x22b") xor headers["x"] != 61:FF"#,
+            r#"not (ssl xor all tags[*] contains "a\"b") xor headers["x"] != 61:FF"#,
+        );
+        assert_format(
+            r#"lower(host) in {"a" "\101\x0a"} or len(host) > 3 and starts_with(host, 00)"#,
+            r#"lower(host) in {"a" "A\x0A"} or len(host) > 3 and starts_with(host, "\x00")"#,
+        );
+        assert_format(
+            "substring(concat(host, tags[0]), 1, 2) == 7a and port & 0x10",
+            r#"substring(concat(host, tags[0]), 1, 2) == 7A and port & 16"#,
+        );
+    }
+
+    #[test]
+    fn test_display_multiline() {
+        let ast = SCHEME
+            .parse(
+                r#"ssl && (port == 80 || port == 8080 and not (host == "a" ^^ host == "b")) or (ssl) or not ssl"#,
+            )
+            .unwrap();
+
+        assert_eq!(
+            format!("{:#}", ast),
+            indoc!(
+                r#"
+                ssl and (
+                    port == 80
+                    or port == 8080 and not (
+                        host == "a"
+                        xor host == "b"
+                    )
+                )
+                or (ssl)
+                or not ssl"#
+            )
+        );
+    }
+
+    fn bytes() -> impl Strategy<Value = String> {
+        let ch = prop_oneof![
+            any::<char>().prop_map(|c| match c {
+                '"' | '\\' => format!("\\{}", c),
+                c => c.to_string(),
+            }),
+            any::<u8>().prop_map(|b| format!("\\x{:02x}", b)),
+            any::<u8>().prop_map(|b| format!("\\{:03o}", b)),
+        ];
+
+        prop_oneof![
+            vec(ch, 0..8).prop_map(|chars| format!("\"{}\"", chars.concat())),
+            vec(any::<u8>(), 1..5).prop_map(|bytes| {
+                bytes
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<Vec<_>>()
+                    .join(":")
+            }),
+        ]
+    }
+
+    fn string() -> impl Strategy<Value = String> {
+        vec(any::<char>(), 0..8).prop_map(|chars| {
+            let s: String = chars
+                .into_iter()
+                .map(|c| match c {
+                    '"' | '\\' => format!("\\{}", c),
+                    c => c.to_string(),
+                })
+                .collect();
+            format!("\"{}\"", s)
+        })
+    }
+
+    fn regex() -> impl Strategy<Value = String> {
+        let atom = select(vec![
+            "a", r"\d", "[a-z]", r#"["]"#, r#"\""#, r"\\", ".", "(x|y)", r#"[\]"]"#, "é", "a+",
+            r"\w*", "^", "$",
+        ]);
+        vec(atom, 1..5).prop_map(|atoms| format!("\"{}\"", atoms.concat()))
+    }
+
+    fn int() -> impl Strategy<Value = String> {
+        prop_oneof![
+            any::<i64>().prop_map(|n| n.to_string()),
+            (0..i64::MAX).prop_map(|n| format!("0x{:x}", n)),
+            (0..i64::MAX).prop_map(|n| format!("0{:o}", n)),
+        ]
+    }
+
+    fn int_range() -> impl Strategy<Value = String> {
+        prop_oneof![
+            any::<i64>().prop_map(|n| n.to_string()),
+            (any::<i64>(), any::<i64>()).prop_map(|(a, b)| format!("{}..{}", a.min(b), a.max(b))),
+        ]
+    }
+
+    fn float() -> impl Strategy<Value = String> {
+        let finite = || any::<f64>().prop_filter("finite", |n| n.is_finite());
+        prop_oneof![
+            finite().prop_map(|n| n.to_string()),
+            finite().prop_map(|n| format!("{:e}", n)),
+            finite().prop_map(|n| format!("{:E}", n)),
+            (-1000i32..1000).prop_map(|n| n.to_string()),
+        ]
+    }
+
+    fn float_range() -> impl Strategy<Value = String> {
+        prop_oneof![
+            float(),
+            (-1000i32..1000, 0..1000i32).prop_map(|(a, b)| format!("{}..{}.5e1", a, a.max(0) + b)),
+        ]
+    }
+
+    fn timestamp() -> impl Strategy<Value = String> {
+        let offset = prop_oneof![
+            Just("Z".to_owned()),
+            ("[+-]", 0..24u32, 0..60u32)
+                .prop_map(|(sign, h, m)| format!("{}{:02}:{:02}", sign, h, m)),
+        ];
+        (
+            (1..9999u32, 1..13u32, 1..29u32),
+            (0..24u32, 0..60u32, 0..60u32),
+            "(\\.[0-9]{1,12})?",
+            offset,
+        )
+            .prop_map(|((y, mo, d), (h, mi, s), fraction, offset)| {
+                format!(
+                    "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}{}",
+                    y, mo, d, h, mi, s, fraction, offset
+                )
+            })
+    }
+
+    fn duration() -> impl Strategy<Value = String> {
+        prop_oneof![
+            (any::<i64>(), "d|h|m|s|ms|us|ns").prop_map(|(n, unit)| format!("{}{}", n, unit)),
+            (0..1000u32, 0..1000u32).prop_map(|(h, ms)| format!("{}h{}ms", h, ms)),
+        ]
+    }
+
+    fn ip() -> impl Strategy<Value = String> {
+        prop_oneof![
+            any::<[u8; 4]>().prop_map(|ip| ::std::net::Ipv4Addr::from(ip).to_string()),
+            any::<[u16; 8]>().prop_map(|ip| ::std::net::Ipv6Addr::from(ip).to_string()),
+        ]
+    }
+
+    fn ip_range() -> impl Strategy<Value = String> {
+        prop_oneof![
+            ip(),
+            any::<[u8; 3]>().prop_map(|[a, b, c]| format!("{}.{}.{}.0/24", a, b, c)),
+            any::<u16>().prop_map(|a| format!("{:x}::/16", a)),
+            (any::<u32>(), any::<u32>()).prop_map(|(a, b)| {
+                let (a, b) = (a.min(b), a.max(b));
+                format!(
+                    "{}..{}",
+                    ::std::net::Ipv4Addr::from(a),
+                    ::std::net::Ipv4Addr::from(b)
+                )
+            }),
+        ]
+    }
+
+    fn mac() -> impl Strategy<Value = String> {
+        fn octets(octets: &[u8], sep: &str) -> String {
+            octets
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(sep)
+        }
+
+        prop_oneof![
+            (any::<[u8; 6]>(), ":|-").prop_map(|(mac, sep)| octets(&mac, &sep)),
+            (any::<[u8; 8]>(), ":|-").prop_map(|(mac, sep)| octets(&mac, &sep)),
+        ]
+    }
+
+    fn mac_range() -> impl Strategy<Value = String> {
+        prop_oneof![
+            mac(),
+            any::<[u8; 3]>()
+                .prop_map(|[a, b, c]| format!("{:02x}:{:02x}:{:02x}:00:00:00/24", a, b, c)),
+            (any::<u8>(), any::<u8>()).prop_map(|(a, b)| {
+                format!(
+                    "02:00:00:00:00:{:02x}..02:00:00:00:00:{:02x}",
+                    a.min(b),
+                    a.max(b)
+                )
+            }),
+        ]
+    }
+
+    fn list(item: impl Strategy<Value = String>) -> impl Strategy<Value = String> {
+        vec(item, 0..4).prop_map(|items| format!("{{{}}}", items.join(" ")))
+    }
+
+    fn comparison() -> impl Strategy<Value = &'static str> {
+        select(vec![
+            "==", "!=", ">=", "<=", ">", "<", "eq", "ne", "ge", "le", "gt", "lt",
+        ])
+    }
+
+    fn leaf() -> impl Strategy<Value = String> {
+        prop_oneof![
+            (comparison(), ip()).prop_map(|(op, ip)| format!("ip {} {}", op, ip)),
+            list(ip_range()).prop_map(|list| format!("ip in {}", list)),
+            (comparison(), bytes()).prop_map(|(op, b)| format!("host {} {}", op, b)),
+            bytes().prop_map(|b| format!("host contains {}", b)),
+            regex().prop_map(|re| format!("host matches {}", re)),
+            regex().prop_map(|re| format!("host ~ {}", re)),
+            list(bytes()).prop_map(|list| format!("host in {}", list)),
+            (comparison(), int()).prop_map(|(op, n)| format!("port {} {}", op, n)),
+            int().prop_map(|n| format!("port & {}", n)),
+            list(int_range()).prop_map(|list| format!("port in {}", list)),
+            (comparison(), float()).prop_map(|(op, n)| format!("score {} {}", op, n)),
+            list(float_range()).prop_map(|list| format!("score in {}", list)),
+            (comparison(), timestamp()).prop_map(|(op, ts)| format!("time {} {}", op, ts)),
+            list(timestamp()).prop_map(|list| format!("time in {}", list)),
+            (comparison(), duration()).prop_map(|(op, d)| format!("ttl {} {}", op, d)),
+            list(duration()).prop_map(|list| format!("ttl in {}", list)),
+            (comparison(), mac()).prop_map(|(op, mac)| format!("mac {} {}", op, mac)),
+            list(mac_range()).prop_map(|list| format!("mac in {}", list)),
+            Just("ssl".to_owned()),
+            (any::<u32>(), bytes()).prop_map(|(i, b)| format!("tags[{}] == {}", i, b)),
+            bytes().prop_map(|b| format!("tags[*] contains {}", b)),
+            bytes().prop_map(|b| format!("all tags[*] != {}", b)),
+            (string(), bytes()).prop_map(|(k, b)| format!("headers[{}] == {}", k, b)),
+            bytes().prop_map(|b| format!("lower(host) == {}", b)),
+            int().prop_map(|n| format!("len(host) > {}", n)),
+            (int(), int())
+                .prop_map(|(a, b)| format!("substring(host, {}, {}) contains \"x\"", a, b)),
+            bytes().prop_map(|b| format!("starts_with(host, {})", b)),
+            bytes().prop_map(|b| format!("concat(host, tags[0]) contains {}", b)),
+        ]
+    }
+
+    fn filter() -> impl Strategy<Value = String> {
+        leaf().prop_recursive(4, 32, 2, |inner| {
+            prop_oneof![
+                (
+                    inner.clone(),
+                    select(vec!["and", "&&", "or", "||", "xor", "^^"]),
+                    inner.clone()
+                )
+                    .prop_map(|(a, op, b)| format!("{} {} {}", a, op, b)),
+                inner.clone().prop_map(|a| format!("not {}", a)),
+                inner.clone().prop_map(|a| format!("!{}", a)),
+                inner.prop_map(|a| format!("({})", a)),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_display_round_trip(input in filter()) {
+            let ast = SCHEME.parse(&input).unwrap();
+            let formatted = ast.to_string();
+            prop_assert_eq!(SCHEME.parse(&formatted), Ok(ast.clone()), "{}", formatted);
+
+            let formatted = format!("{:#}", ast);
+            prop_assert_eq!(SCHEME.parse(&formatted), Ok(ast), "{}", formatted);
+        }
+
+        #[test]
+        fn test_deserialize_round_trip(input in filter()) {
+            let json = serde_json::to_value(SCHEME.parse(&input).unwrap()).unwrap();
+            let ast = SCHEME.deserialize_filter(&json).unwrap();
+            prop_assert_eq!(serde_json::to_value(&ast).unwrap(), json);
+
+            // parentheses are restored where needed
+            let formatted = ast.to_string();
+            prop_assert_eq!(SCHEME.parse(&formatted), Ok(ast), "{}", formatted);
+        }
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let ast = SCHEME
+            .deserialize_filter(json!({
+                "op": "And",
+                "items": [
+                    {
+                        "op": "Or",
+                        "items": [
+                            { "field": "ssl", "op": "IsTrue" },
+                            {
+                                "field": "port",
+                                "op": "OneOf",
+                                "rhs": [443, { "start": 8000, "end": 8080 }]
+                            }
+                        ]
+                    },
+                    {
+                        "op": "Not",
+                        "arg": {
+                            "field": "tags",
+                            "indexes": ["Each"],
+                            "quantifier": "All",
+                            "op": "Contains",
+                            "rhs": [1, 2]
+                        }
+                    },
+                    {
+                        "function": {
+                            "name": "starts_with",
+                            "args": [{ "field": "headers", "indexes": [{ "MapKey": "x" }] }, "a"]
+                        },
+                        "op": "IsTrue"
+                    },
+                    {
+                        "field": "ip",
+                        "op": "OneOf",
+                        "rhs": ["10.0.0.0/8", { "start": "::1", "end": "::2" }]
+                    }
+                ]
+            }))
+            .unwrap();
+
+        assert_eq!(
+            ast.to_string(),
+            r#"(ssl or port in {443 8000..8080}) and not all tags[*] contains 01:02 and starts_with(headers["x"], "a") and ip in {10.0.0.0/8 ::1..::2}"#
+        );
+
+        let seed = FilterAstSeed::new(&SCHEME);
+        let ast = seed.deserialize(&mut serde_json::Deserializer::from_str(
+            r#"{"field": "ssl", "op": "IsTrue"}"#,
+        ));
+        assert_eq!(ast.unwrap(), SCHEME.parse("ssl").unwrap());
+    }
+
+    #[test]
+    fn test_deserialize_errors() {
+        let error = |json: Value| SCHEME.deserialize_filter(&json).unwrap_err().to_string();
+
+        assert_eq!(
+            error(
+                json!({ "op": "And", "items": [{ "field": "ssl", "op": "IsTrue" }, { "field": "foo", "op": "IsTrue" }] })
+            ),
+            "unknown field at $.items[1].field"
+        );
+        assert_eq!(
+            error(json!({ "field": "port", "op": "Equal", "rhs": "80" })),
+            "expected a 64-bit integer at $.rhs"
+        );
+        assert_eq!(
+            error(json!({ "field": "port", "op": "Equal", "rhs": 9_223_372_036_854_775_808u64 })),
+            "expected a 64-bit integer at $.rhs"
+        );
+        assert_eq!(
+            error(json!({ "field": "port", "op": "Contains", "rhs": "80" })),
+            "cannot use this operation type Int at $.op"
+        );
+        assert_eq!(
+            error(json!({ "field": "port", "op": "Similar", "rhs": 80 })),
+            r#"invalid value "Similar" at $.op"#
+        );
+        assert_eq!(
+            error(json!({ "field": "port", "op": "Equal" })),
+            r#"missing property "rhs" at $"#
+        );
+        assert_eq!(
+            error(json!({ "field": "ssl", "op": "IsTrue", "quantifer": "All" })),
+            r#"unknown property "quantifer" at $.quantifer"#
+        );
+        #[cfg(feature = "regex")]
+        assert!(error(
+            json!({ "op": "Not", "arg": { "field": "host", "op": "Matches", "rhs": "(" } })
+        )
+        .ends_with(" at $.arg.rhs"));
+        assert_eq!(
+            error(
+                json!({ "field": "tags", "indexes": [{ "MapKey": "a" }], "op": "Equal", "rhs": "a" })
+            ),
+            r#"cannot access index MapKey("a") on type Array(Bytes) at $.indexes[0]"#
+        );
+        assert_eq!(
+            error(json!({ "field": "tags", "indexes": [0], "op": "Equal", "rhs": "a" })),
+            r#"expected "Each", {"ArrayIndex": ...} or {"MapKey": ...} at $.indexes[0]"#
+        );
+        assert_eq!(
+            error(
+                json!({ "field": "tags", "indexes": [{ "ArrayIndex": 0 }], "quantifier": "All", "op": "Equal", "rhs": "a" })
+            ),
+            "quantifier requires an expression with [*] index at $.quantifier"
+        );
+        assert_eq!(
+            error(json!({ "field": "port", "op": "OneOf", "rhs": [1, { "start": 5, "end": 3 }] })),
+            "incompatible range bounds at $.rhs[1]"
+        );
+        assert_eq!(
+            error(
+                json!({ "field": "ip", "op": "OneOf", "rhs": [{ "start": "::1", "end": "1.2.3.4" }] })
+            ),
+            "incompatible range bounds at $.rhs[0]"
+        );
+        assert_eq!(
+            error(json!({ "function": { "name": "len", "args": [] }, "op": "Equal", "rhs": 1 })),
+            "expected from 1 to 1 function arguments at $.function.args"
+        );
+        assert_eq!(
+            error(json!({ "function": { "name": "len", "args": [{ "field": "port" }] }, "op": "Equal", "rhs": 1 })),
+            "invalid type of argument #0: expected value of type Bytes, but got Int at $.function.args[0]"
+        );
+        assert_eq!(
+            error(
+                json!({ "function": { "name": "len", "args": [{ "field": "tags", "indexes": ["Each"] }] }, "op": "Equal", "rhs": 1 })
+            ),
+            "cannot access index Each on type Array(Bytes) at $.function.args[0].indexes[0]"
+        );
+        assert_eq!(
+            error(json!({ "function": { "name": "foo", "args": [] }, "op": "IsTrue" })),
+            "unknown function at $.function.name"
+        );
+        assert_eq!(
+            error(json!({ "op": "Or", "items": [] })),
+            "expected a non-empty array at $.items"
+        );
+        assert_eq!(error(json!([])), "expected an object at $");
+
+        let seed = FilterAstSeed::new(&SCHEME);
+        let err = seed
+            .deserialize(&mut serde_json::Deserializer::from_str(
+                r#"{"field": "foo", "op": "IsTrue"}"#,
+            ))
+            .unwrap_err();
+        assert!(err.to_string().starts_with("unknown field at $.field"));
     }
 }
@@ -1,7 +1,16 @@
-use super::{combined_expr::CombinedExpr, field_expr::FieldExpr, CompiledExpr, Expr};
+use super::{
+    combined_expr::{fmt_multiline_parens, CombinedExpr},
+    field_expr::FieldExpr,
+    trace::{ExecutionTrace, TraceNode},
+    CompiledExpr, Expr,
+};
+use deserialize::{DeserializeError, FromRawWith, JsonPath, RawValue};
+use execution_context::ExecutionContext;
+use filter_set::{SetExpr, SetLeaves};
 use lex::{expect, skip_space, Lex, LexResult, LexWith};
 use scheme::{Field, Scheme};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
 
 lex_enum!(UnaryOp {
     "not" | "!" => Not,
@@ -43,6 +52,74 @@ impl<'i, 's> LexWith<'i, &'s Scheme> for SimpleExpr<'s> {
     }
 }
 
+impl<'s> FromRawWith<&'s Scheme> for SimpleExpr<'s> {
+    fn from_raw_with(
+        value: &RawValue,
+        path: &JsonPath<'_>,
+        scheme: &'s Scheme,
+    ) -> Result<Self, DeserializeError> {
+        let object = value.as_object(path)?;
+
+        Ok(match object.get("arg") {
+            Some(arg) => {
+                object.check_keys(&["op", "arg"], path)?;
+
+                let op = object.require("op", path)?.as_enum(&path.key("op"))?;
+
+                // parentheses are not serialized, so restore them where needed
+                let arg = match CombinedExpr::from_raw_with(arg, &path.key("arg"), scheme)? {
+                    CombinedExpr::Simple(arg) => arg,
+                    arg => SimpleExpr::Parenthesized(Box::new(arg)),
+                };
+
+                SimpleExpr::Unary {
+                    op,
+                    arg: Box::new(arg),
+                }
+            }
+            None => SimpleExpr::Field(FieldExpr::from_raw_with(value, path, scheme)?),
+        })
+    }
+}
+
+impl<'s> Display for SimpleExpr<'s> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SimpleExpr::Field(op) => Display::fmt(op, f),
+            SimpleExpr::Parenthesized(op) => write!(f, "({})", op),
+            SimpleExpr::Unary {
+                op: UnaryOp::Not,
+                arg,
+            } => write!(f, "not {}", arg),
+        }
+    }
+}
+
+impl<'s> SimpleExpr<'s> {
+    /// Writes the expression breaking up parenthesized combinations into
+    /// multiple lines, see [`CombinedExpr::fmt_multiline`].
+    pub(crate) fn fmt_multiline(&self, f: &mut Formatter<'_>, indent: usize) -> fmt::Result {
+        match self {
+            SimpleExpr::Field(op) => Display::fmt(op, f),
+            SimpleExpr::Parenthesized(op) => match **op {
+                CombinedExpr::Combining { .. } => fmt_multiline_parens(op, f, indent),
+                CombinedExpr::Simple(ref op) => {
+                    f.write_str("(")?;
+                    op.fmt_multiline(f, indent)?;
+                    f.write_str(")")
+                }
+            },
+            SimpleExpr::Unary {
+                op: UnaryOp::Not,
+                arg,
+            } => {
+                f.write_str("not ")?;
+                arg.fmt_multiline(f, indent)
+            }
+        }
+    }
+}
+
 impl<'s> Expr<'s> for SimpleExpr<'s> {
     fn uses(&self, field: Field<'s>) -> bool {
         match self {
@@ -52,6 +129,38 @@ impl<'s> Expr<'s> for SimpleExpr<'s> {
         }
     }
 
+    fn lower(self, leaves: &mut SetLeaves<'s>) -> SetExpr {
+        match self {
+            SimpleExpr::Field(op) => op.lower(leaves),
+            SimpleExpr::Parenthesized(op) => op.lower(leaves),
+            SimpleExpr::Unary {
+                op: UnaryOp::Not,
+                arg,
+            } => SetExpr::Not(Box::new(arg.lower(leaves))),
+        }
+    }
+
+    fn trace<'a>(&'a self, ctx: Option<&'a ExecutionContext<'_>>) -> ExecutionTrace<'a> {
+        match self {
+            SimpleExpr::Field(op) => op.trace(ctx),
+            SimpleExpr::Parenthesized(op) => op.trace(ctx),
+            SimpleExpr::Unary { op, arg } => {
+                let arg = arg.trace(ctx);
+                let result = arg.result().map(|result| match op {
+                    UnaryOp::Not => !result,
+                });
+
+                ExecutionTrace::new(
+                    TraceNode::Unary {
+                        op: *op,
+                        arg: Box::new(arg),
+                    },
+                    result,
+                )
+            }
+        }
+    }
+
     fn compile(self) -> CompiledExpr<'s> {
         match self {
             SimpleExpr::Field(op) => op.compile(),
@@ -61,7 +170,7 @@ impl<'s> Expr<'s> for SimpleExpr<'s> {
                 arg,
             } => {
                 let arg = arg.compile();
-                CompiledExpr::new(move |ctx| !arg.execute(ctx))
+                CompiledExpr::new(move |ctx| !arg.execute_in(ctx))
             }
         }
     }
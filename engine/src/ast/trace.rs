@@ -0,0 +1,188 @@
+use super::{combined_expr::CombiningOp, field_expr::FieldExpr, simple_expr::UnaryOp};
+use serde::Serialize;
+use types::LhsValue;
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub(crate) enum TraceNode<'a> {
+    Field {
+        #[serde(flatten)]
+        expr: &'a FieldExpr<'a>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        value: Option<LhsValue<'a>>,
+    },
+    Combining {
+        op: CombiningOp,
+        items: Vec<ExecutionTrace<'a>>,
+    },
+    Unary {
+        op: UnaryOp,
+        arg: Box<ExecutionTrace<'a>>,
+    },
+}
+
+/// A trace of a filter execution produced by
+/// [`FilterAst::execute_traced`](::FilterAst::execute_traced).
+///
+/// It mirrors the structure of the filter and records for each expression
+/// whether it was evaluated and what the result was. Comparisons
+/// additionally record the value they were given, if it was set.
+///
+/// When serialized, it has the same shape as the [`FilterAst`](::FilterAst)
+/// with `evaluated`, `result` and `value` properties added to each node.
+#[derive(Debug, Serialize)]
+pub struct ExecutionTrace<'a> {
+    #[serde(flatten)]
+    node: TraceNode<'a>,
+
+    evaluated: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<bool>,
+}
+
+impl<'a> ExecutionTrace<'a> {
+    pub(crate) fn new(node: TraceNode<'a>, result: Option<bool>) -> Self {
+        ExecutionTrace {
+            node,
+            evaluated: result.is_some(),
+            result,
+        }
+    }
+
+    /// Returns the result of the expression, or `None` if it wasn't
+    /// evaluated, e.g. because of short-circuiting in `and` or `or`.
+    pub fn result(&self) -> Option<bool> {
+        self.result
+    }
+
+    /// Returns the value a comparison was performed on.
+    ///
+    /// For comparisons with `[*]` this is the value that was iterated over.
+    /// It's `None` for logical operators and comparisons that weren't
+    /// evaluated or were given no value.
+    pub fn value(&self) -> Option<&LhsValue<'a>> {
+        match &self.node {
+            TraceNode::Field { value, .. } => value.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns traces of nested expressions.
+    pub fn children(&self) -> &[ExecutionTrace<'a>] {
+        match &self.node {
+            TraceNode::Field { .. } => &[],
+            TraceNode::Combining { items, .. } => items,
+            TraceNode::Unary { arg, .. } => ::std::slice::from_ref(arg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use execution_context::ExecutionContext;
+    use filter::SchemeMismatchError;
+    use types::LhsValue;
+
+    #[test]
+    fn test_execute_traced() {
+        let scheme = Scheme! { port: Int, host: Bytes, ssl: Bool, tags: Array(Bytes) };
+
+        let ast = scheme
+            .parse(r#"port == 80 or (host contains "example" and not ssl) or tags[*] == "a""#)
+            .unwrap();
+
+        let ctx = &mut ExecutionContext::new(&scheme);
+
+        ctx.set_field_value("port", 443).unwrap();
+        ctx.set_field_value("host", "example.org").unwrap();
+        ctx.set_field_value("ssl", false).unwrap();
+
+        let trace = ast.execute_traced(ctx).unwrap();
+
+        assert_eq!(trace.result(), Some(true));
+        assert_eq!(trace.children().len(), 3);
+        assert_eq!(trace.children()[0].value(), Some(&LhsValue::Int(443)));
+        assert_eq!(trace.children()[2].result(), None);
+
+        assert_json!(
+            trace,
+            {
+                "op": "Or",
+                "items": [
+                    {
+                        "field": "port",
+                        "op": "Equal",
+                        "rhs": 80,
+                        "value": 443,
+                        "evaluated": true,
+                        "result": false
+                    },
+                    {
+                        "op": "And",
+                        "items": [
+                            {
+                                "field": "host",
+                                "op": "Contains",
+                                "rhs": "example",
+                                "value": "example.org",
+                                "evaluated": true,
+                                "result": true
+                            },
+                            {
+                                "op": "Not",
+                                "arg": {
+                                    "field": "ssl",
+                                    "op": "IsTrue",
+                                    "value": false,
+                                    "evaluated": true,
+                                    "result": false
+                                },
+                                "evaluated": true,
+                                "result": true
+                            }
+                        ],
+                        "evaluated": true,
+                        "result": true
+                    },
+                    {
+                        "field": "tags",
+                        "indexes": ["Each"],
+                        "op": "Equal",
+                        "rhs": "a",
+                        "evaluated": false
+                    }
+                ],
+                "evaluated": true,
+                "result": true
+            }
+        );
+
+        // the result always matches the compiled filter
+        ctx.set_field_value("host", "example.com").unwrap();
+        ctx.set_field_value("ssl", true).unwrap();
+
+        let trace = ast.execute_traced(ctx).unwrap();
+
+        assert_eq!(trace.result(), Some(false));
+        assert_eq!(ast.clone().compile().execute(ctx), Ok(false));
+
+        // unset fields are evaluated, but have no value
+        assert_eq!(trace.children()[2].result(), Some(false));
+        assert_eq!(trace.children()[2].value(), None);
+    }
+
+    #[test]
+    fn test_scheme_mismatch() {
+        let scheme1 = Scheme! { foo: Int };
+        let scheme2 = Scheme! { foo: Int, bar: Int };
+        let ast = scheme1.parse("foo == 42").unwrap();
+        let ctx = ExecutionContext::new(&scheme2);
+
+        assert_eq!(
+            ast.execute_traced(&ctx).map(|trace| trace.result()),
+            Err(SchemeMismatchError)
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use failure::Fail;
+use lex::{complete, Lex, LexErrorKind};
+use serde::de::{
+    self, value, Deserialize, DeserializeOwned, Deserializer, IntoDeserializer, MapAccess,
+    SeqAccess, Visitor,
+};
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    marker::PhantomData,
+};
+
+#[derive(Debug, PartialEq, Fail)]
+pub enum DeserializeErrorKind {
+    #[fail(display = "{}", _0)]
+    Invalid(String),
+
+    #[fail(display = "expected {}", _0)]
+    Expected(&'static str),
+
+    #[fail(display = "invalid value {:?}", _0)]
+    InvalidValue(String),
+
+    #[fail(display = "missing property {:?}", _0)]
+    MissingProperty(&'static str),
+
+    #[fail(display = "unknown property {:?}", _0)]
+    UnknownProperty(String),
+
+    #[fail(display = "{}", _0)]
+    Lex(#[cause] LexErrorKind),
+}
+
+impl From<LexErrorKind> for DeserializeErrorKind {
+    fn from(kind: LexErrorKind) -> Self {
+        DeserializeErrorKind::Lex(kind)
+    }
+}
+
+/// An error that occurs when deserializing an invalid representation of a
+/// [`FilterAst`](::FilterAst).
+///
+/// It points to the invalid value with a path like `$.items[1].field`.
+#[derive(Debug, PartialEq)]
+pub struct DeserializeError {
+    kind: DeserializeErrorKind,
+    path: String,
+}
+
+impl Error for DeserializeError {}
+
+impl Display for DeserializeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.path)
+    }
+}
+
+impl DeserializeError {
+    /// Returns the path to the invalid value, e.g. `$.items[1].field`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// A path to a value that is being deserialized, used to point errors at it.
+#[derive(Debug, Clone, Copy)]
+pub enum JsonPath<'a> {
+    Root,
+    Key(&'a JsonPath<'a>, &'a str),
+    Index(&'a JsonPath<'a>, usize),
+}
+
+impl<'a> Display for JsonPath<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonPath::Root => f.write_str("$"),
+            JsonPath::Key(parent, key) => write!(f, "{}.{}", parent, key),
+            JsonPath::Index(parent, index) => write!(f, "{}[{}]", parent, index),
+        }
+    }
+}
+
+impl<'a> JsonPath<'a> {
+    pub fn key(&'a self, key: &'a str) -> Self {
+        JsonPath::Key(self, key)
+    }
+
+    pub fn index(&'a self, index: usize) -> Self {
+        JsonPath::Index(self, index)
+    }
+
+    pub fn error(&self, kind: impl Into<DeserializeErrorKind>) -> DeserializeError {
+        DeserializeError {
+            kind: kind.into(),
+            path: self.to_string(),
+        }
+    }
+}
+
+/// A JSON-like value that the serialized form is read into before being
+/// validated, because meaning of some properties depends on others, e.g.
+/// `rhs` is interpreted according to the type of `field`.
+#[derive(Debug)]
+pub enum RawValue {
+    Int(i128),
+    Float(f64),
+    String(String),
+    Array(Vec<RawValue>),
+    Object(RawObject),
+    /// A null or a boolean, neither of which can be used in a filter.
+    Other,
+}
+
+/// Properties of an object in their original order.
+#[derive(Debug)]
+pub struct RawObject(Vec<(String, RawValue)>);
+
+impl RawObject {
+    pub fn get(&self, key: &str) -> Option<&RawValue> {
+        self.0
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn require(
+        &self,
+        key: &'static str,
+        path: &JsonPath<'_>,
+    ) -> Result<&RawValue, DeserializeError> {
+        self.get(key)
+            .ok_or_else(|| path.error(DeserializeErrorKind::MissingProperty(key)))
+    }
+
+    /// Checks that the object doesn't have any properties except the given
+    /// ones, so that misspelled properties don't get silently ignored.
+    pub fn check_keys(
+        &self,
+        allowed: &[&str],
+        path: &JsonPath<'_>,
+    ) -> Result<(), DeserializeError> {
+        match self.0.iter().find(|(name, _)| !allowed.contains(&&**name)) {
+            Some((name, _)) => Err(path
+                .key(name)
+                .error(DeserializeErrorKind::UnknownProperty(name.clone()))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl RawValue {
+    pub fn as_object(&self, path: &JsonPath<'_>) -> Result<&RawObject, DeserializeError> {
+        match self {
+            RawValue::Object(object) => Ok(object),
+            _ => Err(path.error(DeserializeErrorKind::Expected("an object"))),
+        }
+    }
+
+    pub fn as_array(&self, path: &JsonPath<'_>) -> Result<&[RawValue], DeserializeError> {
+        match self {
+            RawValue::Array(array) => Ok(array),
+            _ => Err(path.error(DeserializeErrorKind::Expected("an array"))),
+        }
+    }
+
+    pub fn as_str(&self, path: &JsonPath<'_>) -> Result<&str, DeserializeError> {
+        match self {
+            RawValue::String(s) => Ok(s),
+            _ => Err(path.error(DeserializeErrorKind::Expected("a string"))),
+        }
+    }
+
+    /// Returns an integer if it fits into `T`, and otherwise reports that the
+    /// `expected` kind of value was required.
+    pub fn as_int<T: TryFrom<i128>>(
+        &self,
+        path: &JsonPath<'_>,
+        expected: &'static str,
+    ) -> Result<T, DeserializeError> {
+        match self {
+            RawValue::Int(num) => T::try_from(*num).ok(),
+            _ => None,
+        }
+        .ok_or_else(|| path.error(DeserializeErrorKind::Expected(expected)))
+    }
+
+    /// Returns a variant of a fieldless enum with the same name as the string.
+    pub fn as_enum<T: DeserializeOwned>(&self, path: &JsonPath<'_>) -> Result<T, DeserializeError> {
+        let name = self.as_str(path)?;
+        T::deserialize(name.into_deserializer()).map_err(|_: value::Error| {
+            path.error(DeserializeErrorKind::InvalidValue(name.to_owned()))
+        })
+    }
+}
+
+struct RawValueVisitor;
+
+impl<'de> Visitor<'de> for RawValueVisitor {
+    type Value = RawValue;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<RawValue, E> {
+        Ok(RawValue::Other)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<RawValue, E> {
+        Ok(RawValue::Other)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<RawValue, D::Error> {
+        RawValue::deserialize(deserializer)
+    }
+
+    fn visit_bool<E: de::Error>(self, _value: bool) -> Result<RawValue, E> {
+        Ok(RawValue::Other)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<RawValue, E> {
+        Ok(RawValue::Int(value.into()))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<RawValue, E> {
+        Ok(RawValue::Int(value.into()))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<RawValue, E> {
+        Ok(RawValue::Float(value))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<RawValue, E> {
+        Ok(RawValue::String(value.to_owned()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<RawValue, E> {
+        Ok(RawValue::String(value))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<RawValue, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(RawValue::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RawValue, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(RawValue::Object(RawObject(entries)))
+    }
+}
+
+impl<'de> Deserialize<'de> for RawValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(RawValueVisitor)
+    }
+}
+
+/// A serde visitor that reads a string in the filter syntax.
+pub(crate) struct LexVisitor<T>(&'static str, PhantomData<T>);
+
+impl<T> LexVisitor<T> {
+    /// Creates a visitor that describes the expected value as `expecting`.
+    pub fn new(expecting: &'static str) -> Self {
+        LexVisitor(expecting, PhantomData)
+    }
+}
+
+impl<'de, T: for<'i> Lex<'i>> Visitor<'de> for LexVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<T, E> {
+        complete(T::lex(value)).map_err(|(kind, _)| E::custom(kind))
+    }
+}
+
+/// Converts a [`RawValue`] into a typed value.
+///
+/// This is the counterpart of [`Lex`](::lex::Lex) for serialized values.
+pub trait FromRaw: Sized {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError>;
+}
+
+/// Converts a [`RawValue`] into a typed value with some extra context.
+///
+/// This is the counterpart of [`LexWith`](::lex::LexWith) for serialized
+/// values.
+pub trait FromRawWith<E>: Sized {
+    fn from_raw_with(
+        value: &RawValue,
+        path: &JsonPath<'_>,
+        extra: E,
+    ) -> Result<Self, DeserializeError>;
+}
+
+impl<T: FromRaw, E> FromRawWith<E> for T {
+    fn from_raw_with(
+        value: &RawValue,
+        path: &JsonPath<'_>,
+        _extra: E,
+    ) -> Result<Self, DeserializeError> {
+        Self::from_raw(value, path)
+    }
+}
+
+#[test]
+fn test_raw_value() {
+    let value: RawValue =
+        serde_json::from_str(r#"{ "a": [1, -2, 1.5, "x", null, true], "b": {} }"#).unwrap();
+
+    let path = JsonPath::Root;
+    let object = value.as_object(&path).unwrap();
+
+    let a_path = path.key("a");
+    let a = object
+        .require("a", &path)
+        .unwrap()
+        .as_array(&a_path)
+        .unwrap();
+
+    assert_eq!(a[0].as_int::<u8>(&a_path.index(0), "a byte"), Ok(1));
+    assert_eq!(
+        a[1].as_int::<u8>(&a_path.index(1), "a byte"),
+        Err(DeserializeError {
+            kind: DeserializeErrorKind::Expected("a byte"),
+            path: "$.a[1]".to_owned(),
+        })
+    );
+    assert_eq!(
+        a[2].as_int::<i32>(&a_path.index(2), "an integer")
+            .unwrap_err()
+            .to_string(),
+        "expected an integer at $.a[2]"
+    );
+    assert_eq!(a[3].as_str(&a_path.index(3)), Ok("x"));
+
+    assert_eq!(
+        object.require("c", &path).unwrap_err().to_string(),
+        r#"missing property "c" at $"#
+    );
+    assert_eq!(object.check_keys(&["a", "b"], &path), Ok(()));
+    assert_eq!(
+        object.check_keys(&["a"], &path).unwrap_err().to_string(),
+        r#"unknown property "b" at $.b"#
+    );
+}
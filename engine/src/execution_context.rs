@@ -1,6 +1,13 @@
 use failure::Fail;
-use scheme::{Field, Scheme};
-use types::{GetType, LhsValue, Type};
+use filter::SchemeMismatchError;
+use rhs_types::Timestamp;
+use scheme::{Field, Scheme, UnknownFieldError};
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, Visitor};
+use std::{
+    fmt::{self, Formatter},
+    sync::{Mutex, OnceLock},
+};
+use types::{GetType, LhsValue, LhsValueSeed, Type};
 
 /// An error that occurs if the type of the value for the field doesn't
 /// match the type specified in the [`Scheme`](struct@Scheme).
@@ -16,6 +23,56 @@ pub struct FieldValueTypeMismatchError {
     pub value_type: Type,
 }
 
+/// An error that occurs when setting a field value in the
+/// [`ExecutionContext`](struct@ExecutionContext).
+#[derive(Debug, PartialEq, Fail)]
+pub enum SetFieldValueError {
+    /// The field isn't registered in the [`Scheme`](struct@Scheme).
+    #[fail(display = "{}", _0)]
+    UnknownField(#[cause] UnknownFieldError),
+
+    /// The value has a different type than the field.
+    #[fail(display = "{}", _0)]
+    TypeMismatch(#[cause] FieldValueTypeMismatchError),
+
+    /// The field belongs to a different [`Scheme`](struct@Scheme).
+    #[fail(display = "{}", _0)]
+    SchemeMismatch(#[cause] SchemeMismatchError),
+}
+
+impl From<UnknownFieldError> for SetFieldValueError {
+    fn from(err: UnknownFieldError) -> Self {
+        SetFieldValueError::UnknownField(err)
+    }
+}
+
+impl From<FieldValueTypeMismatchError> for SetFieldValueError {
+    fn from(err: FieldValueTypeMismatchError) -> Self {
+        SetFieldValueError::TypeMismatch(err)
+    }
+}
+
+impl From<SchemeMismatchError> for SetFieldValueError {
+    fn from(err: SchemeMismatchError) -> Self {
+        SetFieldValueError::SchemeMismatch(err)
+    }
+}
+
+type ValueProvider<'e> = Box<dyn 'e + Send + FnOnce() -> Option<LhsValue<'e>>>;
+
+// A runtime value of a single field.
+enum FieldValue<'e> {
+    Unset,
+    Set(LhsValue<'e>),
+    // The value is computed by the provider on the first access and then
+    // cached for the lifetime of the slot. Both are thread-safe, so that the
+    // context can still be shared between threads executing filters.
+    Lazy {
+        provider: Mutex<Option<ValueProvider<'e>>>,
+        value: OnceLock<Option<LhsValue<'e>>>,
+    },
+}
+
 /// An execution context stores an associated [`Scheme`](struct@Scheme) and a
 /// set of runtime values to execute [`Filter`](::Filter) against.
 ///
@@ -23,7 +80,8 @@ pub struct FieldValueTypeMismatchError {
 /// index-based access to values for a filter during execution.
 pub struct ExecutionContext<'e> {
     scheme: &'e Scheme,
-    values: Box<[Option<LhsValue<'e>>]>,
+    values: Box<[FieldValue<'e>]>,
+    now: OnceLock<Timestamp>,
 }
 
 impl<'e> ExecutionContext<'e> {
@@ -33,7 +91,10 @@ impl<'e> ExecutionContext<'e> {
     pub fn new<'s: 'e>(scheme: &'s Scheme) -> Self {
         ExecutionContext {
             scheme,
-            values: vec![None; scheme.get_field_count()].into(),
+            values: (0..scheme.get_field_count())
+                .map(|_| FieldValue::Unset)
+                .collect(),
+            now: OnceLock::new(),
         }
     }
 
@@ -42,44 +103,236 @@ impl<'e> ExecutionContext<'e> {
         self.scheme
     }
 
-    pub(crate) fn get_field_value_unchecked(&self, field: Field<'e>) -> &LhsValue<'e> {
+    /// Returns the time that functions like `now()` use as the current one.
+    ///
+    /// Unless set with [`set_now`](Self::set_now), it's read from the system
+    /// clock on the first call and then stays the same, so that all filters
+    /// executed against the context agree on it.
+    pub fn now(&self) -> Timestamp {
+        *self.now.get_or_init(Timestamp::now)
+    }
+
+    /// Sets the time that functions like `now()` use as the current one,
+    /// e.g. the time when a request was received, or a fixed time in tests.
+    pub fn set_now(&mut self, now: Timestamp) {
+        self.now = OnceLock::from(now);
+    }
+
+    /// Removes values and providers of all fields and resets the current
+    /// time, keeping the allocated memory for reuse.
+    pub fn clear(&mut self) {
+        for value in self.values.iter_mut() {
+            *value = FieldValue::Unset;
+        }
+        self.now = OnceLock::new();
+    }
+
+    /// Removes the value or the provider of a given field name.
+    pub fn unset_field_value(&mut self, name: &str) -> Result<(), UnknownFieldError> {
+        let field = self.scheme.get_field(name)?;
+        self.values[field.index()] = FieldValue::Unset;
+        Ok(())
+    }
+
+    /// Removes all values and rebinds the context to a new lifetime and the
+    /// given scheme, keeping the allocated memory when possible.
+    ///
+    /// Values borrow data for the lifetime of the context, so a context
+    /// filled with values from one buffer can't be filled with values from
+    /// another, shorter-lived one. Rebinding allows to keep a context per
+    /// worker and reuse it for each request instead:
+    ///
+    /// ```
+    /// # use wirefilter::{ExecutionContext, Scheme};
+    /// let scheme: Scheme = Scheme! { http.host: Bytes };
+    /// let filter = scheme.parse(r#"http.host == "example.org""#).unwrap().compile();
+    /// let mut pooled = ExecutionContext::new(&scheme);
+    ///
+    /// for &(request, expected) in &[("example.org", true), ("example.com", false)] {
+    ///     let host = request.to_string();
+    ///
+    ///     let mut ctx = pooled.rebind(&scheme);
+    ///     ctx.set_field_value("http.host", host.as_str()).unwrap();
+    ///     assert_eq!(filter.execute(&ctx), Ok(expected));
+    ///
+    ///     pooled = ctx.rebind(&scheme);
+    /// }
+    /// ```
+    pub fn rebind<'f>(self, scheme: &'f Scheme) -> ExecutionContext<'f> {
+        // All values are dropped, so the allocation can be reused for values
+        // with any other lifetime.
+        let mut values: Vec<FieldValue<'f>> = self
+            .values
+            .into_vec()
+            .into_iter()
+            .map(|_| FieldValue::Unset)
+            .collect();
+
+        values.resize_with(scheme.get_field_count(), || FieldValue::Unset);
+
+        ExecutionContext {
+            scheme,
+            values: values.into_boxed_slice(),
+            now: OnceLock::new(),
+        }
+    }
+
+    /// Returns a value of the field, or `None` if it wasn't set.
+    ///
+    /// Following Wireshark, any comparison against a missing value should
+    /// resolve to `false`.
+    pub(crate) fn get_field_value_unchecked(&self, field: Field<'_>) -> Option<&LhsValue<'e>> {
         // This is safe because this code is reachable only from Filter::execute
         // which already performs the scheme compatibility check, but check that
         // invariant holds in the future at least in the debug mode.
         debug_assert!(self.scheme() == field.scheme());
 
-        // For now we panic in this, but later we are going to align behaviour
-        // with wireshark: resolve all subexpressions that don't have RHS value
-        // to `false`.
-        self.values[field.index()].as_ref().unwrap_or_else(|| {
-            panic!(
-                "Field {} was registered but not given a value",
-                field.name()
-            );
-        })
+        match &self.values[field.index()] {
+            FieldValue::Unset => None,
+            FieldValue::Set(value) => Some(value),
+            FieldValue::Lazy { provider, value } => value
+                .get_or_init(|| {
+                    // don't hold the lock while the provider runs, so that a
+                    // panicking provider doesn't poison it
+                    let provider = provider.lock().unwrap().take();
+                    provider
+                        .and_then(|provider| provider())
+                        // a value of a wrong type is treated as missing
+                        .filter(|value| value.get_type() == field.get_type())
+                })
+                .as_ref(),
+        }
+    }
+
+    /// Returns whether the field has a value.
+    ///
+    /// Providers are invoked to find out whether they return a value, which
+    /// is cached for the following accesses.
+    pub(crate) fn has_field_value(&self, field: Field<'_>) -> bool {
+        self.get_field_value_unchecked(field).is_some()
     }
 
     /// Sets a runtime value for a given field name.
+    ///
+    /// Fails if the field isn't registered in the scheme or has a different
+    /// type.
     pub fn set_field_value<'v: 'e, V: Into<LhsValue<'v>>>(
         &mut self,
         name: &str,
         value: V,
-    ) -> Result<(), FieldValueTypeMismatchError> {
-        let field = self.scheme.get_field_index(name).unwrap();
+    ) -> Result<(), SetFieldValueError> {
+        let field = self.scheme.get_field(name)?;
+        self.set_by_field(field, value)
+    }
+
+    /// Sets a runtime value for a given field handle.
+    ///
+    /// This is a faster alternative to [`set_field_value`](Self::set_field_value)
+    /// for hot paths, as the field is looked up only once with
+    /// [`Scheme::get_field`](::Scheme::get_field).
+    ///
+    /// Fails if the field belongs to a different scheme or has a different
+    /// type.
+    pub fn set_by_field<'v: 'e, V: Into<LhsValue<'v>>>(
+        &mut self,
+        field: Field<'e>,
+        value: V,
+    ) -> Result<(), SetFieldValueError> {
+        if field.scheme() != self.scheme {
+            return Err(SchemeMismatchError.into());
+        }
+
         let value = value.into();
 
         let field_type = field.get_type();
         let value_type = value.get_type();
 
         if field_type == value_type {
-            self.values[field.index()] = Some(value);
+            self.values[field.index()] = FieldValue::Set(value);
             Ok(())
         } else {
             Err(FieldValueTypeMismatchError {
                 field_type,
                 value_type,
-            })
+            }
+            .into())
+        }
+    }
+
+    /// Sets a provider that lazily computes a runtime value for a given
+    /// field name.
+    ///
+    /// The provider is invoked at most once, on the first access to the field
+    /// during [`Filter::execute`](::Filter::execute), and its result is cached
+    /// for the rest of the context lifetime. Fields that are never reached by
+    /// a filter don't invoke their providers at all, except during a
+    /// [strict execution](::Filter::execute_strict), which checks all fields
+    /// used by the filter upfront.
+    ///
+    /// If the provider returns `None` or a value of a type different from the
+    /// one specified in the scheme, the field is treated as missing.
+    ///
+    /// The provider must be `Send`, as it's invoked by whichever thread first
+    /// executes a filter that reaches the field.
+    ///
+    /// Fails if the field isn't registered in the scheme.
+    pub fn set_field_value_provider<F>(
+        &mut self,
+        name: &str,
+        provider: F,
+    ) -> Result<(), UnknownFieldError>
+    where
+        F: 'e + Send + FnOnce() -> Option<LhsValue<'e>>,
+    {
+        let field = self.scheme.get_field(name)?;
+
+        self.values[field.index()] = FieldValue::Lazy {
+            provider: Mutex::new(Some(Box::new(provider))),
+            value: OnceLock::new(),
+        };
+
+        Ok(())
+    }
+}
+
+/// Sets values of fields from a map of field names to values, e.g. a JSON
+/// object like `{"http.host": "example.org", "tcp.port": 443}`.
+///
+/// Values are read according to the types of fields in the scheme, see
+/// [`LhsValueSeed`](::LhsValueSeed), and can borrow from the input. Fields
+/// that aren't mentioned keep their previous values.
+impl<'de: 'e, 'e> DeserializeSeed<'de> for &mut ExecutionContext<'e> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(ContextVisitor(self))
+    }
+}
+
+struct ContextVisitor<'c, 'e>(&'c mut ExecutionContext<'e>);
+
+impl<'de: 'e, 'e, 'c> Visitor<'de> for ContextVisitor<'c, 'e> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a map of field names to values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<(), A::Error> {
+        let ctx = self.0;
+
+        while let Some(name) = access.next_key::<String>()? {
+            let field = ctx
+                .scheme
+                .get_field(&name)
+                .map_err(|_| de::Error::custom(format_args!("unknown field {:?}", name)))?;
+
+            let value = access.next_value_seed(LhsValueSeed(&field.get_type()))?;
+
+            ctx.values[field.index()] = FieldValue::Set(value);
         }
+
+        Ok(())
     }
 }
 
@@ -91,9 +344,245 @@ fn test_field_value_type_mismatch() {
 
     assert_eq!(
         ctx.set_field_value("foo", LhsValue::Bool(false)),
-        Err(FieldValueTypeMismatchError {
-            field_type: Type::Int,
-            value_type: Type::Bool
-        })
+        Err(SetFieldValueError::TypeMismatch(
+            FieldValueTypeMismatchError {
+                field_type: Type::Int,
+                value_type: Type::Bool
+            }
+        ))
     );
 }
+
+#[test]
+fn test_unknown_field() {
+    let scheme = Scheme! { foo: Int };
+
+    let mut ctx = ExecutionContext::new(&scheme);
+
+    assert_eq!(
+        ctx.set_field_value("bar", 42),
+        Err(SetFieldValueError::UnknownField(UnknownFieldError))
+    );
+    assert_eq!(
+        ctx.set_field_value_provider("bar", || Some(LhsValue::Int(42))),
+        Err(UnknownFieldError)
+    );
+}
+
+#[test]
+fn test_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<ExecutionContext<'static>>();
+}
+
+#[test]
+fn test_field_value_provider() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let scheme = Scheme! { foo: Int, bar: Int, baz: Bool };
+
+    let calls = AtomicUsize::new(0);
+
+    let mut ctx = ExecutionContext::new(&scheme);
+
+    ctx.set_field_value_provider("foo", || {
+        calls.fetch_add(1, Ordering::SeqCst);
+        Some(LhsValue::Int(42))
+    })
+    .unwrap();
+
+    ctx.set_field_value_provider("bar", || None).unwrap();
+    ctx.set_field_value_provider("baz", || Some(LhsValue::Int(1)))
+        .unwrap();
+
+    let foo = scheme.get_field("foo").unwrap();
+    let bar = scheme.get_field("bar").unwrap();
+    let baz = scheme.get_field("baz").unwrap();
+
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    assert_eq!(ctx.has_field_value(foo), true);
+    assert_eq!(ctx.get_field_value_unchecked(foo), Some(&LhsValue::Int(42)));
+    assert_eq!(ctx.get_field_value_unchecked(foo), Some(&LhsValue::Int(42)));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    assert!(!ctx.has_field_value(bar));
+    assert_eq!(ctx.get_field_value_unchecked(bar), None);
+    assert!(!ctx.has_field_value(baz));
+    assert_eq!(ctx.get_field_value_unchecked(baz), None);
+}
+
+#[test]
+fn test_deserialize() {
+    use serde_json::Deserializer;
+
+    let scheme = Scheme! { host: Bytes, port: Int, ip: Ip, tags: Array(Bytes) };
+
+    let mut ctx = ExecutionContext::new(&scheme);
+
+    let json = r#"{ "host": "escaped \u2764", "port": 443, "tags": [] }"#;
+    ctx.deserialize(&mut Deserializer::from_str(json)).unwrap();
+
+    let host = scheme.get_field("host").unwrap();
+    let port = scheme.get_field("port").unwrap();
+    let ip = scheme.get_field("ip").unwrap();
+    let tags = scheme.get_field("tags").unwrap();
+
+    assert_eq!(
+        ctx.get_field_value_unchecked(host),
+        Some(&LhsValue::from("escaped ❤"))
+    );
+    assert_eq!(
+        ctx.get_field_value_unchecked(port),
+        Some(&LhsValue::Int(443))
+    );
+    assert_eq!(ctx.get_field_value_unchecked(ip), None);
+    assert_eq!(
+        ctx.get_field_value_unchecked(tags),
+        Some(&LhsValue::Array(::lhs_types::Array::new(Type::Bytes)))
+    );
+
+    let json = r#"{ "port": 80, "foo": 1 }"#;
+    assert_eq!(
+        ctx.deserialize(&mut Deserializer::from_str(json))
+            .unwrap_err()
+            .to_string(),
+        r#"unknown field "foo" at line 1 column 19"#
+    );
+    assert_eq!(
+        ctx.get_field_value_unchecked(port),
+        Some(&LhsValue::Int(80))
+    );
+
+    let json = r#"{ "port": "80" }"#;
+    assert!(ctx.deserialize(&mut Deserializer::from_str(json)).is_err());
+}
+
+#[test]
+fn test_deserialize_mac() {
+    use rhs_types::Mac;
+    use serde_json::Deserializer;
+
+    let scheme = Scheme! { eth.src: Mac };
+    let eth_src = scheme.get_field("eth.src").unwrap();
+
+    let value = LhsValue::Mac(Mac::from([0x00, 0x1b, 0x21, 0x3c, 0x9d, 0xf8]));
+    let serialized = serde_json::to_string(&value).unwrap();
+
+    // MAC addresses are strings in JSON, so only the scheme tells them apart
+    // from bytes
+    let untagged: LhsValue<'_> = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(untagged.get_type(), Type::Bytes);
+
+    let json = format!(r#"{{ "eth.src": {} }}"#, serialized);
+
+    let mut ctx = ExecutionContext::new(&scheme);
+    ctx.deserialize(&mut Deserializer::from_str(&json)).unwrap();
+
+    assert_eq!(ctx.get_field_value_unchecked(eth_src), Some(&value));
+}
+
+#[test]
+fn test_set_by_field() {
+    let scheme = Scheme! { foo: Int, bar: Bytes };
+    let other_scheme = Scheme! { foo: Int };
+
+    let foo = scheme.get_field("foo").unwrap();
+    let bar = scheme.get_field("bar").unwrap();
+
+    let mut ctx = ExecutionContext::new(&scheme);
+
+    assert_eq!(ctx.set_by_field(foo, 42), Ok(()));
+    assert_eq!(ctx.set_by_field(bar, "a"), Ok(()));
+    assert_eq!(ctx.get_field_value_unchecked(foo), Some(&LhsValue::Int(42)));
+    assert_eq!(
+        ctx.get_field_value_unchecked(bar),
+        Some(&LhsValue::from("a"))
+    );
+
+    assert_eq!(
+        ctx.set_by_field(bar, 42),
+        Err(SetFieldValueError::TypeMismatch(
+            FieldValueTypeMismatchError {
+                field_type: Type::Bytes,
+                value_type: Type::Int
+            }
+        ))
+    );
+
+    assert_eq!(
+        ctx.set_by_field(other_scheme.get_field("foo").unwrap(), 42),
+        Err(SetFieldValueError::SchemeMismatch(SchemeMismatchError))
+    );
+}
+
+#[test]
+fn test_clear() {
+    let scheme = Scheme! { foo: Int, bar: Bytes };
+
+    let foo = scheme.get_field("foo").unwrap();
+    let bar = scheme.get_field("bar").unwrap();
+
+    let mut ctx = ExecutionContext::new(&scheme);
+
+    ctx.set_field_value("foo", 42).unwrap();
+    ctx.set_field_value_provider("bar", || Some("a".into()))
+        .unwrap();
+
+    assert_eq!(ctx.unset_field_value("foo"), Ok(()));
+    assert!(!ctx.has_field_value(foo));
+    assert!(ctx.has_field_value(bar));
+    assert_eq!(ctx.unset_field_value("baz"), Err(UnknownFieldError));
+
+    ctx.set_field_value("foo", 42).unwrap();
+    ctx.set_now(Timestamp::from_unix_secs(0));
+    ctx.clear();
+
+    assert!(!ctx.has_field_value(foo));
+    assert!(!ctx.has_field_value(bar));
+    assert_ne!(ctx.now(), Timestamp::from_unix_secs(0));
+}
+
+#[test]
+fn test_now() {
+    let scheme = Scheme! { foo: Int };
+
+    let mut ctx = ExecutionContext::new(&scheme);
+
+    // the system clock is read only once
+    let now = ctx.now();
+    assert_eq!(ctx.now(), now);
+
+    ctx.set_now(Timestamp::from_unix_secs(1_554_121_800));
+    assert_eq!(ctx.now(), Timestamp::from_unix_secs(1_554_121_800));
+}
+
+#[test]
+fn test_rebind() {
+    let scheme = Scheme! { foo: Int, bar: Bytes };
+    let other_scheme = Scheme! { foo: Int, bar: Bytes, baz: Bool };
+
+    let bar = scheme.get_field("bar").unwrap();
+
+    let mut pooled = ExecutionContext::new(&scheme);
+
+    for value in &["a", "b"] {
+        let value = value.to_string();
+
+        let mut ctx = pooled.rebind(&scheme);
+        assert!(!ctx.has_field_value(bar));
+
+        ctx.set_field_value("bar", value.as_str()).unwrap();
+        assert_eq!(
+            ctx.get_field_value_unchecked(bar),
+            Some(&LhsValue::from(value.as_str()))
+        );
+
+        pooled = ctx.rebind(&scheme);
+    }
+
+    let mut ctx = pooled.rebind(&other_scheme);
+    assert_eq!(ctx.set_field_value("baz", true), Ok(()));
+    assert_eq!(ctx.values.len(), 3);
+}
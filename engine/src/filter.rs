@@ -1,6 +1,8 @@
 use execution_context::ExecutionContext;
 use failure::Fail;
-use scheme::Scheme;
+use lists::ListSnapshots;
+use scheme::{Field, Scheme};
+use std::ops::Deref;
 
 /// An error that occurs if filter and provided [`ExecutionContext`] have
 /// different [schemes](struct@Scheme).
@@ -8,23 +10,94 @@ use scheme::Scheme;
 #[fail(display = "execution context doesn't match the scheme with which filter was parsed")]
 pub struct SchemeMismatchError;
 
+/// An error that occurs if a field used by the filter wasn't given a value in
+/// the [`ExecutionContext`] during a [strict execution](Filter::execute_strict).
+#[derive(Debug, PartialEq, Fail)]
+#[fail(display = "field {} was registered but not given a value", field_name)]
+pub struct MissingFieldError {
+    /// The name of the missing field.
+    pub field_name: String,
+}
+
+/// An error that occurs during a [strict execution](Filter::execute_strict).
+#[derive(Debug, PartialEq, Fail)]
+pub enum ExecutionError {
+    /// Filter and provided [`ExecutionContext`] have different schemes.
+    #[fail(display = "{}", _0)]
+    SchemeMismatch(#[cause] SchemeMismatchError),
+
+    /// One of the fields used by the filter is not set.
+    #[fail(display = "{}", _0)]
+    MissingField(#[cause] MissingFieldError),
+}
+
+impl From<SchemeMismatchError> for ExecutionError {
+    fn from(err: SchemeMismatchError) -> Self {
+        ExecutionError::SchemeMismatch(err)
+    }
+}
+
+impl From<MissingFieldError> for ExecutionError {
+    fn from(err: MissingFieldError) -> Self {
+        ExecutionError::MissingField(err)
+    }
+}
+
 // Each AST expression node gets compiled into CompiledExpr. Therefore, Filter
 // essentialy is a public API facade for a tree of CompiledExprs. When filter
 // gets executed it calls `execute` method on its root expression which then
 // under the hood propagates field values to its leafs by recursively calling
 // their `execute` methods and aggregating results into a single boolean value
 // as recursion unwinds.
-pub(crate) struct CompiledExpr<'s>(Box<dyn 's + Fn(&ExecutionContext<'s>) -> bool>);
+//
+// Closures accept contexts with values of any lifetime, so that the same
+// filter can be executed against contexts built from short-lived buffers.
+pub(crate) struct CompiledExpr<'s>(Box<dyn 's + for<'e> Fn(&Execution<'_, 'e>) -> bool>);
 
 impl<'s> CompiledExpr<'s> {
     /// Creates a compiled expression IR from a generic closure.
-    pub(crate) fn new(closure: impl 's + Fn(&ExecutionContext<'s>) -> bool) -> Self {
+    pub(crate) fn new(closure: impl 's + for<'e> Fn(&Execution<'_, 'e>) -> bool) -> Self {
         CompiledExpr(Box::new(closure))
     }
 
     /// Executes a filter against a provided context with values.
-    pub fn execute(&self, ctx: &ExecutionContext<'s>) -> bool {
-        self.0(ctx)
+    pub fn execute(&self, ctx: &ExecutionContext<'_>) -> bool {
+        self.execute_in(&Execution::new(ctx))
+    }
+
+    /// Executes a filter as a part of an ongoing execution.
+    pub fn execute_in(&self, execution: &Execution<'_, '_>) -> bool {
+        self.0(execution)
+    }
+}
+
+// State of a single execution shared by all expressions of a filter, or of
+// a filter set. It dereferences to the context with values of fields.
+pub(crate) struct Execution<'c, 'e> {
+    ctx: &'c ExecutionContext<'e>,
+    lists: ListSnapshots,
+}
+
+impl<'c, 'e> Execution<'c, 'e> {
+    pub fn new(ctx: &'c ExecutionContext<'e>) -> Self {
+        Execution {
+            ctx,
+            lists: ListSnapshots::default(),
+        }
+    }
+
+    /// Returns the contents of lists as they were on their first lookup
+    /// during this execution.
+    pub fn lists(&self) -> &ListSnapshots {
+        &self.lists
+    }
+}
+
+impl<'c, 'e> Deref for Execution<'c, 'e> {
+    type Target = ExecutionContext<'e>;
+
+    fn deref(&self) -> &ExecutionContext<'e> {
+        self.ctx
     }
 }
 
@@ -46,27 +119,63 @@ impl<'s> CompiledExpr<'s> {
 pub struct Filter<'s> {
     root_expr: CompiledExpr<'s>,
     scheme: &'s Scheme,
+    fields: Box<[Field<'s>]>,
 }
 
 impl<'s> Filter<'s> {
     /// Creates a compiled expression IR from a generic closure.
-    pub(crate) fn new(root_expr: CompiledExpr<'s>, scheme: &'s Scheme) -> Self {
-        Filter { root_expr, scheme }
+    pub(crate) fn new(
+        root_expr: CompiledExpr<'s>,
+        scheme: &'s Scheme,
+        fields: Box<[Field<'s>]>,
+    ) -> Self {
+        Filter {
+            root_expr,
+            scheme,
+            fields,
+        }
     }
 
     /// Executes a filter against a provided context with values.
-    pub fn execute(&self, ctx: &ExecutionContext<'s>) -> Result<bool, SchemeMismatchError> {
+    ///
+    /// Like in Wireshark, any comparison against a field that wasn't given a
+    /// value resolves to `false`, including `!=`, while `not` simply negates
+    /// the result of its argument. For example, if `port` is not set, both
+    /// `port == 80` and `port != 80` are `false`, while `not port == 80` is
+    /// `true`.
+    pub fn execute(&self, ctx: &ExecutionContext<'_>) -> Result<bool, SchemeMismatchError> {
         if self.scheme == ctx.scheme() {
             Ok(self.root_expr.execute(ctx))
         } else {
             Err(SchemeMismatchError)
         }
     }
+
+    /// Executes a filter against a provided context with values, but fails
+    /// if any of the fields used by the filter wasn't given a value.
+    pub fn execute_strict(&self, ctx: &ExecutionContext<'_>) -> Result<bool, ExecutionError> {
+        if self.scheme != ctx.scheme() {
+            return Err(SchemeMismatchError.into());
+        }
+
+        if let Some(field) = self
+            .fields
+            .iter()
+            .find(|&&field| !ctx.has_field_value(field))
+        {
+            return Err(MissingFieldError {
+                field_name: field.name().to_owned(),
+            }
+            .into());
+        }
+
+        Ok(self.root_expr.execute(ctx))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::SchemeMismatchError;
+    use super::{ExecutionError, MissingFieldError, SchemeMismatchError};
     use execution_context::ExecutionContext;
 
     #[test]
@@ -78,4 +187,92 @@ mod tests {
 
         assert_eq!(filter.execute(&ctx), Err(SchemeMismatchError));
     }
+
+    #[test]
+    fn test_missing_field() {
+        let scheme = Scheme! { foo: Int, bar: Bytes, baz: Bool };
+        let ctx = &mut ExecutionContext::new(&scheme);
+        ctx.set_field_value("foo", 42).unwrap();
+
+        let execute = |filter: &str| scheme.parse(filter).unwrap().compile().execute(ctx);
+
+        assert_eq!(execute("bar == \"a\""), Ok(false));
+        assert_eq!(execute("bar != \"a\""), Ok(false));
+        assert_eq!(execute("bar contains \"a\""), Ok(false));
+        assert_eq!(execute("bar in {\"a\" \"b\"}"), Ok(false));
+        assert_eq!(execute("baz"), Ok(false));
+        assert_eq!(execute("not bar == \"a\""), Ok(true));
+        assert_eq!(execute("not bar != \"a\""), Ok(true));
+        assert_eq!(execute("not baz"), Ok(true));
+        assert_eq!(execute("foo == 42 or bar == \"a\""), Ok(true));
+        assert_eq!(execute("foo == 42 and bar != \"a\""), Ok(false));
+    }
+
+    #[test]
+    fn test_execute_strict() {
+        let other_scheme = Scheme! { foo: Int };
+        let scheme = Scheme! { foo: Int, bar: Bytes, baz: Bool };
+        let filter = scheme.parse("foo == 42 or bar == \"a\"").unwrap().compile();
+        let ctx = &mut ExecutionContext::new(&scheme);
+
+        ctx.set_field_value("foo", 42).unwrap();
+
+        assert_eq!(
+            filter.execute_strict(ctx),
+            Err(ExecutionError::MissingField(MissingFieldError {
+                field_name: "bar".to_owned()
+            }))
+        );
+
+        // only fields used by the filter are required
+        ctx.set_field_value("bar", "b").unwrap();
+
+        assert_eq!(filter.execute_strict(ctx), Ok(true));
+
+        let ctx = ExecutionContext::new(&other_scheme);
+
+        assert_eq!(
+            filter.execute_strict(&ctx),
+            Err(ExecutionError::SchemeMismatch(SchemeMismatchError))
+        );
+    }
+
+    #[test]
+    fn test_field_value_provider() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let bar_calls = AtomicUsize::new(0);
+        let scheme = Scheme! { foo: Int, bar: Bytes };
+        let filter = scheme.parse("foo == 42 or bar == \"a\"").unwrap().compile();
+        let ctx = &mut ExecutionContext::new(&scheme);
+
+        ctx.set_field_value_provider("foo", || Some(42.into()))
+            .unwrap();
+        ctx.set_field_value_provider("bar", || {
+            bar_calls.fetch_add(1, Ordering::SeqCst);
+            Some("a".into())
+        })
+        .unwrap();
+
+        assert_eq!(filter.execute(ctx), Ok(true));
+
+        // `bar` is never reached because `foo` already matches
+        assert_eq!(bar_calls.load(Ordering::SeqCst), 0);
+
+        // but the strict check resolves all used fields
+        assert_eq!(filter.execute_strict(ctx), Ok(true));
+        assert_eq!(filter.execute_strict(ctx), Ok(true));
+        assert_eq!(bar_calls.load(Ordering::SeqCst), 1);
+
+        // providers that don't return a value leave their fields missing
+        ctx.set_field_value_provider("bar", || None).unwrap();
+
+        assert_eq!(filter.execute(ctx), Ok(true));
+        assert_eq!(
+            filter.execute_strict(ctx),
+            Err(ExecutionError::MissingField(MissingFieldError {
+                field_name: "bar".to_owned()
+            }))
+        );
+    }
 }
@@ -0,0 +1,495 @@
+use aho_corasick::AhoCorasick;
+use ast::{Expr, FieldExpr, FilterAst, IndexExpr};
+use execution_context::ExecutionContext;
+use filter::{CompiledExpr, Execution, SchemeMismatchError};
+use fnv::FnvBuildHasher;
+#[cfg(feature = "regex")]
+use rhs_types::RegexSet;
+use scheme::Scheme;
+use std::{collections::HashMap, fmt::Display};
+use types::LhsValue;
+
+// A filter lowered into a tree of combinators, where each leaf is an index
+// into the list of unique field expressions of the whole set.
+pub(crate) enum SetExpr {
+    Leaf(usize),
+    Not(Box<SetExpr>),
+    And(Box<[SetExpr]>),
+    Or(Box<[SetExpr]>),
+    Xor(Box<[SetExpr]>),
+}
+
+// Field expressions can't be hashed, so instead they are looked up by their
+// canonical syntax, and compared only with ones that are written the same.
+type SyntaxIndex = HashMap<String, Vec<usize>, FnvBuildHasher>;
+
+// Returns an index of an item equal to the given one among `items`, if any,
+// or adds it to the index as `new_index` otherwise.
+fn find_or_index<T: PartialEq + Display>(
+    index: &mut SyntaxIndex,
+    items: impl Fn(usize) -> T,
+    item: T,
+    new_index: usize,
+) -> Option<usize> {
+    let candidates = index.entry(item.to_string()).or_default();
+
+    match candidates.iter().find(|&&i| items(i) == item) {
+        Some(&i) => Some(i),
+        None => {
+            candidates.push(new_index);
+            None
+        }
+    }
+}
+
+// Collects unique field expressions from all filters of a set.
+#[derive(Default)]
+pub(crate) struct SetLeaves<'s> {
+    exprs: Vec<FieldExpr<'s>>,
+    index: SyntaxIndex,
+}
+
+impl<'s> SetLeaves<'s> {
+    /// Returns an index of the given expression, adding it to the list only
+    /// if an identical one wasn't seen before.
+    pub(crate) fn insert(&mut self, expr: FieldExpr<'s>) -> usize {
+        let exprs = &self.exprs;
+        let new_index = exprs.len();
+
+        match find_or_index(&mut self.index, |i| &exprs[i], &expr, new_index) {
+            Some(index) => index,
+            None => {
+                self.exprs.push(expr);
+                new_index
+            }
+        }
+    }
+}
+
+// A way to resolve results of several leaves at once.
+enum GroupMatcher {
+    // `contains` comparisons with needles in the order of leaves.
+    Contains(AhoCorasick),
+    // `matches` comparisons with regexes in the order of leaves.
+    #[cfg(feature = "regex")]
+    Matches(RegexSet),
+}
+
+// A group of leaves sharing the same LHS, which is resolved only once.
+struct LeafGroup<'s> {
+    lhs: IndexExpr<'s>,
+    leaves: Box<[usize]>,
+    matcher: GroupMatcher,
+}
+
+impl<'s> LeafGroup<'s> {
+    fn execute(&self, ctx: &Execution<'_, '_>, results: &mut [Option<bool>]) {
+        for &leaf in self.leaves.iter() {
+            results[leaf] = Some(false);
+        }
+
+        let value = match self.lhs.execute(ctx) {
+            Some(LhsValue::Bytes(bytes)) => bytes,
+            Some(_) => unreachable!(),
+            None => return,
+        };
+
+        match &self.matcher {
+            GroupMatcher::Contains(searcher) => {
+                for m in searcher.find_overlapping_iter(&*value) {
+                    results[self.leaves[m.pattern().as_usize()]] = Some(true);
+                }
+            }
+            #[cfg(feature = "regex")]
+            GroupMatcher::Matches(regex_set) => {
+                for index in regex_set.matches(&value) {
+                    results[self.leaves[index]] = Some(true);
+                }
+            }
+        }
+    }
+}
+
+// Groups leaves selected by `split`, which returns their LHS and the RHS of
+// the comparison, by the LHS and builds a matcher for each group from RHS
+// values in the order of leaves.
+fn group_leaves<'e, 's: 'e, T>(
+    exprs: &'e [FieldExpr<'s>],
+    split: impl Fn(&'e FieldExpr<'s>) -> Option<(&'e IndexExpr<'s>, T)>,
+    build: impl Fn(Vec<T>) -> Option<GroupMatcher>,
+) -> Vec<LeafGroup<'s>> {
+    let mut groups: Vec<(&IndexExpr<'s>, Vec<usize>, Vec<T>)> = Vec::new();
+    let mut lhs_index = SyntaxIndex::default();
+
+    for (index, expr) in exprs.iter().enumerate() {
+        if let Some((lhs, rhs)) = split(expr) {
+            let new_group = groups.len();
+
+            match find_or_index(&mut lhs_index, |i| groups[i].0, lhs, new_group) {
+                Some(group) => {
+                    let (_, leaves, values) = &mut groups[group];
+                    leaves.push(index);
+                    values.push(rhs);
+                }
+                None => groups.push((lhs, vec![index], vec![rhs])),
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        // a single comparison is faster to perform on its own
+        .filter(|(_, leaves, _)| leaves.len() > 1)
+        .filter_map(|(lhs, leaves, values)| {
+            // if the matcher can't be built, e.g. because it's too large,
+            // leaves are simply executed one by one
+            let matcher = build(values)?;
+
+            Some(LeafGroup {
+                lhs: lhs.clone(),
+                leaves: leaves.into_boxed_slice(),
+                matcher,
+            })
+        })
+        .collect()
+}
+
+// Groups `contains` leaves by their LHS, so that all needles for the same
+// value can be found in a single pass.
+fn group_contains<'s>(exprs: &[FieldExpr<'s>]) -> Vec<LeafGroup<'s>> {
+    group_leaves(exprs, FieldExpr::as_contains, |needles| {
+        AhoCorasick::new(needles).ok().map(GroupMatcher::Contains)
+    })
+}
+
+// Groups `matches` leaves by their LHS, so that all regexes for the same
+// value are matched in a single pass.
+#[cfg(feature = "regex")]
+fn group_matches<'s>(exprs: &[FieldExpr<'s>]) -> Vec<LeafGroup<'s>> {
+    group_leaves(exprs, FieldExpr::as_matches, |regexes| {
+        RegexSet::new(regexes).ok().map(GroupMatcher::Matches)
+    })
+}
+
+#[cfg(not(feature = "regex"))]
+fn group_matches<'s>(_exprs: &[FieldExpr<'s>]) -> Vec<LeafGroup<'s>> {
+    Vec::new()
+}
+
+enum SetLeaf<'s> {
+    Expr(CompiledExpr<'s>),
+    Group(usize),
+}
+
+/// A set of filters compiled together for a batch execution.
+///
+/// Identical comparisons shared by multiple filters, e.g. the same
+/// `http.host contains "example"` check used by several rules, are executed
+/// only once per [`execute`](FilterSet::execute) call.
+///
+/// Moreover, all `contains` checks on the same field, as well as all
+/// `matches` checks when the `regex` feature is enabled, are performed
+/// together with a single scan of the field value.
+pub struct FilterSet<'s> {
+    scheme: &'s Scheme,
+    filters: Box<[SetExpr]>,
+    leaves: Box<[SetLeaf<'s>]>,
+    groups: Box<[LeafGroup<'s>]>,
+}
+
+impl<'s> FilterSet<'s> {
+    /// Compiles a collection of [`FilterAst`]s associated with the same
+    /// scheme into a [`FilterSet`].
+    ///
+    /// Each filter is identified by its position in the given collection.
+    pub fn new(
+        scheme: &'s Scheme,
+        filters: impl IntoIterator<Item = FilterAst<'s>>,
+    ) -> Result<Self, SchemeMismatchError> {
+        let mut leaves = SetLeaves::default();
+
+        let filters = filters
+            .into_iter()
+            .map(|filter| {
+                if filter.scheme() == scheme {
+                    Ok(filter.lower(&mut leaves))
+                } else {
+                    Err(SchemeMismatchError)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .into_boxed_slice();
+
+        let groups = group_contains(&leaves.exprs)
+            .into_iter()
+            .chain(group_matches(&leaves.exprs))
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+
+        let mut leaf_groups = vec![None; leaves.exprs.len()];
+
+        for (index, group) in groups.iter().enumerate() {
+            for &leaf in group.leaves.iter() {
+                leaf_groups[leaf] = Some(index);
+            }
+        }
+
+        let leaves = leaves
+            .exprs
+            .into_iter()
+            .zip(leaf_groups)
+            .map(|(expr, group)| match group {
+                Some(group) => SetLeaf::Group(group),
+                None => SetLeaf::Expr(expr.compile()),
+            })
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+
+        Ok(FilterSet {
+            scheme,
+            filters,
+            leaves,
+            groups,
+        })
+    }
+
+    /// Returns the number of filters in the set.
+    pub fn len(&self) -> usize {
+        self.filters.len()
+    }
+
+    /// Returns `true` if the set contains no filters.
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    fn execute_expr(
+        &self,
+        expr: &SetExpr,
+        ctx: &Execution<'_, '_>,
+        results: &mut [Option<bool>],
+    ) -> bool {
+        match expr {
+            SetExpr::Leaf(index) => {
+                if let Some(result) = results[*index] {
+                    return result;
+                }
+
+                match &self.leaves[*index] {
+                    SetLeaf::Expr(expr) => {
+                        let result = expr.execute_in(ctx);
+                        results[*index] = Some(result);
+                        result
+                    }
+                    SetLeaf::Group(group) => {
+                        self.groups[*group].execute(ctx, results);
+                        results[*index].unwrap()
+                    }
+                }
+            }
+            SetExpr::Not(arg) => !self.execute_expr(arg, ctx, results),
+            SetExpr::And(items) => items
+                .iter()
+                .all(|item| self.execute_expr(item, ctx, results)),
+            SetExpr::Or(items) => items
+                .iter()
+                .any(|item| self.execute_expr(item, ctx, results)),
+            SetExpr::Xor(items) => items.iter().fold(false, |acc, item| {
+                acc ^ self.execute_expr(item, ctx, results)
+            }),
+        }
+    }
+
+    /// Executes all filters against a provided context with values and
+    /// returns positions of the matching ones in ascending order.
+    ///
+    /// Each filter behaves exactly as if it was compiled and executed on its
+    /// own with [`Filter::execute`](::Filter::execute).
+    pub fn execute(&self, ctx: &ExecutionContext<'_>) -> Result<Vec<usize>, SchemeMismatchError> {
+        if self.scheme != ctx.scheme() {
+            return Err(SchemeMismatchError);
+        }
+
+        let ctx = &Execution::new(ctx);
+        let mut results = vec![None; self.leaves.len()];
+
+        Ok(self
+            .filters
+            .iter()
+            .enumerate()
+            .filter(|(_, filter)| self.execute_expr(filter, ctx, &mut results))
+            .map(|(index, _)| index)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FilterSet;
+    use execution_context::ExecutionContext;
+    use filter::SchemeMismatchError;
+
+    #[test]
+    fn test_execute() {
+        let scheme = Scheme! { http.host: Bytes, port: Int, ssl: Bool };
+
+        let filters = [
+            r#"http.host contains "example" and port == 443"#,
+            r#"http.host contains "example" or ssl"#,
+            r#"not http.host contains "example""#,
+            r#"(port == 443) xor ssl"#,
+            r#"port in {80 443} && http.host == "example.org""#,
+        ];
+
+        let set = FilterSet::new(
+            &scheme,
+            filters.iter().map(|filter| scheme.parse(filter).unwrap()),
+        )
+        .unwrap();
+
+        assert_eq!(set.len(), 5);
+
+        // shared comparisons are compiled only once
+        assert_eq!(set.leaves.len(), 5);
+
+        let ctx = &mut ExecutionContext::new(&scheme);
+
+        ctx.set_field_value("http.host", "example.org").unwrap();
+        ctx.set_field_value("port", 443).unwrap();
+        ctx.set_field_value("ssl", false).unwrap();
+
+        assert_eq!(set.execute(ctx), Ok(vec![0, 1, 3, 4]));
+
+        ctx.set_field_value("http.host", "example.com").unwrap();
+        ctx.set_field_value("ssl", true).unwrap();
+
+        assert_eq!(set.execute(ctx), Ok(vec![0, 1]));
+
+        for (index, filter) in filters.iter().enumerate() {
+            let filter = scheme.parse(filter).unwrap().compile();
+
+            assert_eq!(
+                filter.execute(ctx),
+                Ok(set.execute(ctx).unwrap().contains(&index))
+            );
+        }
+    }
+
+    #[test]
+    fn test_contains_group() {
+        let scheme = Scheme! { http.uri: Bytes, http.host: Bytes, tags: Array(Bytes) };
+
+        let filters = [
+            r#"http.uri contains "/admin""#,
+            r#"http.uri contains "admin" and http.host contains "example""#,
+            r#"http.uri contains "min/x""#,
+            r#"not http.uri contains "login""#,
+            r#"http.uri contains "/admin" or http.uri contains "" "#,
+            r#"tags[*] contains "admin""#,
+        ];
+
+        let set = FilterSet::new(
+            &scheme,
+            filters.iter().map(|filter| scheme.parse(filter).unwrap()),
+        )
+        .unwrap();
+
+        // only `http.uri` needles are grouped
+        assert_eq!(set.groups.len(), 1);
+        assert_eq!(&*set.groups[0].leaves, &[0, 1, 3, 4, 5]);
+
+        let ctx = &mut ExecutionContext::new(&scheme);
+
+        assert_eq!(set.execute(ctx), Ok(vec![3]));
+
+        ctx.set_field_value("http.uri", "/admin/xyz").unwrap();
+        ctx.set_field_value("http.host", "example.org").unwrap();
+
+        assert_eq!(set.execute(ctx), Ok(vec![0, 1, 2, 3, 4]));
+
+        ctx.set_field_value("http.uri", "/login").unwrap();
+
+        assert_eq!(set.execute(ctx), Ok(vec![4]));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_matches_group() {
+        let scheme = Scheme! { http.ua: Bytes, http.host: Bytes };
+
+        let filters = [
+            r#"http.ua matches "^curl/""#,
+            r#"http.ua ~ "bot\b" and http.host matches "example""#,
+            r#"not http.ua matches "(?i)mozilla""#,
+            r#"http.ua contains "curl" or http.ua ~ "\d+\.\d+""#,
+        ];
+
+        let set = FilterSet::new(
+            &scheme,
+            filters.iter().map(|filter| scheme.parse(filter).unwrap()),
+        )
+        .unwrap();
+
+        // only `http.ua` regexes are grouped
+        assert_eq!(set.groups.len(), 1);
+        assert_eq!(&*set.groups[0].leaves, &[0, 1, 3, 5]);
+
+        let ctx = &mut ExecutionContext::new(&scheme);
+
+        assert_eq!(set.execute(ctx), Ok(vec![2]));
+
+        ctx.set_field_value("http.ua", "curl/7.64.1").unwrap();
+        ctx.set_field_value("http.host", "example.org").unwrap();
+
+        assert_eq!(set.execute(ctx), Ok(vec![0, 2, 3]));
+
+        ctx.set_field_value("http.ua", "Mozilla/5.0 (compatible; Googlebot)")
+            .unwrap();
+
+        assert_eq!(set.execute(ctx), Ok(vec![1, 3]));
+
+        for (index, filter) in filters.iter().enumerate() {
+            let filter = scheme.parse(filter).unwrap().compile();
+
+            assert_eq!(
+                filter.execute(ctx),
+                Ok(set.execute(ctx).unwrap().contains(&index))
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_field() {
+        let scheme = Scheme! { foo: Int, bar: Int };
+
+        let set = FilterSet::new(
+            &scheme,
+            vec![
+                scheme.parse("foo == 1").unwrap(),
+                scheme.parse("foo != 1").unwrap(),
+                scheme.parse("not foo == 1").unwrap(),
+                scheme.parse("bar == 2").unwrap(),
+            ],
+        )
+        .unwrap();
+
+        let ctx = &mut ExecutionContext::new(&scheme);
+
+        ctx.set_field_value("bar", 2).unwrap();
+
+        assert_eq!(set.execute(ctx), Ok(vec![2, 3]));
+    }
+
+    #[test]
+    fn test_scheme_mismatch() {
+        let scheme1 = Scheme! { foo: Int };
+        let scheme2 = Scheme! { foo: Int, bar: Int };
+
+        assert!(FilterSet::new(&scheme1, vec![scheme2.parse("foo == 1").unwrap()]).is_err());
+
+        let set = FilterSet::new(&scheme1, vec![scheme1.parse("foo == 1").unwrap()]).unwrap();
+        let ctx = ExecutionContext::new(&scheme2);
+
+        assert_eq!(set.execute(&ctx), Err(SchemeMismatchError));
+    }
+}
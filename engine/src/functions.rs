@@ -0,0 +1,139 @@
+use rhs_types::Timestamp;
+use std::fmt::{self, Debug, Formatter};
+use types::{GetType, LhsValue, Type};
+
+/// An iterator over function arguments as [`LhsValue`]s.
+///
+/// Optional arguments that were omitted in the filter are filled in with
+/// their default values, so the iterator always yields one value for each
+/// function parameter.
+pub type FunctionArgs<'i, 'a> = &'i mut dyn ExactSizeIterator<Item = LhsValue<'a>>;
+
+/// Defines what kind of argument a function parameter accepts.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FunctionArgKind {
+    /// Allows only literal values, e.g. `"example.org"` or `10`.
+    Literal,
+    /// Allows only fields and results of other function calls.
+    Field,
+}
+
+/// Defines a mandatory function parameter.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FunctionParam {
+    /// How the argument can be specified.
+    pub arg_kind: FunctionArgKind,
+    /// The type of the argument.
+    pub val_type: Type,
+}
+
+/// Defines an optional function parameter.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FunctionOptParam {
+    /// How the argument can be specified.
+    pub arg_kind: FunctionArgKind,
+    /// The value that is used when the argument is omitted.
+    ///
+    /// It also defines the type of the argument.
+    pub default_value: LhsValue<'static>,
+}
+
+type FunctionPtr =
+    dyn for<'a> Fn(FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> + Sync + Send + 'static;
+
+type ClockedFunctionPtr =
+    dyn for<'a> Fn(Timestamp, FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> + Sync + Send + 'static;
+
+enum FunctionPtrKind {
+    Pure(Box<FunctionPtr>),
+    Clocked(Box<ClockedFunctionPtr>),
+}
+
+/// A Rust implementation of a function.
+///
+/// It receives values of all the arguments and returns either a value of the
+/// declared return type, or `None` if the result is undefined, in which case
+/// any comparison with the result evaluates to `false`.
+pub struct FunctionImpl(FunctionPtrKind);
+
+impl FunctionImpl {
+    /// Creates a function implementation from a closure.
+    pub fn new<F>(func: F) -> Self
+    where
+        F: for<'a> Fn(FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> + Sync + Send + 'static,
+    {
+        FunctionImpl(FunctionPtrKind::Pure(Box::new(func)))
+    }
+
+    /// Creates an implementation of a function that depends on the current
+    /// time, e.g. `now()`.
+    ///
+    /// Before the arguments, the closure receives the time of the
+    /// [`ExecutionContext`](::ExecutionContext) the filter is executed
+    /// against, see [`ExecutionContext::now`](::ExecutionContext::now).
+    pub fn with_clock<F>(func: F) -> Self
+    where
+        F: for<'a> Fn(Timestamp, FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>>
+            + Sync
+            + Send
+            + 'static,
+    {
+        FunctionImpl(FunctionPtrKind::Clocked(Box::new(func)))
+    }
+
+    /// Calls the function with provided arguments.
+    ///
+    /// Functions created with [`with_clock`](Self::with_clock) are given the
+    /// current time of the system clock.
+    pub fn execute<'a>(&self, args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+        self.execute_with_clock(Timestamp::now, args)
+    }
+
+    // The clock is only read for functions that need it.
+    pub(crate) fn execute_with_clock<'a>(
+        &self,
+        now: impl FnOnce() -> Timestamp,
+        args: FunctionArgs<'_, 'a>,
+    ) -> Option<LhsValue<'a>> {
+        match &self.0 {
+            FunctionPtrKind::Pure(func) => func(args),
+            FunctionPtrKind::Clocked(func) => func(now(), args),
+        }
+    }
+}
+
+impl Debug for FunctionImpl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            FunctionPtrKind::Pure(func) => write!(f, "FunctionImpl({:p})", func),
+            FunctionPtrKind::Clocked(func) => write!(f, "FunctionImpl({:p})", func),
+        }
+    }
+}
+
+/// Defines a function that can be registered in a
+/// [`Scheme`](struct@::Scheme) and called from filters, e.g.
+/// `lower(http.host) == "example.org"`.
+#[derive(Debug)]
+pub struct Function {
+    /// Mandatory parameters.
+    pub params: Vec<FunctionParam>,
+    /// Optional parameters that follow the mandatory ones.
+    pub opt_params: Vec<FunctionOptParam>,
+    /// The type of the returned value.
+    pub return_type: Type,
+    /// The actual implementation.
+    pub implementation: FunctionImpl,
+}
+
+impl Function {
+    pub(crate) fn get_param(&self, index: usize) -> Option<(FunctionArgKind, Type)> {
+        if let Some(param) = self.params.get(index) {
+            return Some((param.arg_kind, param.val_type.clone()));
+        }
+
+        self.opt_params
+            .get(index - self.params.len())
+            .map(|param| (param.arg_kind, param.default_value.get_type()))
+    }
+}
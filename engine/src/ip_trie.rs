@@ -0,0 +1,300 @@
+use range_set::RangeSet;
+use std::{
+    marker::PhantomData,
+    net::{Ipv4Addr, Ipv6Addr},
+    ops::RangeInclusive,
+};
+
+/// Number of address bits consumed by each level of the trie.
+const STRIDE: u32 = 6;
+
+/// An address that can be stored in an [`IpTrie`].
+pub trait TrieKey: Copy + Ord {
+    /// Number of bits in the address.
+    const BITS: u32;
+
+    /// Returns the address as a number.
+    fn to_bits(self) -> u128;
+}
+
+impl TrieKey for Ipv4Addr {
+    const BITS: u32 = 32;
+
+    fn to_bits(self) -> u128 {
+        u32::from(self).into()
+    }
+}
+
+impl TrieKey for Ipv6Addr {
+    const BITS: u32 = 128;
+
+    fn to_bits(self) -> u128 {
+        self.into()
+    }
+}
+
+/// Returns a mask with the lowest `n` bits set.
+fn low_bits(n: u32) -> u128 {
+    if n >= 128 {
+        !0
+    } else {
+        (1 << n) - 1
+    }
+}
+
+/// Splits an inclusive range of numbers with `bits` significant bits into
+/// the smallest list of aligned prefixes that cover it, and calls `f` with
+/// each prefix and its length.
+fn split_into_prefixes(range: RangeInclusive<u128>, bits: u32, mut f: impl FnMut(u128, u32)) {
+    let (mut start, end) = range.into_inner();
+
+    loop {
+        // the largest block that starts at `start` and doesn't go past `end`
+        let mut size = if start == 0 {
+            bits
+        } else {
+            start.trailing_zeros().min(bits)
+        };
+        while start + low_bits(size) > end {
+            size -= 1;
+        }
+
+        f(start, bits - size);
+
+        let last = start + low_bits(size);
+        if last >= end {
+            break;
+        }
+        start = last + 1;
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Node {
+    /// Bitmap of chunks that are fully covered by the set.
+    leaves: u64,
+    /// Bitmap of chunks that are partially covered and have a child node.
+    children: u64,
+    /// Index of the first child node; the rest follow it in the same order
+    /// as bits in `children`.
+    base: u32,
+}
+
+/// A compressed multibit trie of IP prefixes in the style of [Poptrie].
+///
+/// Each node covers 6 bits of an address, and stores which of the
+/// 64 possible values of these bits are in the set and which ones continue
+/// in a child node as bitmaps. Children of a node are stored next to each
+/// other, so a child is found by counting set bits before it, and lookups
+/// take at most one memory access per level regardless of the size of the
+/// set.
+///
+/// Unlike a [`RangeSet`], this doesn't degrade with millions of ranges, but
+/// takes more memory for each of them.
+///
+/// [Poptrie]: https://conferences.sigcomm.org/sigcomm/2015/pdf/papers/p57.pdf
+pub struct IpTrie<T> {
+    nodes: Vec<Node>,
+    ty: PhantomData<T>,
+}
+
+impl<T: TrieKey> IpTrie<T> {
+    /// Returns the first chunk of a left-aligned key.
+    fn chunk(key: u128) -> u32 {
+        (key >> (128 - STRIDE)) as u32
+    }
+
+    /// Fills in the node at `index` with the given left-aligned prefixes,
+    /// which must be sorted, disjoint and share the first `depth` bits.
+    fn build(&mut self, index: usize, prefixes: &[(u128, u32)], depth: u32) {
+        let mut node = Node {
+            leaves: 0,
+            children: 0,
+            base: self.nodes.len() as u32,
+        };
+        let mut groups = Vec::new();
+
+        let mut rest = prefixes;
+        while let Some(&(prefix, len)) = rest.first() {
+            let chunk = Self::chunk(prefix << depth);
+
+            if len <= depth + STRIDE {
+                // a short prefix covers several chunks at once
+                let count = 1u32 << (depth + STRIDE - len);
+                node.leaves |= (low_bits(count) as u64) << chunk;
+                rest = &rest[1..];
+            } else {
+                let group_len = rest
+                    .iter()
+                    .take_while(|&&(prefix, len)| {
+                        len > depth + STRIDE && Self::chunk(prefix << depth) == chunk
+                    })
+                    .count();
+                node.children |= 1 << chunk;
+                groups.push(&rest[..group_len]);
+                rest = &rest[group_len..];
+            }
+        }
+
+        let base = node.base as usize;
+        self.nodes[index] = node;
+        self.nodes.resize(
+            base + groups.len(),
+            Node {
+                leaves: 0,
+                children: 0,
+                base: 0,
+            },
+        );
+
+        for (i, group) in groups.into_iter().enumerate() {
+            self.build(base + i, group, depth + STRIDE);
+        }
+    }
+
+    /// Checks whether the address is in the set.
+    pub fn contains(&self, value: &T) -> bool {
+        let mut key = value.to_bits() << (128 - T::BITS);
+        let mut node = &self.nodes[0];
+
+        loop {
+            let bit = 1u64 << Self::chunk(key);
+
+            if node.leaves & bit != 0 {
+                return true;
+            }
+
+            if node.children & bit == 0 {
+                return false;
+            }
+
+            let offset = (node.children & (bit - 1)).count_ones();
+            node = &self.nodes[node.base as usize + offset as usize];
+            key <<= STRIDE;
+        }
+    }
+}
+
+impl<T: TrieKey> From<RangeSet<T>> for IpTrie<T> {
+    fn from(ranges: RangeSet<T>) -> Self {
+        // ranges in a `RangeSet` are already sorted and merged, so prefixes
+        // they are split into are sorted and disjoint too
+        let mut prefixes = Vec::new();
+        for range in ranges.ranges() {
+            let range = range.start().to_bits()..=range.end().to_bits();
+            split_into_prefixes(range, T::BITS, |prefix, len| {
+                prefixes.push((prefix << (128 - T::BITS), len));
+            });
+        }
+
+        let mut trie = IpTrie {
+            nodes: vec![Node {
+                leaves: 0,
+                children: 0,
+                base: 0,
+            }],
+            ty: PhantomData,
+        };
+        trie.build(0, &prefixes, 0);
+        trie
+    }
+}
+
+impl<T: TrieKey> From<Vec<RangeInclusive<T>>> for IpTrie<T> {
+    fn from(ranges: Vec<RangeInclusive<T>>) -> Self {
+        RangeSet::from(ranges).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::{collection::vec, prelude::*};
+
+    #[test]
+    fn test_split_into_prefixes() {
+        let split = |range, bits| {
+            let mut prefixes = Vec::new();
+            split_into_prefixes(range, bits, |prefix, len| prefixes.push((prefix, len)));
+            prefixes
+        };
+
+        assert_eq!(split(0..=!0, 128), vec![(0, 0)]);
+        assert_eq!(split(0..=255, 8), vec![(0, 0)]);
+        assert_eq!(split(10..=10, 8), vec![(10, 8)]);
+        assert_eq!(
+            split(1..=14, 4),
+            vec![(1, 4), (2, 3), (4, 2), (8, 2), (12, 3), (14, 4)]
+        );
+    }
+
+    #[test]
+    fn test_contains() {
+        let trie = IpTrie::from(vec![
+            Ipv4Addr::new(10, 0, 0, 0)..=Ipv4Addr::new(10, 255, 255, 255),
+            Ipv4Addr::new(192, 168, 0, 1)..=Ipv4Addr::new(192, 168, 0, 1),
+            Ipv4Addr::new(192, 168, 1, 5)..=Ipv4Addr::new(192, 168, 2, 7),
+        ]);
+
+        assert!(trie.contains(&Ipv4Addr::new(10, 1, 2, 3)));
+        assert!(trie.contains(&Ipv4Addr::new(192, 168, 0, 1)));
+        assert!(trie.contains(&Ipv4Addr::new(192, 168, 1, 200)));
+        assert!(trie.contains(&Ipv4Addr::new(192, 168, 2, 7)));
+        assert!(!trie.contains(&Ipv4Addr::new(9, 255, 255, 255)));
+        assert!(!trie.contains(&Ipv4Addr::new(192, 168, 0, 2)));
+        assert!(!trie.contains(&Ipv4Addr::new(192, 168, 2, 8)));
+
+        let trie = IpTrie::from(vec![Ipv6Addr::from(0)..=Ipv6Addr::from(!0)]);
+        assert!(trie.contains(&Ipv6Addr::from(12345)));
+
+        let trie = IpTrie::<Ipv6Addr>::from(vec![]);
+        assert!(!trie.contains(&Ipv6Addr::from(12345)));
+    }
+
+    fn ranges<T: Copy + Ord>(bounds: Vec<(T, T)>) -> Vec<RangeInclusive<T>> {
+        bounds
+            .into_iter()
+            .map(|(a, b)| if a <= b { a..=b } else { b..=a })
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn test_same_as_range_set_v4(
+            bounds in vec((any::<u32>(), any::<u32>()), 0..20),
+            values in vec(any::<u32>(), 100),
+        ) {
+            let ranges = ranges(
+                bounds
+                    .into_iter()
+                    .map(|(a, b)| (Ipv4Addr::from(a), Ipv4Addr::from((b & 0xFFFF) ^ a)))
+                    .collect(),
+            );
+            let range_set = RangeSet::from(ranges.clone());
+            let trie = IpTrie::from(ranges.clone());
+
+            for value in values.into_iter().map(Ipv4Addr::from).chain(ranges.iter().map(|r| *r.end())) {
+                prop_assert_eq!(trie.contains(&value), range_set.contains(&value), "{}", value);
+            }
+        }
+
+        #[test]
+        fn test_same_as_range_set_v6(
+            bounds in vec((any::<u128>(), any::<u128>()), 0..20),
+            values in vec(any::<u128>(), 100),
+        ) {
+            let ranges = ranges(
+                bounds
+                    .into_iter()
+                    .map(|(a, b)| (Ipv6Addr::from(a), Ipv6Addr::from((b >> 64) ^ a)))
+                    .collect(),
+            );
+            let range_set = RangeSet::from(ranges.clone());
+            let trie = IpTrie::from(ranges.clone());
+
+            for value in values.into_iter().map(Ipv6Addr::from).chain(ranges.iter().map(|r| *r.end())) {
+                prop_assert_eq!(trie.contains(&value), range_set.contains(&value), "{}", value);
+            }
+        }
+    }
+}
@@ -1,9 +1,10 @@
+use ast::FieldIndex;
 use cidr::NetworkParseError;
 use failure::Fail;
 use rhs_types::RegexError;
-use scheme::UnknownFieldError;
-use std::num::ParseIntError;
-use types::Type;
+use scheme::{UnknownFieldError, UnknownFunctionError, UnknownListError};
+use std::num::{ParseFloatError, ParseIntError};
+use types::{Type, TypeMismatchError};
 
 #[derive(Debug, PartialEq, Fail)]
 pub enum LexErrorKind {
@@ -20,6 +21,27 @@ pub enum LexErrorKind {
         radix: u32,
     },
 
+    #[fail(display = "{}", _0)]
+    ParseFloat(#[cause] ParseFloatError),
+
+    #[fail(display = "number is out of range of a 64-bit float")]
+    FloatOutOfRange,
+
+    #[fail(display = "invalid date or time")]
+    InvalidTimestamp,
+
+    #[fail(display = "duration is out of range")]
+    DurationOutOfRange,
+
+    #[fail(display = "expected 6 or 8 octets in a MAC address, but found {}", _0)]
+    InvalidMacLength(usize),
+
+    #[fail(display = "MAC address prefix length is too long")]
+    MacPrefixLengthTooLong,
+
+    #[fail(display = "MAC address has bits set after its prefix")]
+    InvalidMacPrefix,
+
     #[fail(display = "{}", _0)]
     ParseNetwork(#[cause] NetworkParseError),
 
@@ -48,6 +70,37 @@ pub enum LexErrorKind {
     #[fail(display = "incompatible range bounds")]
     IncompatibleRangeBounds,
 
+    #[fail(display = "cannot access index {:?} on type {:?}", index, actual)]
+    InvalidIndexAccess { index: FieldIndex, actual: Type },
+
+    #[fail(display = "quantifier requires an expression with [*] index")]
+    MissingEachIndex,
+
+    #[fail(display = "{}", _0)]
+    UnknownFunction(#[cause] UnknownFunctionError),
+
+    #[fail(
+        display = "expected from {} to {} function arguments",
+        expected_min, expected_max
+    )]
+    InvalidArgumentsCount {
+        expected_min: usize,
+        expected_max: usize,
+    },
+
+    #[fail(display = "invalid type of argument #{}: {}", index, mismatch)]
+    InvalidArgumentType {
+        index: usize,
+        #[cause]
+        mismatch: TypeMismatchError,
+    },
+
+    #[fail(display = "{}", _0)]
+    UnknownList(#[cause] UnknownListError),
+
+    #[fail(display = "invalid type of list: {}", _0)]
+    InvalidListType(#[cause] TypeMismatchError),
+
     #[fail(display = "unrecognised input")]
     EOF,
 }
@@ -143,7 +196,7 @@ macro_rules! lex_enum {
     // This is invoked when no more variants are left to process.
     // At this point declaration and lexer body are considered complete.
     (@decl { $($preamble:tt)* } $name:ident $input:ident $decl:tt { $($expr:stmt)* } {}) => {
-        #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+        #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
         $($preamble)*
         pub enum $name $decl
 
@@ -0,0 +1,129 @@
+use serde::{
+    de::{self, Deserialize, Deserializer},
+    Serialize, Serializer,
+};
+use std::{
+    fmt::{self, Debug, Formatter},
+    slice,
+};
+use types::{GetType, LhsValue, Type, TypeMismatchError};
+
+/// An array of [`LhsValue`]s of the same [`Type`].
+///
+/// This is used to provide multi-valued fields (e.g. all occurrences of a
+/// header) to the [execution context](::ExecutionContext).
+#[derive(PartialEq, Eq, Clone)]
+pub struct Array<'a> {
+    val_type: Type,
+    data: Vec<LhsValue<'a>>,
+}
+
+impl<'a> Array<'a> {
+    /// Creates an empty array for values of a given type.
+    pub fn new(val_type: Type) -> Self {
+        Array {
+            val_type,
+            data: Vec::new(),
+        }
+    }
+
+    /// Returns the type of values stored in this array.
+    pub fn value_type(&self) -> &Type {
+        &self.val_type
+    }
+
+    /// Appends a value to the end of the array, checking that it has
+    /// the expected type.
+    pub fn push<V: Into<LhsValue<'a>>>(&mut self, value: V) -> Result<(), TypeMismatchError> {
+        let value = value.into();
+        let value_type = value.get_type();
+
+        if value_type == self.val_type {
+            self.data.push(value);
+            Ok(())
+        } else {
+            Err(TypeMismatchError {
+                expected: self.val_type.clone(),
+                actual: value_type,
+            })
+        }
+    }
+
+    /// Returns a value at a given index, if any.
+    pub fn get(&self, index: usize) -> Option<&LhsValue<'a>> {
+        self.data.get(index)
+    }
+
+    /// Returns the number of values in the array.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns `true` if the array contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns an iterator over values of the array.
+    pub fn iter(&self) -> slice::Iter<'_, LhsValue<'a>> {
+        self.data.iter()
+    }
+}
+
+impl<'a> GetType for Array<'a> {
+    fn get_type(&self) -> Type {
+        Type::Array(Box::new(self.val_type.clone()))
+    }
+}
+
+impl<'a> Debug for Array<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(&self.data).finish()
+    }
+}
+
+impl<'a> Serialize for Array<'a> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser.collect_seq(&self.data)
+    }
+}
+
+// JSON arrays don't carry any type information, so we infer the type of
+// values from the first item and require the rest to match it.
+impl<'de: 'a, 'a> Deserialize<'de> for Array<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = Vec::<LhsValue<'a>>::deserialize(deserializer)?;
+
+        let mut array = match data.first() {
+            Some(value) => Array::new(value.get_type()),
+            None => return Err(de::Error::custom("cannot infer type of an empty array")),
+        };
+
+        for value in data {
+            array.push(value).map_err(de::Error::custom)?;
+        }
+
+        Ok(array)
+    }
+}
+
+#[test]
+fn test_push() {
+    let mut array = Array::new(Type::Int);
+
+    assert_eq!(array.push(1), Ok(()));
+    assert_eq!(array.push(2), Ok(()));
+
+    assert_eq!(
+        array.push("three"),
+        Err(TypeMismatchError {
+            expected: Type::Int,
+            actual: Type::Bytes,
+        })
+    );
+
+    assert_eq!(array.len(), 2);
+    assert_eq!(array.get(1), Some(&LhsValue::Int(2)));
+    assert_eq!(array.get(2), None);
+    assert_eq!(array.get_type(), Type::Array(Box::new(Type::Int)));
+}
@@ -0,0 +1,163 @@
+use fnv::FnvBuildHasher;
+use indexmap::{map::Values, IndexMap};
+use serde::{
+    de::{self, Deserialize, Deserializer, MapAccess, Visitor},
+    ser::{self, Serialize, SerializeMap, Serializer},
+};
+use std::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    str,
+};
+use types::{GetType, LhsValue, Type, TypeMismatchError};
+
+/// A map from byte keys to [`LhsValue`]s of the same [`Type`].
+///
+/// This is used to provide key/value fields (e.g. HTTP headers or query
+/// arguments) to the [execution context](::ExecutionContext).
+#[derive(PartialEq, Eq, Clone)]
+pub struct Map<'a> {
+    val_type: Type,
+    data: IndexMap<Box<[u8]>, LhsValue<'a>, FnvBuildHasher>,
+}
+
+impl<'a> Map<'a> {
+    /// Creates an empty map for values of a given type.
+    pub fn new(val_type: Type) -> Self {
+        Map {
+            val_type,
+            data: IndexMap::default(),
+        }
+    }
+
+    /// Returns the type of values stored in this map.
+    pub fn value_type(&self) -> &Type {
+        &self.val_type
+    }
+
+    /// Inserts a value for a given key, checking that it has the expected
+    /// type.
+    ///
+    /// If the map already had a value for this key, it's replaced.
+    pub fn insert<V: Into<LhsValue<'a>>>(
+        &mut self,
+        key: &[u8],
+        value: V,
+    ) -> Result<(), TypeMismatchError> {
+        let value = value.into();
+        let value_type = value.get_type();
+
+        if value_type == self.val_type {
+            self.data.insert(key.into(), value);
+            Ok(())
+        } else {
+            Err(TypeMismatchError {
+                expected: self.val_type.clone(),
+                actual: value_type,
+            })
+        }
+    }
+
+    /// Returns a value for a given key, if any.
+    pub fn get(&self, key: &[u8]) -> Option<&LhsValue<'a>> {
+        self.data.get(key)
+    }
+
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns an iterator over values of the map in insertion order.
+    pub fn values(&self) -> Values<'_, Box<[u8]>, LhsValue<'a>> {
+        self.data.values()
+    }
+}
+
+impl<'a> GetType for Map<'a> {
+    fn get_type(&self) -> Type {
+        Type::Map(Box::new(self.val_type.clone()))
+    }
+}
+
+impl<'a> Debug for Map<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.data
+                    .iter()
+                    .map(|(key, value)| (String::from_utf8_lossy(key), value)),
+            )
+            .finish()
+    }
+}
+
+impl<'a> Serialize for Map<'a> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        let mut out = ser.serialize_map(Some(self.len()))?;
+        for (key, value) in &self.data {
+            let key = str::from_utf8(key)
+                .map_err(|_| ser::Error::custom("map keys must be valid UTF-8"))?;
+            out.serialize_entry(key, value)?;
+        }
+        out.end()
+    }
+}
+
+struct MapVisitor<'a>(PhantomData<Map<'a>>);
+
+impl<'de: 'a, 'a> Visitor<'de> for MapVisitor<'a> {
+    type Value = Map<'a>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "a non-empty map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut map: Option<Map<'a>> = None;
+
+        while let Some((key, value)) = access.next_entry::<String, LhsValue<'a>>()? {
+            map.get_or_insert_with(|| Map::new(value.get_type()))
+                .insert(key.as_bytes(), value)
+                .map_err(de::Error::custom)?;
+        }
+
+        map.ok_or_else(|| de::Error::custom("cannot infer type of an empty map"))
+    }
+}
+
+// Like with arrays, JSON objects don't carry any type information, so we
+// infer the type of values from the first entry and require the rest to
+// match it.
+impl<'de: 'a, 'a> Deserialize<'de> for Map<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(MapVisitor(PhantomData))
+    }
+}
+
+#[test]
+fn test_insert() {
+    let mut map = Map::new(Type::Bytes);
+
+    assert_eq!(map.insert(b"a", "1"), Ok(()));
+    assert_eq!(map.insert(b"b", "2"), Ok(()));
+    assert_eq!(map.insert(b"a", "3"), Ok(()));
+
+    assert_eq!(
+        map.insert(b"c", 4),
+        Err(TypeMismatchError {
+            expected: Type::Bytes,
+            actual: Type::Int,
+        })
+    );
+
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get(b"a"), Some(&LhsValue::from("3")));
+    assert_eq!(map.get(b"c"), None);
+    assert_eq!(map.get_type(), Type::Map(Box::new(Type::Bytes)));
+}
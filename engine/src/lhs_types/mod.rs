@@ -0,0 +1,4 @@
+mod array;
+mod map;
+
+pub use self::{array::Array, map::Map};
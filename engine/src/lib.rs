@@ -55,6 +55,7 @@
 //! ```
 #![warn(missing_docs)]
 
+extern crate aho_corasick;
 extern crate cfg_if;
 extern crate failure;
 extern crate serde;
@@ -65,6 +66,9 @@ extern crate indoc;
 #[cfg(test)]
 extern crate lazy_static;
 
+#[cfg(test)]
+extern crate proptest;
+
 #[cfg(test)]
 extern crate serde_json;
 
@@ -83,18 +87,44 @@ mod lex;
 mod scheme;
 
 mod ast;
+mod deserialize;
 mod execution_context;
 mod filter;
+mod filter_set;
+mod functions;
 mod heap_searcher;
+mod ip_trie;
+mod lhs_types;
+mod lists;
 mod range_set;
 mod rhs_types;
+mod std_lib;
 mod strict_partial_ord;
 mod types;
 
 pub use self::{
-    ast::FilterAst,
-    execution_context::{ExecutionContext, FieldValueTypeMismatchError},
-    filter::{Filter, SchemeMismatchError},
-    scheme::{FieldRedefinitionError, ParseError, Scheme, UnknownFieldError},
-    types::{GetType, LhsValue, Type},
+    ast::{ExecutionTrace, FilterAst, FilterAstSeed},
+    deserialize::DeserializeError,
+    execution_context::{ExecutionContext, FieldValueTypeMismatchError, SetFieldValueError},
+    filter::{ExecutionError, Filter, MissingFieldError, SchemeMismatchError},
+    filter_set::FilterSet,
+    functions::{
+        Function, FunctionArgKind, FunctionArgs, FunctionImpl, FunctionOptParam, FunctionParam,
+    },
+    lhs_types::{Array, Map},
+    lists::List,
+    rhs_types::{Duration, Float, Mac, Timestamp},
+    scheme::{
+        Field, FieldRedefinitionError, FunctionRedefinitionError, ListRedefinitionError,
+        ParseError, Scheme, UnknownFieldError, UnknownFunctionError, UnknownListError,
+    },
+    types::{GetType, LhsValue, LhsValueSeed, Type, TypeMismatchError},
+};
+
+// Lookup structures used internally, exported only for benchmarks.
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub use self::{
+    ip_trie::{IpTrie, TrieKey},
+    range_set::RangeSet,
 };
@@ -0,0 +1,450 @@
+use deserialize::{DeserializeError, DeserializeErrorKind, FromRawWith, JsonPath, RawValue};
+use fnv::FnvBuildHasher;
+use indexmap::IndexSet;
+use ip_trie::{IpTrie, TrieKey};
+use lex::{complete, LexErrorKind, LexWith};
+use range_set::RangeSet;
+use rhs_types::{Duration, ExplicitIpRange, Float, Mac, Timestamp};
+use scheme::ParseError;
+use serde::{Deserialize, Deserializer};
+use std::{
+    cell::RefCell,
+    fmt::{self, Debug, Formatter},
+    mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ops::RangeInclusive,
+    sync::{Arc, RwLock},
+};
+use types::{GetType, LhsValue, RhsValues, Type, TypeMismatchError};
+
+/// Number of merged IP ranges above which they are looked up in an
+/// [`IpTrie`] instead of being binary searched in a [`RangeSet`].
+///
+/// The trie is faster at any size, but takes several times more memory.
+/// Up to this size binary search stays within 2x of it for both v4 and v6
+/// (see the `ip_set` benchmarks), while above it lookups get 2-4x slower.
+const IP_TRIE_THRESHOLD: usize = 256;
+
+/// A set of IP addresses of the same version.
+pub(crate) enum IpSet<T> {
+    Ranges(RangeSet<T>),
+    Trie(IpTrie<T>),
+}
+
+impl<T: TrieKey> From<Vec<RangeInclusive<T>>> for IpSet<T> {
+    fn from(ranges: Vec<RangeInclusive<T>>) -> Self {
+        let ranges = RangeSet::from(ranges);
+
+        if ranges.ranges().len() > IP_TRIE_THRESHOLD {
+            IpSet::Trie(ranges.into())
+        } else {
+            IpSet::Ranges(ranges)
+        }
+    }
+}
+
+impl<T: TrieKey> IpSet<T> {
+    fn contains(&self, addr: &T) -> bool {
+        match self {
+            IpSet::Ranges(ranges) => ranges.contains(addr),
+            IpSet::Trie(trie) => trie.contains(addr),
+        }
+    }
+}
+
+/// A set of values compiled for fast lookups, used by both `in { ... }`
+/// comparisons and named lists.
+pub(crate) enum ValueSet {
+    Ip {
+        v4: IpSet<Ipv4Addr>,
+        v6: IpSet<Ipv6Addr>,
+    },
+    Int(RangeSet<i64>),
+    Float(RangeSet<Float>),
+    Timestamp(RangeSet<Timestamp>),
+    Duration(RangeSet<Duration>),
+    Mac(RangeSet<Mac>),
+    Bytes(IndexSet<Box<[u8]>, FnvBuildHasher>),
+}
+
+impl From<RhsValues> for ValueSet {
+    fn from(values: RhsValues) -> Self {
+        match values {
+            RhsValues::Ip(ranges) => {
+                let mut v4 = Vec::new();
+                let mut v6 = Vec::new();
+                for range in ranges {
+                    match range.into() {
+                        ExplicitIpRange::V4(range) => v4.push(range),
+                        ExplicitIpRange::V6(range) => v6.push(range),
+                    }
+                }
+                ValueSet::Ip {
+                    v4: v4.into(),
+                    v6: v6.into(),
+                }
+            }
+            RhsValues::Int(values) => ValueSet::Int(values.into_iter().collect()),
+            RhsValues::Float(values) => ValueSet::Float(values.into_iter().collect()),
+            RhsValues::Timestamp(values) => ValueSet::Timestamp(values.into_iter().collect()),
+            RhsValues::Duration(values) => ValueSet::Duration(values.into_iter().collect()),
+            RhsValues::Mac(ranges) => {
+                ValueSet::Mac(ranges.into_iter().map(RangeInclusive::from).collect())
+            }
+            RhsValues::Bytes(values) => {
+                ValueSet::Bytes(values.into_iter().map(|value| value.into()).collect())
+            }
+            RhsValues::Bool(_) => unreachable!(),
+        }
+    }
+}
+
+impl ValueSet {
+    /// Checks whether the value is in the set.
+    ///
+    /// The value must have the type the set was created for.
+    pub fn contains(&self, value: &LhsValue<'_>) -> bool {
+        match (self, value) {
+            (ValueSet::Ip { v4, .. }, LhsValue::Ip(IpAddr::V4(addr))) => v4.contains(addr),
+            (ValueSet::Ip { v6, .. }, LhsValue::Ip(IpAddr::V6(addr))) => v6.contains(addr),
+            (ValueSet::Int(values), LhsValue::Int(value)) => values.contains(value),
+            // NaN is not in any range, but would be found by the total order
+            (ValueSet::Float(_), LhsValue::Float(value)) if value.0.is_nan() => false,
+            (ValueSet::Float(values), LhsValue::Float(value)) => {
+                values.contains(&value.normalize())
+            }
+            (ValueSet::Timestamp(values), LhsValue::Timestamp(value)) => values.contains(value),
+            (ValueSet::Duration(values), LhsValue::Duration(value)) => values.contains(value),
+            (ValueSet::Mac(values), LhsValue::Mac(value)) => values.contains(value),
+            (ValueSet::Bytes(values), LhsValue::Bytes(value)) => values.contains(value as &[u8]),
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn rhs_values_len(values: &RhsValues) -> usize {
+    match values {
+        RhsValues::Ip(values) => values.len(),
+        RhsValues::Int(values) => values.len(),
+        RhsValues::Float(values) => values.len(),
+        RhsValues::Timestamp(values) => values.len(),
+        RhsValues::Duration(values) => values.len(),
+        RhsValues::Mac(values) => values.len(),
+        RhsValues::Bytes(values) => values.len(),
+        RhsValues::Bool(values) => values.len(),
+    }
+}
+
+struct ListContents {
+    len: usize,
+    values: ValueSet,
+}
+
+/// A list of values of the same [`Type`] that filters can refer to by name
+/// with `field in $name`.
+///
+/// Lists are registered in a [`Scheme`](struct@::Scheme) with
+/// [`Scheme::add_list`](::Scheme::add_list) and are compiled once for all
+/// filters that refer to them, so that large sets of values don't need to be
+/// inlined into each filter.
+///
+/// A list is a shared handle: its clones refer to the same contents, which
+/// can be swapped with [`List::replace`] at any time. Filters read the
+/// contents once per execution, so they see the new values without being
+/// recompiled.
+#[derive(Clone)]
+pub struct List {
+    ty: Type,
+    contents: Arc<RwLock<Arc<ListContents>>>,
+}
+
+impl List {
+    fn new(ty: Type, values: RhsValues) -> Self {
+        let contents = ListContents {
+            len: rhs_values_len(&values),
+            values: values.into(),
+        };
+
+        List {
+            ty,
+            contents: Arc::new(RwLock::new(Arc::new(contents))),
+        }
+    }
+
+    // Takes a snapshot of the current contents, so that lookups don't hold
+    // the lock and replacements don't wait for them.
+    fn contents(&self) -> Arc<ListContents> {
+        self.contents.read().unwrap().clone()
+    }
+
+    // Only types that support `in { ... }` comparisons can be used for lists.
+    fn check_type(ty: &Type) -> Result<(), LexErrorKind> {
+        match ty {
+            Type::Ip
+            | Type::Int
+            | Type::Float
+            | Type::Timestamp
+            | Type::Duration
+            | Type::Mac
+            | Type::Bytes => Ok(()),
+            _ => Err(LexErrorKind::UnsupportedOp {
+                field_type: ty.clone(),
+            }),
+        }
+    }
+
+    /// Parses a list of values in the same syntax as in `in { ... }`
+    /// comparisons, e.g. `{ 10.0.0.0/8 192.168.0.1 }`.
+    pub fn parse(ty: Type, input: &str) -> Result<Self, ParseError<'_>> {
+        Self::check_type(&ty).map_err(|kind| ParseError::new(input, (kind, input)))?;
+
+        let values = complete(RhsValues::lex_with(input.trim(), &ty))
+            .map_err(|err| ParseError::new(input, err))?;
+
+        Ok(List::new(ty, values))
+    }
+
+    /// Deserializes a list of values from the same form as the `rhs` of a
+    /// serialized `OneOf` comparison, e.g. `["10.0.0.0/8", "192.168.0.1"]`.
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        ty: Type,
+        deserializer: D,
+    ) -> Result<Self, DeserializeError> {
+        Self::check_type(&ty).map_err(|kind| JsonPath::Root.error(kind))?;
+
+        let value = RawValue::deserialize(deserializer)
+            .map_err(|err| JsonPath::Root.error(DeserializeErrorKind::Invalid(err.to_string())))?;
+
+        let values = RhsValues::from_raw_with(&value, &JsonPath::Root, &ty)?;
+
+        Ok(List::new(ty, values))
+    }
+
+    /// Atomically replaces contents of this list and all of its clones with
+    /// contents of another list of the same type.
+    ///
+    /// Each execution of a filter or a [`FilterSet`](::FilterSet) reads the
+    /// contents on its first lookup in the list and keeps using them until
+    /// it finishes, so filters that are being executed concurrently see
+    /// either the old or the new contents, but never a mix of both.
+    pub fn replace(&self, other: &List) -> Result<(), TypeMismatchError> {
+        if self.ty != other.ty {
+            return Err(TypeMismatchError {
+                expected: self.ty.clone(),
+                actual: other.ty.clone(),
+            });
+        }
+
+        let contents = other.contents();
+        let old = mem::replace(&mut *self.contents.write().unwrap(), contents);
+
+        // The lock is released by now, so that the old contents are freed
+        // without blocking lookups.
+        drop(old);
+
+        Ok(())
+    }
+
+    /// Returns the number of values and ranges in the list as they were
+    /// given.
+    pub fn len(&self) -> usize {
+        self.contents().len
+    }
+
+    /// Returns `true` if the list contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn contains(&self, value: &LhsValue<'_>, snapshots: &ListSnapshots) -> bool {
+        snapshots.with(self, |contents| contents.values.contains(value))
+    }
+}
+
+type ListSnapshot = (*const RwLock<Arc<ListContents>>, Arc<ListContents>);
+
+/// Contents of lists taken during a single execution.
+///
+/// Each list is read on its first lookup, and further lookups reuse the
+/// snapshot, so that they neither touch the shared lock and the reference
+/// count, nor see a replacement that happened in between.
+#[derive(Default)]
+pub(crate) struct ListSnapshots(RefCell<Vec<ListSnapshot>>);
+
+impl ListSnapshots {
+    fn with<T>(&self, list: &List, func: impl FnOnce(&ListContents) -> T) -> T {
+        let key = Arc::as_ptr(&list.contents);
+        let mut snapshots = self.0.borrow_mut();
+
+        let index = match snapshots.iter().position(|(list, _)| *list == key) {
+            Some(index) => index,
+            None => {
+                snapshots.push((key, list.contents()));
+                snapshots.len() - 1
+            }
+        };
+
+        func(&snapshots[index].1)
+    }
+}
+
+impl Debug for List {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("List")
+            .field("ty", &self.ty)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl GetType for List {
+    fn get_type(&self) -> Type {
+        self.ty.clone()
+    }
+}
+
+#[cfg(test)]
+use execution_context::ExecutionContext;
+
+#[test]
+fn test_parse() {
+    let list = List::parse(Type::Ip, "{ 10.0.0.0/8 ::1 192.168.0.1..192.168.0.9 }").unwrap();
+
+    assert_eq!(list.len(), 3);
+    assert_eq!(list.get_type(), Type::Ip);
+    assert!(list
+        .contents()
+        .values
+        .contains(&LhsValue::Ip(IpAddr::from([10, 1, 2, 3]))));
+    assert!(list
+        .contents()
+        .values
+        .contains(&LhsValue::Ip(IpAddr::from([192, 168, 0, 5]))));
+    assert!(list
+        .contents()
+        .values
+        .contains(&LhsValue::Ip(IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]))));
+    assert!(!list
+        .contents()
+        .values
+        .contains(&LhsValue::Ip(IpAddr::from([192, 168, 0, 10]))));
+
+    let list = List::parse(Type::Bytes, r#"{ "a" "b" }"#).unwrap();
+    assert!(list.contents().values.contains(&LhsValue::from("a")));
+    assert!(!list.contents().values.contains(&LhsValue::from("c")));
+
+    assert_eq!(
+        List::parse(Type::Int, "{ 1 a }").unwrap_err().to_string(),
+        "Filter parsing error (1:5):\n{ 1 a }\n    ^ invalid digit found in string while parsing with radix 10\n"
+    );
+
+    assert!(List::parse(Type::Bool, "{}").is_err());
+    assert!(List::parse(Type::Array(Box::new(Type::Int)), "{}").is_err());
+}
+
+#[test]
+fn test_large_ip_list() {
+    let input = (0..1000)
+        .map(|i| format!("10.{}.{}.0/24", i / 256, i % 256))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let list = List::parse(Type::Ip, &format!("{{ {} ::/1 }}", input)).unwrap();
+
+    match &list.contents().values {
+        ValueSet::Ip {
+            v4: IpSet::Trie(_),
+            v6: IpSet::Ranges(_),
+        } => {}
+        _ => panic!("expected a trie for IPv4 addresses"),
+    }
+
+    assert!(list
+        .contents()
+        .values
+        .contains(&LhsValue::Ip(IpAddr::from([10, 3, 231, 1]))));
+    assert!(!list
+        .contents()
+        .values
+        .contains(&LhsValue::Ip(IpAddr::from([10, 3, 232, 1]))));
+    assert!(list
+        .contents()
+        .values
+        .contains(&LhsValue::Ip(IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]))));
+}
+
+#[test]
+fn test_deserialize() {
+    let list = List::deserialize(
+        Type::Int,
+        serde_json::json!([1, { "start": 10, "end": 20 }]),
+    )
+    .unwrap();
+
+    assert_eq!(list.len(), 2);
+    assert!(list.contents().values.contains(&LhsValue::Int(15)));
+    assert!(!list.contents().values.contains(&LhsValue::Int(2)));
+
+    assert_eq!(
+        List::deserialize(Type::Int, serde_json::json!([1, "2"]))
+            .unwrap_err()
+            .to_string(),
+        "expected an integer or a range at $[1]"
+    );
+}
+
+#[test]
+fn test_replace() {
+    let scheme = &mut Scheme! { port: Int };
+    let list = List::parse(Type::Int, "{ 80 443 }").unwrap();
+    scheme.add_list("ports".into(), list.clone()).unwrap();
+
+    let filter = scheme.parse("port in $ports").unwrap().compile();
+    let ctx = &mut ExecutionContext::new(scheme);
+    ctx.set_field_value("port", 8080).unwrap();
+
+    assert_eq!(filter.execute(ctx), Ok(false));
+
+    list.replace(&List::parse(Type::Int, "{ 8000..8999 }").unwrap())
+        .unwrap();
+
+    assert_eq!(list.len(), 1);
+    assert_eq!(scheme.get_list("ports").unwrap().len(), 1);
+    assert_eq!(filter.execute(ctx), Ok(true));
+
+    assert_eq!(
+        list.replace(&List::parse(Type::Bytes, "{}").unwrap()),
+        Err(TypeMismatchError {
+            expected: Type::Int,
+            actual: Type::Bytes,
+        })
+    );
+    assert_eq!(filter.execute(ctx), Ok(true));
+}
+
+#[test]
+fn test_replace_during_execution() {
+    let scheme = &mut Scheme! { a: Int, b: Int };
+    let list = List::parse(Type::Int, "{ 2 }").unwrap();
+    scheme.add_list("ports".into(), list.clone()).unwrap();
+
+    let filter = scheme
+        .parse("a in $ports or b in $ports")
+        .unwrap()
+        .compile();
+    let ctx = &mut ExecutionContext::new(scheme);
+    ctx.set_field_value("a", 1).unwrap();
+    {
+        let list = list.clone();
+        ctx.set_field_value_provider("b", move || {
+            list.replace(&List::parse(Type::Int, "{ 1 }").unwrap())
+                .unwrap();
+            Some(LhsValue::Int(1))
+        })
+        .unwrap();
+    }
+
+    // `b` is looked up in the same contents as `a`, even though they were
+    // replaced in between
+    assert_eq!(filter.execute(ctx), Ok(false));
+    assert_eq!(filter.execute(ctx), Ok(true));
+}
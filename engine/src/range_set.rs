@@ -34,6 +34,11 @@ impl<T: Ord + Copy> FromIterator<RangeInclusive<T>> for RangeSet<T> {
 }
 
 impl<T> RangeSet<T> {
+    /// Returns the ranges in the set, sorted and merged.
+    pub fn ranges(&self) -> &[RangeInclusive<T>] {
+        &self.ranges
+    }
+
     /// Like [`HashSet::contains`](std::collections::HashSet::contains),
     /// checks whether any compatible type is in the set.
     pub fn contains<Q>(&self, value: &Q) -> bool
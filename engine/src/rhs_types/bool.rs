@@ -1,3 +1,4 @@
+use deserialize::{DeserializeError, FromRaw, JsonPath, RawValue};
 use lex::{Lex, LexResult};
 use serde::Serialize;
 use std::{borrow::Borrow, cmp::Ordering};
@@ -33,3 +34,9 @@ impl<'i> Lex<'i> for UninhabitedBool {
         unreachable!()
     }
 }
+
+impl FromRaw for UninhabitedBool {
+    fn from_raw(_value: &RawValue, _path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        unreachable!()
+    }
+}
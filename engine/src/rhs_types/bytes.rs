@@ -1,8 +1,9 @@
+use deserialize::{DeserializeError, DeserializeErrorKind, FromRaw, JsonPath, RawValue};
 use lex::{expect, take, Lex, LexErrorKind, LexResult};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{
     borrow::Borrow,
-    fmt::{self, Debug, Formatter},
+    fmt::{self, Debug, Display, Formatter, Write},
     hash::{Hash, Hasher},
     ops::Deref,
     str,
@@ -51,7 +52,7 @@ impl From<Bytes> for Box<[u8]> {
 impl Debug for Bytes {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Bytes::Str(s) => s.fmt(f),
+            Bytes::Str(s) => Debug::fmt(s, f),
             Bytes::Raw(b) => {
                 for (i, b) in b.iter().cloned().enumerate() {
                     if i != 0 {
@@ -65,6 +66,40 @@ impl Debug for Bytes {
     }
 }
 
+// Writes a string literal that is lexed back into the same string.
+fn fmt_quoted(s: &str, f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                f.write_char('\\')?;
+                f.write_char(c)?;
+            }
+            c if c.is_ascii_control() => write!(f, "\\x{:02X}", c as u8)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// Writes bytes in the filter syntax, as a string literal if they are valid
+/// UTF-8, or as a hex sequence otherwise.
+pub(crate) fn fmt_bytes(bytes: &[u8], f: &mut Formatter<'_>) -> fmt::Result {
+    match str::from_utf8(bytes) {
+        Ok(s) => fmt_quoted(s, f),
+        Err(_) => Debug::fmt(&Bytes::Raw(bytes.into()), f),
+    }
+}
+
+impl Display for Bytes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Bytes::Str(s) => fmt_quoted(s, f),
+            Bytes::Raw(_) => Debug::fmt(self, f),
+        }
+    }
+}
+
 impl Deref for Bytes {
     type Target = [u8];
 
@@ -90,7 +125,7 @@ fn fixed_byte(input: &str, digits: usize, radix: u32) -> LexResult<'_, u8> {
     }
 }
 
-fn hex_byte(input: &str) -> LexResult<'_, u8> {
+pub(crate) fn hex_byte(input: &str) -> LexResult<'_, u8> {
     fixed_byte(input, 2, 16)
 }
 
@@ -162,6 +197,25 @@ impl<'i> Lex<'i> for Bytes {
     }
 }
 
+// Reads bytes from a string or, if they are not valid UTF-8, from an array of
+// octets, as produced by `Serialize`.
+impl FromRaw for Bytes {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        match value {
+            RawValue::String(s) => Ok(s.clone().into()),
+            RawValue::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| item.as_int(&path.index(i), "a byte"))
+                .collect::<Result<Vec<u8>, _>>()
+                .map(Bytes::from),
+            _ => Err(path.error(DeserializeErrorKind::Expected(
+                "a string or an array of bytes",
+            ))),
+        }
+    }
+}
+
 impl StrictPartialOrd for [u8] {}
 
 #[test]
@@ -198,6 +252,13 @@ fn test() {
         "n"
     );
 
+    assert_eq!(
+        Bytes::from("s\\t\"r\n\0t\u{e9}".to_owned()).to_string(),
+        r#""s\\t\"r\x0A\x00té""#
+    );
+
+    assert_eq!(Bytes::from(vec![0x01, 0x2F]).to_string(), "01:2F");
+
     assert_err!(
         Bytes::lex(r#""abcd\"#),
         LexErrorKind::MissingEndingQuote,
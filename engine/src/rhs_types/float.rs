@@ -0,0 +1,226 @@
+use deserialize::{DeserializeError, DeserializeErrorKind, FromRaw, JsonPath, RawValue};
+use lex::{expect, span, take_while, Lex, LexErrorKind, LexResult};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Debug, Display, Formatter},
+    ops::RangeInclusive,
+};
+use strict_partial_ord::StrictPartialOrd;
+
+/// A 64-bit floating point number.
+///
+/// Unlike `f64`, it has a total order and equality, so that it can be used
+/// in ASTs and sets. Filters compare these numbers with the usual IEEE 754
+/// semantics instead: `-0.0` is equal to `0.0`, and NaN is neither equal,
+/// less nor greater than any number, including itself, so that only `!=`
+/// matches it.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Float(pub f64);
+
+impl PartialEq for Float {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Float {}
+
+impl PartialOrd for Float {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Float {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl StrictPartialOrd for Float {
+    fn strict_partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.0.partial_cmp(&other.0)
+    }
+}
+
+impl From<f64> for Float {
+    fn from(value: f64) -> Self {
+        Float(value)
+    }
+}
+
+impl Float {
+    /// Returns the same number with `-0.0` replaced by `0.0`, so that it can
+    /// be looked up among ranges sorted in the total order.
+    pub(crate) fn normalize(self) -> Self {
+        if self.0 == 0.0 {
+            Float(0.0)
+        } else {
+            self
+        }
+    }
+}
+
+impl Debug for Float {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+// `Debug` of `f64` uses the shortest representation that reads back as the
+// same number and switches to the exponent form for very large and small
+// numbers, which is what we want in filters too.
+impl Display for Float {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+fn lex_digits(input: &str) -> LexResult<'_, &str> {
+    take_while(input, "digit", |c| c.is_ascii_digit())
+}
+
+impl<'i> Lex<'i> for Float {
+    fn lex(input: &str) -> LexResult<'_, Self> {
+        let initial_input = input;
+
+        let input = expect(input, "-").unwrap_or(input);
+        let (_, mut input) = lex_digits(input)?;
+
+        // don't consume `.` of the `..` range operator
+        if let Ok(rest) = expect(input, ".") {
+            if rest.starts_with(|c: char| c.is_ascii_digit()) {
+                input = lex_digits(rest)?.1;
+            }
+        }
+
+        if let Ok(rest) = expect(input, "e").or_else(|_| expect(input, "E")) {
+            let rest = expect(rest, "+")
+                .or_else(|_| expect(rest, "-"))
+                .unwrap_or(rest);
+            input = lex_digits(rest)?.1;
+        }
+
+        let number = span(initial_input, input);
+
+        match number.parse::<f64>() {
+            Ok(value) if value.is_finite() => Ok((Float(value), input)),
+            Ok(_) => Err((LexErrorKind::FloatOutOfRange, number)),
+            Err(err) => Err((LexErrorKind::ParseFloat(err), number)),
+        }
+    }
+}
+
+impl<'i> Lex<'i> for RangeInclusive<Float> {
+    fn lex(input: &str) -> LexResult<'_, Self> {
+        let initial_input = input;
+        let (first, input) = Float::lex(input)?;
+        let (last, input) = if let Ok(input) = expect(input, "..") {
+            Float::lex(input)?
+        } else {
+            (first, input)
+        };
+        let (first, last) = (first.normalize(), last.normalize());
+        if last < first {
+            return Err((
+                LexErrorKind::IncompatibleRangeBounds,
+                span(initial_input, input),
+            ));
+        }
+        Ok((first..=last, input))
+    }
+}
+
+impl FromRaw for Float {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        match *value {
+            RawValue::Int(num) => Ok(Float(num as f64)),
+            RawValue::Float(num) => Ok(Float(num)),
+            _ => Err(path.error(DeserializeErrorKind::Expected("a number"))),
+        }
+    }
+}
+
+// Reads either a single number or a `{ "start": ..., "end": ... }` range.
+impl FromRaw for RangeInclusive<Float> {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        let (first, last) = match value {
+            RawValue::Int(_) | RawValue::Float(_) => {
+                let num = Float::from_raw(value, path)?;
+                (num, num)
+            }
+            RawValue::Object(object) => {
+                object.check_keys(&["start", "end"], path)?;
+                (
+                    Float::from_raw(object.require("start", path)?, &path.key("start"))?,
+                    Float::from_raw(object.require("end", path)?, &path.key("end"))?,
+                )
+            }
+            _ => {
+                return Err(path.error(DeserializeErrorKind::Expected("a number or a range")));
+            }
+        };
+        let (first, last) = (first.normalize(), last.normalize());
+        if last < first {
+            return Err(path.error(LexErrorKind::IncompatibleRangeBounds));
+        }
+        Ok(first..=last)
+    }
+}
+
+#[test]
+fn test() {
+    assert_ok!(Float::lex("0"), Float(0.0), "");
+    assert_ok!(Float::lex("0.5;"), Float(0.5), ";");
+    assert_ok!(Float::lex("-12.25-"), Float(-12.25), "-");
+    assert_ok!(Float::lex("1e3!"), Float(1000.0), "!");
+    assert_ok!(Float::lex("2.5E-3"), Float(0.0025), "");
+    assert_ok!(Float::lex("1e+2"), Float(100.0), "");
+    assert_ok!(Float::lex("1..2"), Float(1.0), "..2");
+    assert_ok!(Float::lex("1.x"), Float(1.0), ".x");
+    assert_err!(Float::lex("1e"), LexErrorKind::ExpectedName("digit"), "");
+    assert_err!(Float::lex("1e400"), LexErrorKind::FloatOutOfRange, "1e400");
+    assert_err!(
+        Float::lex("nan"),
+        LexErrorKind::ExpectedName("digit"),
+        "nan"
+    );
+    assert_ok!(RangeInclusive::lex("0.5..1"), Float(0.5)..=Float(1.0));
+    assert_ok!(RangeInclusive::lex("-0.0..1e1"), Float(0.0)..=Float(10.0));
+    assert_ok!(RangeInclusive::lex("0.0..-0.0"), Float(0.0)..=Float(0.0));
+    assert_err!(
+        <RangeInclusive<Float>>::lex("1.5..0.5"),
+        LexErrorKind::IncompatibleRangeBounds,
+        "1.5..0.5"
+    );
+}
+
+#[test]
+fn test_strict_partial_ord() {
+    let nan = Float(f64::NAN);
+
+    assert_eq!(
+        Float(-0.0).strict_partial_cmp(&Float(0.0)),
+        Some(Ordering::Equal)
+    );
+    assert_eq!(nan.strict_partial_cmp(&nan), None);
+    assert_eq!(nan.strict_partial_cmp(&Float(1.0)), None);
+    assert_eq!(
+        Float(1.0).strict_partial_cmp(&Float(2.0)),
+        Some(Ordering::Less)
+    );
+
+    // while the total order used for equality of ASTs is reflexive
+    assert_eq!(nan, nan);
+    assert!(Float(-0.0) < Float(0.0));
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(Float(1.0).to_string(), "1.0");
+    assert_eq!(Float(-0.25).to_string(), "-0.25");
+    assert_eq!(Float(1e300).to_string(), "1e300");
+    assert_eq!(Float(1.5e-7).to_string(), "1.5e-7");
+}
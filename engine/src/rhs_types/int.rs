@@ -1,3 +1,4 @@
+use deserialize::{DeserializeError, DeserializeErrorKind, FromRaw, JsonPath, RawValue};
 use lex::{expect, span, take_while, Lex, LexErrorKind, LexResult};
 use std::ops::RangeInclusive;
 use strict_partial_ord::StrictPartialOrd;
@@ -7,14 +8,14 @@ fn lex_digits(input: &str) -> LexResult<'_, &str> {
     take_while(input, "digit", |c| c.is_digit(16))
 }
 
-fn parse_number<'i>((input, rest): (&'i str, &'i str), radix: u32) -> LexResult<'_, i32> {
-    match i32::from_str_radix(input, radix) {
+fn parse_number<'i>((input, rest): (&'i str, &'i str), radix: u32) -> LexResult<'_, i64> {
+    match i64::from_str_radix(input, radix) {
         Ok(res) => Ok((res, rest)),
         Err(err) => Err((LexErrorKind::ParseInt { err, radix }, input)),
     }
 }
 
-impl<'i> Lex<'i> for i32 {
+impl<'i> Lex<'i> for i64 {
     fn lex(input: &str) -> LexResult<'_, Self> {
         if let Ok(input) = expect(input, "0x") {
             parse_number(lex_digits(input)?, 16)
@@ -34,12 +35,12 @@ impl<'i> Lex<'i> for i32 {
     }
 }
 
-impl<'i> Lex<'i> for RangeInclusive<i32> {
+impl<'i> Lex<'i> for RangeInclusive<i64> {
     fn lex(input: &str) -> LexResult<'_, Self> {
         let initial_input = input;
-        let (first, input) = i32::lex(input)?;
+        let (first, input) = i64::lex(input)?;
         let (last, input) = if let Ok(input) = expect(input, "..") {
-            i32::lex(input)?
+            i64::lex(input)?
         } else {
             (first, input)
         };
@@ -53,49 +54,94 @@ impl<'i> Lex<'i> for RangeInclusive<i32> {
     }
 }
 
-impl StrictPartialOrd for i32 {}
+impl FromRaw for i64 {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        value.as_int(path, "a 64-bit integer")
+    }
+}
+
+// Reads either a single integer or a `{ "start": ..., "end": ... }` range.
+impl FromRaw for RangeInclusive<i64> {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        let (first, last) = match value {
+            RawValue::Int(_) => {
+                let num = i64::from_raw(value, path)?;
+                (num, num)
+            }
+            RawValue::Object(object) => {
+                object.check_keys(&["start", "end"], path)?;
+                (
+                    i64::from_raw(object.require("start", path)?, &path.key("start"))?,
+                    i64::from_raw(object.require("end", path)?, &path.key("end"))?,
+                )
+            }
+            _ => {
+                return Err(path.error(DeserializeErrorKind::Expected("an integer or a range")));
+            }
+        };
+        if last < first {
+            return Err(path.error(LexErrorKind::IncompatibleRangeBounds));
+        }
+        Ok(first..=last)
+    }
+}
+
+impl StrictPartialOrd for i64 {}
 
 #[test]
 fn test() {
     use std::str::FromStr;
 
-    assert_ok!(i32::lex("0"), 0i32, "");
-    assert_ok!(i32::lex("0-"), 0i32, "-");
-    assert_ok!(i32::lex("0x1f5+"), 501i32, "+");
-    assert_ok!(i32::lex("0123;"), 83i32, ";");
-    assert_ok!(i32::lex("78!"), 78i32, "!");
-    assert_ok!(i32::lex("0xefg"), 239i32, "g");
-    assert_ok!(i32::lex("-12-"), -12i32, "-");
+    assert_ok!(i64::lex("0"), 0i64, "");
+    assert_ok!(i64::lex("0-"), 0i64, "-");
+    assert_ok!(i64::lex("0x1f5+"), 501i64, "+");
+    assert_ok!(i64::lex("0123;"), 83i64, ";");
+    assert_ok!(i64::lex("78!"), 78i64, "!");
+    assert_ok!(i64::lex("0xefg"), 239i64, "g");
+    assert_ok!(i64::lex("-12-"), -12i64, "-");
+    assert_ok!(i64::lex("2147483648"), 2_147_483_648i64, "");
+    assert_ok!(i64::lex("0xffffffff"), 0xffff_ffffi64, "");
+    assert_ok!(i64::lex("0777777777777"), 0o777_777_777_777i64, "");
+    assert_ok!(i64::lex("0x7fffffffffffffff"), i64::MAX, "");
+    assert_ok!(i64::lex("-9223372036854775808"), i64::MIN, "");
     assert_err!(
-        i32::lex("-2147483649!"),
+        i64::lex("-9223372036854775809!"),
         LexErrorKind::ParseInt {
-            err: i32::from_str("-2147483649").unwrap_err(),
+            err: i64::from_str("-9223372036854775809").unwrap_err(),
             radix: 10
         },
-        "-2147483649"
+        "-9223372036854775809"
     );
     assert_err!(
-        i32::lex("2147483648!"),
+        i64::lex("9223372036854775808!"),
         LexErrorKind::ParseInt {
-            err: i32::from_str("2147483648").unwrap_err(),
+            err: i64::from_str("9223372036854775808").unwrap_err(),
             radix: 10
         },
-        "2147483648"
+        "9223372036854775808"
     );
     assert_err!(
-        i32::lex("10fex"),
+        i64::lex("10fex"),
         LexErrorKind::ParseInt {
-            err: i32::from_str("10fe").unwrap_err(),
+            err: i64::from_str("10fe").unwrap_err(),
             radix: 10
         },
         "10fe"
     );
-    assert_ok!(RangeInclusive::lex("78!"), 78i32..=78i32, "!");
-    assert_ok!(RangeInclusive::lex("0..10"), 0i32..=10i32);
-    assert_ok!(RangeInclusive::lex("0123..0xefg"), 83i32..=239i32, "g");
-    assert_ok!(RangeInclusive::lex("-20..-10"), -20i32..=-10i32);
     assert_err!(
-        <RangeInclusive<i32>>::lex("10..0"),
+        i64::lex("0x8000000000000000"),
+        LexErrorKind::ParseInt {
+            err: i64::from_str_radix("8000000000000000", 16).unwrap_err(),
+            radix: 16
+        },
+        "8000000000000000"
+    );
+    assert_ok!(RangeInclusive::lex("78!"), 78i64..=78i64, "!");
+    assert_ok!(RangeInclusive::lex("0..10"), 0i64..=10i64);
+    assert_ok!(RangeInclusive::lex("0123..0xefg"), 83i64..=239i64, "g");
+    assert_ok!(RangeInclusive::lex("-20..-10"), -20i64..=-10i64);
+    assert_err!(
+        <RangeInclusive<i64>>::lex("10..0"),
         LexErrorKind::IncompatibleRangeBounds,
         "10..0"
     );
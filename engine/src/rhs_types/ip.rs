@@ -1,8 +1,10 @@
 use cidr::{Cidr, IpCidr, Ipv4Cidr, Ipv6Cidr, NetworkParseError};
-use lex::{take_while, Lex, LexError, LexErrorKind, LexResult};
+use deserialize::{DeserializeError, DeserializeErrorKind, FromRaw, JsonPath, RawValue};
+use lex::{complete, take_while, Lex, LexError, LexErrorKind, LexResult};
 use serde::Serialize;
 use std::{
     cmp::Ordering,
+    fmt::{self, Display, Formatter},
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
     ops::RangeInclusive,
     str::FromStr,
@@ -46,6 +48,40 @@ pub enum IpRange {
     Cidr(IpCidr),
 }
 
+impl ExplicitIpRange {
+    /// Creates a range if both bounds are of the same IP version and are in
+    /// the ascending order.
+    fn new(first: IpAddr, last: IpAddr) -> Option<Self> {
+        match (first, last) {
+            (IpAddr::V4(first), IpAddr::V4(last)) if first <= last => {
+                Some(ExplicitIpRange::V4(first..=last))
+            }
+            (IpAddr::V6(first), IpAddr::V6(last)) if first <= last => {
+                Some(ExplicitIpRange::V6(first..=last))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for ExplicitIpRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ExplicitIpRange::V4(range) => write!(f, "{}..{}", range.start(), range.end()),
+            ExplicitIpRange::V6(range) => write!(f, "{}..{}", range.start(), range.end()),
+        }
+    }
+}
+
+impl Display for IpRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IpRange::Explicit(range) => Display::fmt(range, f),
+            IpRange::Cidr(cidr) => Display::fmt(cidr, f),
+        }
+    }
+}
+
 impl<'i> Lex<'i> for IpRange {
     fn lex(input: &str) -> LexResult<'_, Self> {
         let (chunk, rest) = match_addr_or_cidr(input)?;
@@ -55,17 +91,10 @@ impl<'i> Lex<'i> for IpRange {
             let first = parse_addr(&chunk[..split_pos])?;
             let last = parse_addr(&chunk[split_pos + "..".len()..])?;
 
-            IpRange::Explicit(match (first, last) {
-                (IpAddr::V4(first), IpAddr::V4(last)) if first <= last => {
-                    ExplicitIpRange::V4(first..=last)
-                }
-                (IpAddr::V6(first), IpAddr::V6(last)) if first <= last => {
-                    ExplicitIpRange::V6(first..=last)
-                }
-                _ => {
-                    return Err((LexErrorKind::IncompatibleRangeBounds, chunk));
-                }
-            })
+            IpRange::Explicit(
+                ExplicitIpRange::new(first, last)
+                    .ok_or((LexErrorKind::IncompatibleRangeBounds, chunk))?,
+            )
         } else {
             IpRange::Cidr(cidr::IpCidr::from_str(chunk).map_err(|err| {
                 let split_pos = chunk.find('/').unwrap_or_else(|| chunk.len());
@@ -84,6 +113,31 @@ impl<'i> Lex<'i> for IpRange {
     }
 }
 
+impl FromRaw for IpAddr {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        complete(IpAddr::lex(value.as_str(path)?)).map_err(|(kind, _)| path.error(kind))
+    }
+}
+
+// Reads either a string in the filter syntax, or a range serialized as
+// `{ "start": ..., "end": ... }`.
+impl FromRaw for IpRange {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        match value {
+            RawValue::String(s) => complete(IpRange::lex(s)).map_err(|(kind, _)| path.error(kind)),
+            RawValue::Object(object) => {
+                object.check_keys(&["start", "end"], path)?;
+                let first = IpAddr::from_raw(object.require("start", path)?, &path.key("start"))?;
+                let last = IpAddr::from_raw(object.require("end", path)?, &path.key("end"))?;
+                ExplicitIpRange::new(first, last)
+                    .map(IpRange::Explicit)
+                    .ok_or_else(|| path.error(LexErrorKind::IncompatibleRangeBounds))
+            }
+            _ => Err(path.error(DeserializeErrorKind::Expected("a string or a range"))),
+        }
+    }
+}
+
 macro_rules! impl_ip_range_from {
     (@single $v:ident, |$input:ident: $ty:ty| $transform:expr) => {
         impl From<$ty> for ExplicitIpRange {
@@ -250,3 +304,17 @@ fn test_strict_partial_ord() {
         }
     }
 }
+
+#[test]
+fn test_display() {
+    for &input in &[
+        "12.34.56.0/24",
+        "12.34.56.78",
+        "::/10",
+        "10.0.0.0..127.0.0.1",
+        "::1..::2",
+    ] {
+        let (range, _) = IpRange::lex(input).unwrap();
+        assert_eq!(IpRange::lex(&range.to_string()), Ok((range, "")));
+    }
+}
@@ -0,0 +1,382 @@
+use super::bytes::hex_byte;
+use deserialize::{
+    DeserializeError, DeserializeErrorKind, FromRaw, JsonPath, LexVisitor, RawValue,
+};
+use lex::{complete, expect, span, take_while, Lex, LexErrorKind, LexResult};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Debug, Display, Formatter},
+    ops::RangeInclusive,
+};
+use strict_partial_ord::StrictPartialOrd;
+
+/// A MAC address in either the 48-bit (EUI-48) or the 64-bit (EUI-64)
+/// format.
+///
+/// Like IPv4 and IPv6 addresses, addresses of different formats are neither
+/// equal nor ordered relative to each other in filters.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Mac {
+    /// A 48-bit address.
+    Eui48([u8; 6]),
+    /// A 64-bit address.
+    Eui64([u8; 8]),
+}
+
+impl Mac {
+    /// Returns the octets of the address.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Mac::Eui48(octets) => octets,
+            Mac::Eui64(octets) => octets,
+        }
+    }
+
+    fn bits(self) -> u32 {
+        self.as_bytes().len() as u32 * 8
+    }
+
+    fn to_u64(self) -> u64 {
+        self.as_bytes()
+            .iter()
+            .fold(0, |acc, &octet| acc << 8 | u64::from(octet))
+    }
+
+    /// Returns an address of the same format with the given numeric value.
+    fn with_u64(self, value: u64) -> Self {
+        let octets = value.to_be_bytes();
+        match self {
+            Mac::Eui48(_) => {
+                let mut res = [0; 6];
+                res.copy_from_slice(&octets[2..]);
+                Mac::Eui48(res)
+            }
+            Mac::Eui64(_) => Mac::Eui64(octets),
+        }
+    }
+
+    fn same_format(self, other: Self) -> bool {
+        self.bits() == other.bits()
+    }
+}
+
+/// Returns the mask of the bits after a prefix of `len` bits.
+fn host_mask(bits: u32, len: u8) -> u64 {
+    match bits - u32::from(len) {
+        0 => 0,
+        host => u64::MAX >> (64 - host),
+    }
+}
+
+impl From<[u8; 6]> for Mac {
+    fn from(octets: [u8; 6]) -> Self {
+        Mac::Eui48(octets)
+    }
+}
+
+impl From<[u8; 8]> for Mac {
+    fn from(octets: [u8; 8]) -> Self {
+        Mac::Eui64(octets)
+    }
+}
+
+impl StrictPartialOrd for Mac {
+    fn strict_partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Mac::Eui48(lhs), Mac::Eui48(rhs)) => Some(lhs.cmp(rhs)),
+            (Mac::Eui64(lhs), Mac::Eui64(rhs)) => Some(lhs.cmp(rhs)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Mac {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, octet) in self.as_bytes().iter().enumerate() {
+            if i != 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{:02x}", octet)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Mac {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+// Unlike bytes, octets must be separated consistently with either `:` or
+// `-`, and there must be exactly as many of them as in one of the formats.
+impl<'i> Lex<'i> for Mac {
+    fn lex(input: &str) -> LexResult<'_, Self> {
+        let initial_input = input;
+        let mut octets = Vec::with_capacity(8);
+        let (octet, mut input) = hex_byte(input)?;
+        octets.push(octet);
+
+        let separator = if input.starts_with('-') { "-" } else { ":" };
+        while let Ok(rest) = expect(input, separator) {
+            let (octet, rest) = hex_byte(rest)?;
+            octets.push(octet);
+            input = rest;
+        }
+
+        let mut mac = match octets.len() {
+            6 => Mac::Eui48([0; 6]),
+            8 => Mac::Eui64([0; 8]),
+            len => {
+                return Err((
+                    LexErrorKind::InvalidMacLength(len),
+                    span(initial_input, input),
+                ));
+            }
+        };
+        match &mut mac {
+            Mac::Eui48(res) => res.copy_from_slice(&octets),
+            Mac::Eui64(res) => res.copy_from_slice(&octets),
+        }
+        Ok((mac, input))
+    }
+}
+
+/// A range of MAC addresses of the same format.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum MacRange {
+    /// An explicit range like `00:00:5e:00:53:00..00:00:5e:00:53:ff`.
+    Explicit(RangeInclusive<Mac>),
+    /// All addresses that start with the given number of bits, like
+    /// `00:00:5e:00:00:00/24` for an OUI. A single address is a prefix of
+    /// its full length.
+    Prefix(Mac, u8),
+}
+
+impl Display for MacRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MacRange::Explicit(range) => write!(f, "{}..{}", range.start(), range.end()),
+            MacRange::Prefix(addr, len) if u32::from(*len) == addr.bits() => Display::fmt(addr, f),
+            MacRange::Prefix(addr, len) => write!(f, "{}/{}", addr, len),
+        }
+    }
+}
+
+impl<'i> Lex<'i> for MacRange {
+    fn lex(input: &str) -> LexResult<'_, Self> {
+        let initial_input = input;
+        let (first, input) = Mac::lex(input)?;
+
+        if let Ok(input) = expect(input, "..") {
+            let (last, input) = Mac::lex(input)?;
+            if !first.same_format(last) || last < first {
+                return Err((
+                    LexErrorKind::IncompatibleRangeBounds,
+                    span(initial_input, input),
+                ));
+            }
+            Ok((MacRange::Explicit(first..=last), input))
+        } else if let Ok(rest) = expect(input, "/") {
+            let (digits, rest) = take_while(rest, "digit", |c| c.is_ascii_digit())?;
+            let len = digits
+                .parse::<u8>()
+                .map_err(|err| (LexErrorKind::ParseInt { err, radix: 10 }, digits))?;
+            if u32::from(len) > first.bits() {
+                return Err((
+                    LexErrorKind::MacPrefixLengthTooLong,
+                    span(initial_input, rest),
+                ));
+            }
+            if first.to_u64() & host_mask(first.bits(), len) != 0 {
+                return Err((LexErrorKind::InvalidMacPrefix, span(initial_input, input)));
+            }
+            Ok((MacRange::Prefix(first, len), rest))
+        } else {
+            Ok((MacRange::Prefix(first, first.bits() as u8), input))
+        }
+    }
+}
+
+impl From<MacRange> for RangeInclusive<Mac> {
+    fn from(range: MacRange) -> Self {
+        match range {
+            MacRange::Explicit(range) => range,
+            MacRange::Prefix(addr, len) => {
+                let last = addr.to_u64() | host_mask(addr.bits(), len);
+                addr..=addr.with_u64(last)
+            }
+        }
+    }
+}
+
+impl Serialize for Mac {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Mac {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(LexVisitor::new("a MAC address"))
+    }
+}
+
+// Serialized like `IpRange`: prefixes as strings, and explicit ranges as
+// `{ "start": ..., "end": ... }`.
+impl Serialize for MacRange {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        match self {
+            MacRange::Explicit(range) => range.serialize(ser),
+            MacRange::Prefix(..) => ser.collect_str(self),
+        }
+    }
+}
+
+impl FromRaw for Mac {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        complete(Mac::lex(value.as_str(path)?)).map_err(|(kind, _)| path.error(kind))
+    }
+}
+
+// Reads either a string in the filter syntax, or a range serialized as
+// `{ "start": ..., "end": ... }`.
+impl FromRaw for MacRange {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        match value {
+            RawValue::String(s) => complete(MacRange::lex(s)).map_err(|(kind, _)| path.error(kind)),
+            RawValue::Object(object) => {
+                object.check_keys(&["start", "end"], path)?;
+                let first = Mac::from_raw(object.require("start", path)?, &path.key("start"))?;
+                let last = Mac::from_raw(object.require("end", path)?, &path.key("end"))?;
+                if !first.same_format(last) || last < first {
+                    return Err(path.error(LexErrorKind::IncompatibleRangeBounds));
+                }
+                Ok(MacRange::Explicit(first..=last))
+            }
+            _ => Err(path.error(DeserializeErrorKind::Expected("a string or a range"))),
+        }
+    }
+}
+
+#[test]
+fn test_lex() {
+    const EUI48: Mac = Mac::Eui48([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]);
+    const EUI64: Mac = Mac::Eui64([0x00, 0x1a, 0x2b, 0xff, 0xfe, 0x3c, 0x4d, 0x5e]);
+
+    assert_ok!(Mac::lex("00:1a:2b:3c:4d:5e;"), EUI48, ";");
+    assert_ok!(Mac::lex("00-1A-2B-3C-4D-5E"), EUI48, "");
+    assert_ok!(Mac::lex("00:1a:2b:ff:fe:3c:4d:5e"), EUI64, "");
+    assert_ok!(Mac::lex("00:1a:2b:3c:4d:5e..ff"), EUI48, "..ff");
+    // mixed separators end the address
+    assert_err!(
+        Mac::lex("00:1a:2b-3c-4d-5e"),
+        LexErrorKind::InvalidMacLength(3),
+        "00:1a:2b"
+    );
+    assert_err!(
+        Mac::lex("00:1a:2b:3c:4d"),
+        LexErrorKind::InvalidMacLength(5),
+        "00:1a:2b:3c:4d"
+    );
+    assert_err!(
+        Mac::lex("00:1a:2b:3c:4d:5e:6f"),
+        LexErrorKind::InvalidMacLength(7),
+        "00:1a:2b:3c:4d:5e:6f"
+    );
+    assert_err!(
+        Mac::lex("00:1a:2b:3c:4d:5"),
+        LexErrorKind::CountMismatch {
+            name: "character",
+            actual: 1,
+            expected: 2,
+        },
+        "5"
+    );
+}
+
+#[test]
+fn test_lex_range() {
+    let oui = Mac::Eui48([0x00, 0x00, 0x5e, 0, 0, 0]);
+
+    assert_ok!(
+        MacRange::lex("00:00:5e:00:00:00/24 "),
+        MacRange::Prefix(oui, 24),
+        " "
+    );
+    assert_ok!(
+        MacRange::lex("00:00:5e:00:00:00"),
+        MacRange::Prefix(oui, 48)
+    );
+    assert_ok!(
+        MacRange::lex("00:00:5e:00:00:00..00:00:5e:00:00:ff"),
+        MacRange::Explicit(oui..=Mac::Eui48([0x00, 0x00, 0x5e, 0, 0, 0xff]))
+    );
+    assert_err!(
+        MacRange::lex("00:00:5e:00:00:00/49"),
+        LexErrorKind::MacPrefixLengthTooLong,
+        "00:00:5e:00:00:00/49"
+    );
+    assert_err!(
+        MacRange::lex("00:00:5e:00:00:01/24"),
+        LexErrorKind::InvalidMacPrefix,
+        "00:00:5e:00:00:01"
+    );
+    assert_err!(
+        MacRange::lex("00:00:5e:00:00:00..00:00:5e:00:00:00:00:ff"),
+        LexErrorKind::IncompatibleRangeBounds,
+        "00:00:5e:00:00:00..00:00:5e:00:00:00:00:ff"
+    );
+    assert_err!(
+        MacRange::lex("00:00:5e:00:00:ff..00:00:5e:00:00:00"),
+        LexErrorKind::IncompatibleRangeBounds,
+        "00:00:5e:00:00:ff..00:00:5e:00:00:00"
+    );
+
+    assert_eq!(
+        RangeInclusive::from(MacRange::Prefix(oui, 24)),
+        oui..=Mac::Eui48([0x00, 0x00, 0x5e, 0xff, 0xff, 0xff])
+    );
+    assert_eq!(
+        RangeInclusive::from(MacRange::Prefix(Mac::Eui64([0; 8]), 0)),
+        Mac::Eui64([0; 8])..=Mac::Eui64([0xff; 8])
+    );
+}
+
+#[test]
+fn test_strict_partial_ord() {
+    let macs = &[
+        Mac::Eui48([0, 0, 0, 0, 0, 1]),
+        Mac::Eui48([0, 0, 0, 0, 0, 2]),
+        Mac::Eui64([0, 0, 0, 0, 0, 0, 0, 1]),
+        Mac::Eui64([0, 0, 0, 0, 0, 0, 0, 2]),
+    ];
+
+    for lhs in macs {
+        for rhs in macs {
+            if lhs.same_format(*rhs) {
+                assert_eq!(lhs.strict_partial_cmp(rhs), lhs.partial_cmp(rhs));
+            } else {
+                assert_eq!(lhs.strict_partial_cmp(rhs), None);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_display() {
+    for &input in &[
+        "00:1a:2b:3c:4d:5e",
+        "00:1a:2b:ff:fe:3c:4d:5e",
+        "00:00:5e:00:00:00/24",
+        "00:00:5e:00:00:00..00:00:5e:00:00:ff",
+    ] {
+        let (range, _) = MacRange::lex(input).unwrap();
+        assert_eq!(range.to_string(), input);
+    }
+    assert_eq!(
+        Mac::lex("00-1A-2B-3C-4D-5E").unwrap().0.to_string(),
+        "00:1a:2b:3c:4d:5e"
+    );
+}
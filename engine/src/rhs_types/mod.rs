@@ -1,12 +1,23 @@
 mod bool;
 mod bytes;
+mod float;
 mod int;
 mod ip;
+mod mac;
 mod regex;
+mod time;
 
 pub use self::{
     bool::UninhabitedBool,
     bytes::Bytes,
+    float::Float,
     ip::{ExplicitIpRange, IpRange},
+    mac::{Mac, MacRange},
     regex::{Error as RegexError, Regex},
+    time::{Duration, Timestamp},
 };
+
+pub(crate) use self::bytes::fmt_bytes;
+
+#[cfg(feature = "regex")]
+pub use self::regex::RegexSet;
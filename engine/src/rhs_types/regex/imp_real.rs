@@ -25,3 +25,20 @@ impl Regex {
         self.0.as_str()
     }
 }
+
+/// A set of regular expressions matched in a single pass.
+pub struct RegexSet(regex::bytes::RegexSet);
+
+impl RegexSet {
+    pub fn new<'r>(regexes: impl IntoIterator<Item = &'r Regex>) -> Result<Self, Error> {
+        ::regex::bytes::RegexSetBuilder::new(regexes.into_iter().map(Regex::as_str))
+            .unicode(false)
+            .build()
+            .map(RegexSet)
+    }
+
+    /// Returns indices of all regexes that match the text.
+    pub fn matches(&self, text: &[u8]) -> impl Iterator<Item = usize> {
+        self.0.matches(text).into_iter()
+    }
+}
@@ -1,8 +1,9 @@
 use cfg_if::cfg_if;
+use deserialize::{DeserializeError, FromRaw, JsonPath, RawValue};
 use lex::{expect, span, Lex, LexErrorKind, LexResult};
 use serde::{Serialize, Serializer};
 use std::{
-    fmt::{self, Debug, Formatter},
+    fmt::{self, Debug, Display, Formatter, Write},
     str::FromStr,
 };
 
@@ -30,6 +31,43 @@ impl Debug for Regex {
     }
 }
 
+// Quotes the regex the same way as it's unquoted by the lexer.
+impl Display for Regex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut in_char_class = false;
+        let mut iter = self.as_str().chars();
+
+        f.write_char('"')?;
+
+        while let Some(c) = iter.next() {
+            match c {
+                '\\' => {
+                    f.write_char('\\')?;
+                    if let Some(c) = iter.next() {
+                        f.write_char(c)?;
+                    }
+                }
+                '"' if !in_char_class => {
+                    f.write_str("\\\"")?;
+                }
+                '[' if !in_char_class => {
+                    in_char_class = true;
+                    f.write_char('[')?;
+                }
+                ']' if in_char_class => {
+                    in_char_class = false;
+                    f.write_char(']')?;
+                }
+                c => {
+                    f.write_char(c)?;
+                }
+            }
+        }
+
+        f.write_char('"')
+    }
+}
+
 impl<'i> Lex<'i> for Regex {
     fn lex(input: &str) -> LexResult<'_, Self> {
         let input = expect(input, "\"")?;
@@ -75,6 +113,13 @@ impl<'i> Lex<'i> for Regex {
     }
 }
 
+impl FromRaw for Regex {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        Regex::from_str(value.as_str(path)?)
+            .map_err(|err| path.error(LexErrorKind::ParseRegex(err)))
+    }
+}
+
 impl Serialize for Regex {
     fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
         self.as_str().serialize(ser)
@@ -91,6 +136,8 @@ fn test() {
 
     assert_json!(expr, r#"[a-z"\]]+\d{1,10}""#);
 
+    assert_eq!(expr.to_string(), r#""[a-z"\]]+\d{1,10}\"""#);
+
     assert_err!(
         Regex::lex(r#""abcd\"#),
         LexErrorKind::MissingEndingQuote,
@@ -0,0 +1,581 @@
+use deserialize::{
+    DeserializeError, DeserializeErrorKind, FromRaw, JsonPath, LexVisitor, RawValue,
+};
+use lex::{complete, expect, span, take_while, Lex, LexErrorKind, LexResult};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    iter,
+    ops::{RangeInclusive, Sub},
+    time::{self, SystemTime, UNIX_EPOCH},
+};
+use strict_partial_ord::StrictPartialOrd;
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+const SECS_PER_DAY: i128 = 86_400;
+
+/// A point in time with nanosecond precision.
+///
+/// In filters, it's written in the RFC 3339 format, e.g.
+/// `2019-04-01T12:30:00Z` or `2019-04-01T14:30:00.5+02:00`, and is always
+/// displayed in UTC.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(i128);
+
+/// A signed span of time with nanosecond precision.
+///
+/// In filters, it's written as a sequence of numbers with units, e.g. `30s`,
+/// `5m`, `7d` or `1h30m`. Supported units are `d`, `h`, `m`, `s`, `ms`, `us`
+/// and `ns`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration(i128);
+
+impl Timestamp {
+    /// Creates a timestamp from the number of seconds since the Unix epoch.
+    pub fn from_unix_secs(secs: i64) -> Self {
+        Timestamp(i128::from(secs) * NANOS_PER_SEC)
+    }
+
+    /// Creates a timestamp from the number of nanoseconds since the Unix
+    /// epoch.
+    pub fn from_unix_nanos(nanos: i128) -> Self {
+        Timestamp(nanos)
+    }
+
+    /// Returns the number of nanoseconds since the Unix epoch.
+    pub fn unix_nanos(self) -> i128 {
+        self.0
+    }
+
+    /// Returns the current time of the system clock.
+    pub fn now() -> Self {
+        SystemTime::now().into()
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(after) => Timestamp(Duration::from(after).0),
+            Err(err) => Timestamp(-Duration::from(err.duration()).0),
+        }
+    }
+}
+
+impl Duration {
+    /// Creates a duration from a number of seconds.
+    pub fn from_secs(secs: i64) -> Self {
+        Duration(i128::from(secs) * NANOS_PER_SEC)
+    }
+
+    /// Creates a duration from a number of nanoseconds.
+    pub fn from_nanos(nanos: i128) -> Self {
+        Duration(nanos)
+    }
+
+    /// Returns the number of nanoseconds in the duration.
+    pub fn as_nanos(self) -> i128 {
+        self.0
+    }
+}
+
+impl From<time::Duration> for Duration {
+    fn from(duration: time::Duration) -> Self {
+        // can't overflow, as `u64` seconds fit into `i128` nanoseconds
+        Duration(duration.as_nanos() as i128)
+    }
+}
+
+// Saturates instead of overflowing, which is only possible for timestamps
+// far outside of the range of RFC 3339.
+impl Sub for Timestamp {
+    type Output = Duration;
+
+    fn sub(self, other: Timestamp) -> Duration {
+        Duration(self.0.saturating_sub(other.0))
+    }
+}
+
+impl StrictPartialOrd for Timestamp {}
+
+impl StrictPartialOrd for Duration {}
+
+fn is_leap_year(year: i128) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i128, month: i128) -> i128 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Converts a date in the proleptic Gregorian calendar into the number of
+// days since the Unix epoch, and back, using algorithms from
+// http://howardhinnant.github.io/date_algorithms.html.
+fn days_from_civil(year: i128, month: i128, day: i128) -> i128 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i128) -> (i128, i128, i128) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+// Lexes a number with exactly `count` digits and checks that it's within
+// the given bounds.
+fn lex_fixed_number(
+    input: &str,
+    count: usize,
+    bounds: RangeInclusive<i128>,
+) -> LexResult<'_, i128> {
+    let digits = input
+        .get(..count)
+        .filter(|digits| digits.bytes().all(|c| c.is_ascii_digit()))
+        .ok_or((LexErrorKind::ExpectedName("digit"), input))?;
+    let number = digits.parse().unwrap();
+    if bounds.contains(&number) {
+        Ok((number, &input[count..]))
+    } else {
+        Err((LexErrorKind::InvalidTimestamp, digits))
+    }
+}
+
+fn lex_fraction(input: &str) -> LexResult<'_, i128> {
+    match expect(input, ".") {
+        Ok(input) => {
+            let (digits, input) = take_while(input, "digit", |c| c.is_ascii_digit())?;
+            // anything beyond nanoseconds is truncated
+            let nanos = digits
+                .bytes()
+                .chain(iter::repeat(b'0'))
+                .take(9)
+                .fold(0, |nanos, c| nanos * 10 + i128::from(c - b'0'));
+            Ok((nanos, input))
+        }
+        Err(_) => Ok((0, input)),
+    }
+}
+
+fn lex_offset(input: &str) -> LexResult<'_, i128> {
+    if let Ok(input) = expect(input, "Z").or_else(|_| expect(input, "z")) {
+        return Ok((0, input));
+    }
+    let (sign, input) = match (expect(input, "+"), expect(input, "-")) {
+        (Ok(input), _) => (1, input),
+        (_, Ok(input)) => (-1, input),
+        _ => return Err((LexErrorKind::ExpectedName("time zone offset"), input)),
+    };
+    let (hours, input) = lex_fixed_number(input, 2, 0..=23)?;
+    let input = expect(input, ":")?;
+    let (minutes, input) = lex_fixed_number(input, 2, 0..=59)?;
+    Ok((sign * (hours * 60 + minutes) * 60, input))
+}
+
+impl<'i> Lex<'i> for Timestamp {
+    fn lex(input: &str) -> LexResult<'_, Self> {
+        let initial_input = input;
+        let (year, input) = lex_fixed_number(input, 4, 0..=9999)?;
+        let input = expect(input, "-")?;
+        let (month, input) = lex_fixed_number(input, 2, 1..=12)?;
+        let input = expect(input, "-")?;
+        let (day, input) = lex_fixed_number(input, 2, 1..=days_in_month(year, month))?;
+        let input = expect(input, "T").or_else(|err| expect(input, "t").map_err(|_| err))?;
+        let (hour, input) = lex_fixed_number(input, 2, 0..=23)?;
+        let input = expect(input, ":")?;
+        let (minute, input) = lex_fixed_number(input, 2, 0..=59)?;
+        let input = expect(input, ":")?;
+        let (second, input) = lex_fixed_number(input, 2, 0..=59)?;
+        let (nanos, input) = lex_fraction(input)?;
+        let (offset, input) = lex_offset(input)?;
+
+        let secs =
+            days_from_civil(year, month, day) * SECS_PER_DAY + hour * 3600 + minute * 60 + second
+                - offset;
+
+        // the offset can move the time out of the range of years that can be
+        // written back in UTC
+        let min = days_from_civil(0, 1, 1) * SECS_PER_DAY;
+        let max = days_from_civil(10_000, 1, 1) * SECS_PER_DAY;
+        if secs < min || secs >= max {
+            return Err((LexErrorKind::InvalidTimestamp, span(initial_input, input)));
+        }
+
+        Ok((Timestamp(secs * NANOS_PER_SEC + nanos), input))
+    }
+}
+
+// Units from the largest to the smallest, in the order they are written in.
+const UNITS: &[(&str, i128)] = &[
+    ("d", SECS_PER_DAY * NANOS_PER_SEC),
+    ("h", 3600 * NANOS_PER_SEC),
+    ("m", 60 * NANOS_PER_SEC),
+    ("s", NANOS_PER_SEC),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+impl<'i> Lex<'i> for Duration {
+    fn lex(input: &str) -> LexResult<'_, Self> {
+        let initial_input = input;
+        let (sign, mut input) = match expect(input, "-") {
+            Ok(input) => (-1, input),
+            Err(_) => (1, input),
+        };
+        let mut nanos: i128 = 0;
+
+        loop {
+            let (digits, rest) = take_while(input, "digit", |c| c.is_ascii_digit())?;
+            // the longest unit wins, so that `ms` isn't read as `m`
+            let (unit, rest) = UNITS
+                .iter()
+                .filter_map(|&(name, unit)| Some((unit, expect(rest, name).ok()?)))
+                .min_by_key(|&(_, rest)| rest.len())
+                .ok_or((LexErrorKind::ExpectedName("duration unit"), rest))?;
+
+            nanos = digits
+                .parse::<i128>()
+                .ok()
+                .and_then(|value| value.checked_mul(unit))
+                .and_then(|value| nanos.checked_add(value))
+                .ok_or_else(|| (LexErrorKind::DurationOutOfRange, span(initial_input, rest)))?;
+            input = rest;
+
+            if !input.starts_with(|c: char| c.is_ascii_digit()) {
+                return Ok((Duration(sign * nanos), input));
+            }
+        }
+    }
+}
+
+fn lex_range<'i, T: Lex<'i> + Copy + Ord>(input: &'i str) -> LexResult<'i, RangeInclusive<T>> {
+    let initial_input = input;
+    let (first, input) = T::lex(input)?;
+    let (last, input) = if let Ok(input) = expect(input, "..") {
+        T::lex(input)?
+    } else {
+        (first, input)
+    };
+    if last < first {
+        return Err((
+            LexErrorKind::IncompatibleRangeBounds,
+            span(initial_input, input),
+        ));
+    }
+    Ok((first..=last, input))
+}
+
+impl<'i> Lex<'i> for RangeInclusive<Timestamp> {
+    fn lex(input: &str) -> LexResult<'_, Self> {
+        lex_range(input)
+    }
+}
+
+impl<'i> Lex<'i> for RangeInclusive<Duration> {
+    fn lex(input: &str) -> LexResult<'_, Self> {
+        lex_range(input)
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let secs = self.0.div_euclid(NANOS_PER_SEC);
+        let nanos = self.0.rem_euclid(NANOS_PER_SEC);
+        let (year, month, day) = civil_from_days(secs.div_euclid(SECS_PER_DAY));
+        let secs = secs.rem_euclid(SECS_PER_DAY);
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        )?;
+
+        if nanos != 0 {
+            let fraction = format!("{:09}", nanos);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+
+        f.write_str("Z")
+    }
+}
+
+impl Display for Duration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.0 == 0 {
+            return f.write_str("0s");
+        }
+
+        if self.0 < 0 {
+            f.write_str("-")?;
+        }
+
+        let mut rest = self.0.unsigned_abs();
+
+        for &(name, unit) in UNITS {
+            let unit = unit as u128;
+            if rest >= unit {
+                write!(f, "{}{}", rest / unit, name)?;
+                rest %= unit;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Debug for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Debug for Duration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+// Both types are serialized as strings in the filter syntax.
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser.collect_str(self)
+    }
+}
+
+impl Serialize for Duration {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(LexVisitor::new("an RFC 3339 timestamp"))
+    }
+}
+
+impl<'de> Deserialize<'de> for Duration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(LexVisitor::new("a duration"))
+    }
+}
+
+fn from_raw_lex<T: for<'i> Lex<'i>>(
+    value: &RawValue,
+    path: &JsonPath<'_>,
+) -> Result<T, DeserializeError> {
+    complete(T::lex(value.as_str(path)?)).map_err(|(kind, _)| path.error(kind))
+}
+
+// Reads either a string in the filter syntax, or a range serialized as
+// `{ "start": ..., "end": ... }`.
+fn range_from_raw<T: for<'i> Lex<'i> + Copy + Ord>(
+    value: &RawValue,
+    path: &JsonPath<'_>,
+) -> Result<RangeInclusive<T>, DeserializeError> {
+    match value {
+        RawValue::String(s) => complete(lex_range(s)).map_err(|(kind, _)| path.error(kind)),
+        RawValue::Object(object) => {
+            object.check_keys(&["start", "end"], path)?;
+            let first: T = from_raw_lex(object.require("start", path)?, &path.key("start"))?;
+            let last: T = from_raw_lex(object.require("end", path)?, &path.key("end"))?;
+            if last < first {
+                return Err(path.error(LexErrorKind::IncompatibleRangeBounds));
+            }
+            Ok(first..=last)
+        }
+        _ => Err(path.error(DeserializeErrorKind::Expected("a string or a range"))),
+    }
+}
+
+impl FromRaw for Timestamp {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        from_raw_lex(value, path)
+    }
+}
+
+impl FromRaw for Duration {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        from_raw_lex(value, path)
+    }
+}
+
+impl FromRaw for RangeInclusive<Timestamp> {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        range_from_raw(value, path)
+    }
+}
+
+impl FromRaw for RangeInclusive<Duration> {
+    fn from_raw(value: &RawValue, path: &JsonPath<'_>) -> Result<Self, DeserializeError> {
+        range_from_raw(value, path)
+    }
+}
+
+#[test]
+fn test_timestamp() {
+    assert_ok!(
+        Timestamp::lex("1970-01-01T00:00:00Z"),
+        Timestamp::from_unix_secs(0)
+    );
+    assert_ok!(
+        Timestamp::lex("2019-04-01T12:30:00Z.."),
+        Timestamp::from_unix_secs(1_554_121_800),
+        ".."
+    );
+    assert_ok!(
+        Timestamp::lex("2019-04-01t14:30:00.5+02:00"),
+        Timestamp::from_unix_nanos(1_554_121_800_500_000_000)
+    );
+    assert_ok!(
+        Timestamp::lex("1969-12-31T19:00:00.0000000019-05:00"),
+        Timestamp::from_unix_nanos(1)
+    );
+    assert_ok!(
+        Timestamp::lex("2020-02-29T00:00:00z"),
+        Timestamp::from_unix_secs(1_582_934_400)
+    );
+    assert_ok!(
+        Timestamp::lex("9999-12-31T23:59:59Z"),
+        Timestamp::from_unix_secs(253_402_300_799)
+    );
+    assert_err!(
+        Timestamp::lex("2019-02-29T00:00:00Z"),
+        LexErrorKind::InvalidTimestamp,
+        "29"
+    );
+    assert_err!(
+        Timestamp::lex("2019-04-01T24:00:00Z"),
+        LexErrorKind::InvalidTimestamp,
+        "24"
+    );
+    assert_err!(
+        Timestamp::lex("9999-12-31T23:00:00-01:00"),
+        LexErrorKind::InvalidTimestamp,
+        "9999-12-31T23:00:00-01:00"
+    );
+    assert_err!(
+        Timestamp::lex("2019-04-01 12:30:00Z"),
+        LexErrorKind::ExpectedLiteral("T"),
+        " 12:30:00Z"
+    );
+    assert_err!(
+        Timestamp::lex("2019-04-01T12:30:00"),
+        LexErrorKind::ExpectedName("time zone offset"),
+        ""
+    );
+    assert_err!(
+        Timestamp::lex("2019-4-01T12:30:00Z"),
+        LexErrorKind::ExpectedName("digit"),
+        "4-01T12:30:00Z"
+    );
+    assert_ok!(
+        RangeInclusive::lex("2019-01-01T00:00:00Z..2020-01-01T00:00:00Z"),
+        Timestamp::from_unix_secs(1_546_300_800)..=Timestamp::from_unix_secs(1_577_836_800)
+    );
+}
+
+#[test]
+fn test_timestamp_display() {
+    let display = |input| complete(Timestamp::lex(input)).unwrap().to_string();
+
+    assert_eq!(display("2019-04-01T12:30:00Z"), "2019-04-01T12:30:00Z");
+    assert_eq!(
+        display("2019-04-01T14:30:00.5+02:00"),
+        "2019-04-01T12:30:00.5Z"
+    );
+    assert_eq!(
+        display("1969-12-31T23:59:59.999Z"),
+        "1969-12-31T23:59:59.999Z"
+    );
+    assert_eq!(display("0000-03-01T00:00:00Z"), "0000-03-01T00:00:00Z");
+    assert_eq!(display("9999-12-31T23:59:59Z"), "9999-12-31T23:59:59Z");
+}
+
+#[test]
+fn test_duration() {
+    assert_ok!(Duration::lex("30s"), Duration::from_secs(30));
+    assert_ok!(Duration::lex("5m;"), Duration::from_secs(300), ";");
+    assert_ok!(Duration::lex("7d"), Duration::from_secs(7 * 86_400));
+    assert_ok!(Duration::lex("1h30m"), Duration::from_secs(5400));
+    assert_ok!(
+        Duration::lex("-1s500ms"),
+        Duration::from_nanos(-1_500_000_000)
+    );
+    assert_ok!(Duration::lex("2us3ns"), Duration::from_nanos(2003));
+    assert_ok!(Duration::lex("1m..2m"), Duration::from_secs(60), "..2m");
+    assert_err!(
+        Duration::lex("30"),
+        LexErrorKind::ExpectedName("duration unit"),
+        ""
+    );
+    assert_err!(
+        Duration::lex("5y"),
+        LexErrorKind::ExpectedName("duration unit"),
+        "y"
+    );
+    assert_err!(
+        Duration::lex("99999999999999999999999999999999d"),
+        LexErrorKind::DurationOutOfRange,
+        "99999999999999999999999999999999d"
+    );
+    assert_err!(
+        <RangeInclusive<Duration>>::lex("1h..30m"),
+        LexErrorKind::IncompatibleRangeBounds,
+        "1h..30m"
+    );
+}
+
+#[test]
+fn test_duration_display() {
+    assert_eq!(Duration::from_secs(0).to_string(), "0s");
+    assert_eq!(Duration::from_secs(90).to_string(), "1m30s");
+    assert_eq!(Duration::from_secs(-7 * 86_400).to_string(), "-7d");
+    assert_eq!(
+        Duration::from_nanos(1_500_000_001).to_string(),
+        "1s500ms1ns"
+    );
+}
+
+#[test]
+fn test_system_time() {
+    assert_eq!(
+        Timestamp::from(UNIX_EPOCH + time::Duration::from_millis(1500)),
+        Timestamp::from_unix_nanos(1_500_000_000)
+    );
+    assert_eq!(
+        Timestamp::from(UNIX_EPOCH - time::Duration::from_secs(1)),
+        Timestamp::from_unix_secs(-1)
+    );
+    assert_eq!(
+        Timestamp::from_unix_secs(10) - Timestamp::from_unix_secs(70),
+        Duration::from_secs(-60)
+    );
+}
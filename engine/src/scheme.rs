@@ -1,19 +1,31 @@
 use ast::FilterAst;
+use deserialize::{DeserializeError, DeserializeErrorKind, FromRawWith, JsonPath, RawValue};
 use failure::Fail;
 use fnv::FnvBuildHasher;
+use functions::Function;
 use indexmap::map::{Entry, IndexMap};
 use lex::{complete, expect, span, take_while, LexErrorKind, LexResult, LexWith};
-use serde::{Deserialize, Serialize, Serializer};
+use lists::List;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     cmp::{max, min},
     error::Error,
     fmt::{self, Debug, Display, Formatter},
     ptr,
 };
+use std_lib::std_functions;
 use types::{GetType, Type};
 
+/// A handle of a field registered in a [`Scheme`](struct@Scheme).
+///
+/// It can be obtained once with [`Scheme::get_field`] and then used to set
+/// values with [`ExecutionContext::set_by_field`](::ExecutionContext::set_by_field)
+/// without looking up the field by name each time.
+///
+/// It has a C-compatible layout, so that it can be passed through FFI.
+#[repr(C)]
 #[derive(PartialEq, Eq, Clone, Copy)]
-pub(crate) struct Field<'s> {
+pub struct Field<'s> {
     scheme: &'s Scheme,
     index: usize,
 }
@@ -30,26 +42,31 @@ impl<'s> Debug for Field<'s> {
     }
 }
 
-impl<'i, 's> LexWith<'i, &'s Scheme> for Field<'s> {
-    fn lex_with(mut input: &'i str, scheme: &'s Scheme) -> LexResult<'i, Self> {
-        let initial_input = input;
-
-        loop {
-            input = take_while(input, "identifier character", |c| {
-                c.is_ascii_alphanumeric() || c == '_'
-            })?
-            .1;
-
-            match expect(input, ".") {
-                Ok(rest) => input = rest,
-                Err(_) => break,
-            };
-        }
+/// Lexes a dot-separated name of a field or a function.
+pub(crate) fn lex_name(mut input: &str) -> LexResult<'_, &str> {
+    let initial_input = input;
+
+    loop {
+        input = take_while(input, "identifier character", |c| {
+            c.is_ascii_alphanumeric() || c == '_'
+        })?
+        .1;
+
+        match expect(input, ".") {
+            Ok(rest) => input = rest,
+            Err(_) => break,
+        };
+    }
+
+    Ok((span(initial_input, input), input))
+}
 
-        let name = span(initial_input, input);
+impl<'i, 's> LexWith<'i, &'s Scheme> for Field<'s> {
+    fn lex_with(input: &'i str, scheme: &'s Scheme) -> LexResult<'i, Self> {
+        let (name, input) = lex_name(input)?;
 
         let field = scheme
-            .get_field_index(name)
+            .get_field(name)
             .map_err(|err| (LexErrorKind::UnknownField(err), name))?;
 
         Ok((field, input))
@@ -57,14 +74,17 @@ impl<'i, 's> LexWith<'i, &'s Scheme> for Field<'s> {
 }
 
 impl<'s> Field<'s> {
+    /// Returns the name of the field.
     pub fn name(&self) -> &'s str {
         self.scheme.fields.get_index(self.index).unwrap().0
     }
 
+    /// Returns the index of the field in the scheme.
     pub fn index(&self) -> usize {
         self.index
     }
 
+    /// Returns the scheme the field belongs to.
     pub fn scheme(&self) -> &'s Scheme {
         self.scheme
     }
@@ -72,7 +92,99 @@ impl<'s> Field<'s> {
 
 impl<'s> GetType for Field<'s> {
     fn get_type(&self) -> Type {
-        *self.scheme.fields.get_index(self.index).unwrap().1
+        self.scheme.fields.get_index(self.index).unwrap().1.clone()
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub(crate) struct FunctionRef<'s> {
+    scheme: &'s Scheme,
+    index: usize,
+}
+
+impl<'s> Serialize for FunctionRef<'s> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        self.name().serialize(ser)
+    }
+}
+
+impl<'s> Debug for FunctionRef<'s> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl<'i, 's> LexWith<'i, &'s Scheme> for FunctionRef<'s> {
+    fn lex_with(input: &'i str, scheme: &'s Scheme) -> LexResult<'i, Self> {
+        let (name, input) = lex_name(input)?;
+
+        let function = scheme
+            .get_function(name)
+            .map_err(|err| (LexErrorKind::UnknownFunction(err), name))?;
+
+        Ok((function, input))
+    }
+}
+
+impl<'s> FunctionRef<'s> {
+    pub fn name(&self) -> &'s str {
+        self.scheme.functions.get_index(self.index).unwrap().0
+    }
+
+    pub fn get_definition(&self) -> &'s Function {
+        self.scheme.functions.get_index(self.index).unwrap().1
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub(crate) struct ListRef<'s> {
+    scheme: &'s Scheme,
+    index: usize,
+}
+
+impl<'s> Serialize for ListRef<'s> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        self.name().serialize(ser)
+    }
+}
+
+impl<'s> Debug for ListRef<'s> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "${}", self.name())
+    }
+}
+
+impl<'s> Display for ListRef<'s> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "${}", self.name())
+    }
+}
+
+impl<'i, 's> LexWith<'i, &'s Scheme> for ListRef<'s> {
+    fn lex_with(input: &'i str, scheme: &'s Scheme) -> LexResult<'i, Self> {
+        let (name, input) = lex_name(expect(input, "$")?)?;
+
+        let list = scheme
+            .get_list_ref(name)
+            .map_err(|err| (LexErrorKind::UnknownList(err), name))?;
+
+        Ok((list, input))
+    }
+}
+
+impl<'s> ListRef<'s> {
+    pub fn name(&self) -> &'s str {
+        self.scheme.lists.get_index(self.index).unwrap().0
+    }
+
+    pub fn get(&self) -> &'s List {
+        self.scheme.lists.get_index(self.index).unwrap().1
+    }
+}
+
+impl<'s> GetType for ListRef<'s> {
+    fn get_type(&self) -> Type {
+        self.get().get_type()
     }
 }
 
@@ -87,6 +199,28 @@ pub struct UnknownFieldError;
 #[fail(display = "attempt to redefine field {}", _0)]
 pub struct FieldRedefinitionError(String);
 
+/// An error that occurs if an unregistered function name was queried from a
+/// [`Scheme`](struct@Scheme).
+#[derive(Debug, PartialEq, Fail)]
+#[fail(display = "unknown function")]
+pub struct UnknownFunctionError;
+
+/// An error that occurs when previously defined function gets redefined.
+#[derive(Debug, PartialEq, Fail)]
+#[fail(display = "attempt to redefine function {}", _0)]
+pub struct FunctionRedefinitionError(String);
+
+/// An error that occurs if an unregistered list name was queried from a
+/// [`Scheme`](struct@Scheme).
+#[derive(Debug, PartialEq, Fail)]
+#[fail(display = "unknown list")]
+pub struct UnknownListError;
+
+/// An error that occurs when previously defined list gets redefined.
+#[derive(Debug, PartialEq, Fail)]
+#[fail(display = "attempt to redefine list {}", _0)]
+pub struct ListRedefinitionError(String);
+
 /// An opaque filter parsing error associated with the original input.
 ///
 /// For now, you can just print it in a debug or a human-readable fashion.
@@ -165,10 +299,20 @@ impl<'i> Display for ParseError<'i> {
 /// This is necessary to provide typechecking for runtime values provided
 /// to the [execution context](::ExecutionContext) and also to aid parser
 /// in ambiguous contexts.
+///
+/// It also holds [functions](::Function) that can be called from filters
+/// and named [lists](::List) that filters can refer to with `$name`.
+/// Those can't be represented in JSON, so only fields are deserialized.
 #[derive(Default, Deserialize)]
 #[serde(transparent)]
 pub struct Scheme {
     fields: IndexMap<String, Type, FnvBuildHasher>,
+
+    #[serde(skip)]
+    functions: IndexMap<String, Function, FnvBuildHasher>,
+
+    #[serde(skip)]
+    lists: IndexMap<String, List, FnvBuildHasher>,
 }
 
 impl PartialEq for Scheme {
@@ -189,6 +333,8 @@ impl<'s> Scheme {
     pub fn with_capacity(n: usize) -> Self {
         Scheme {
             fields: IndexMap::with_capacity_and_hasher(n, FnvBuildHasher::default()),
+            functions: IndexMap::default(),
+            lists: IndexMap::default(),
         }
     }
 
@@ -216,7 +362,8 @@ impl<'s> Scheme {
         Ok(scheme)
     }
 
-    pub(crate) fn get_field_index(&'s self, name: &str) -> Result<Field<'s>, UnknownFieldError> {
+    /// Returns a handle of a field with the given name.
+    pub fn get_field(&'s self, name: &str) -> Result<Field<'s>, UnknownFieldError> {
         match self.fields.get_full(name) {
             Some((index, ..)) => Ok(Field {
                 scheme: self,
@@ -230,32 +377,153 @@ impl<'s> Scheme {
         self.fields.len()
     }
 
+    pub(crate) fn fields(&'s self) -> impl Iterator<Item = Field<'s>> {
+        (0..self.fields.len()).map(move |index| Field {
+            scheme: self,
+            index,
+        })
+    }
+
+    /// Registers a function that can be called from filters.
+    pub fn add_function(
+        &mut self,
+        name: String,
+        function: Function,
+    ) -> Result<(), FunctionRedefinitionError> {
+        match self.functions.entry(name) {
+            Entry::Occupied(entry) => Err(FunctionRedefinitionError(entry.key().to_string())),
+            Entry::Vacant(entry) => {
+                entry.insert(function);
+                Ok(())
+            }
+        }
+    }
+
+    /// Registers all the built-in functions:
+    ///
+    ///  * `lower(bytes)` and `upper(bytes)` convert ASCII letters to lower or
+    ///    upper case respectively.
+    ///  * `len(bytes)` returns the length of the value in bytes.
+    ///  * `starts_with(bytes, "prefix")` and `ends_with(bytes, "suffix")`
+    ///    check whether the value starts or ends with a given literal.
+    ///  * `concat(bytes, bytes)` concatenates two values.
+    ///  * `substring(bytes, start[, end])` returns a part of the value between
+    ///    given offsets. Negative offsets are counted from the end, and an
+    ///    omitted `end` means the end of the value.
+    ///  * `url_decode(bytes)` decodes `%XX` sequences and `+` characters.
+    ///  * `remove_bytes(bytes, "chars")` removes all occurrences of any of
+    ///    given bytes.
+    ///  * `to_string(int)` converts an integer to its decimal representation.
+    ///  * `now()` returns the current time of the execution context, see
+    ///    [`ExecutionContext::now`](::ExecutionContext::now).
+    ///  * `since(timestamp)` and `until(timestamp)` return the duration from
+    ///    the given time to the current one and back respectively, e.g.
+    ///    `since(http.request.timestamp) < 5m`.
+    ///
+    /// Fails if any of these names are already taken by other functions.
+    pub fn add_std_functions(&mut self) -> Result<(), FunctionRedefinitionError> {
+        for (name, function) in std_functions() {
+            self.add_function(name.to_owned(), function)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get_function(
+        &'s self,
+        name: &str,
+    ) -> Result<FunctionRef<'s>, UnknownFunctionError> {
+        match self.functions.get_full(name) {
+            Some((index, ..)) => Ok(FunctionRef {
+                scheme: self,
+                index,
+            }),
+            None => Err(UnknownFunctionError),
+        }
+    }
+
+    /// Registers a named list of values that filters can refer to with
+    /// `field in $name`.
+    pub fn add_list(&mut self, name: String, list: List) -> Result<(), ListRedefinitionError> {
+        match self.lists.entry(name) {
+            Entry::Occupied(entry) => Err(ListRedefinitionError(entry.key().to_string())),
+            Entry::Vacant(entry) => {
+                entry.insert(list);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns a list with the given name, e.g. to [replace](List::replace)
+    /// its contents.
+    pub fn get_list(&self, name: &str) -> Result<&List, UnknownListError> {
+        self.lists.get(name).ok_or(UnknownListError)
+    }
+
+    pub(crate) fn get_list_ref(&'s self, name: &str) -> Result<ListRef<'s>, UnknownListError> {
+        match self.lists.get_full(name) {
+            Some((index, ..)) => Ok(ListRef {
+                scheme: self,
+                index,
+            }),
+            None => Err(UnknownListError),
+        }
+    }
+
     /// Parses a filter into an AST form.
     pub fn parse<'i>(&'s self, input: &'i str) -> Result<FilterAst<'s>, ParseError<'i>> {
         complete(FilterAst::lex_with(input.trim(), self)).map_err(|err| ParseError::new(input, err))
     }
+
+    /// Deserializes a filter from the form produced by serializing a
+    /// [`FilterAst`], validating it in the same way as [`Scheme::parse`].
+    ///
+    /// This is the same as deserializing with a
+    /// [`FilterAstSeed`](::FilterAstSeed), but returns the path to the
+    /// invalid value as a part of the error.
+    pub fn deserialize_filter<'de, D: Deserializer<'de>>(
+        &'s self,
+        deserializer: D,
+    ) -> Result<FilterAst<'s>, DeserializeError> {
+        let value = RawValue::deserialize(deserializer)
+            .map_err(|err| JsonPath::Root.error(DeserializeErrorKind::Invalid(err.to_string())))?;
+        FilterAst::from_raw_with(&value, &JsonPath::Root, self)
+    }
 }
 
 /// A convenience macro for constructing a [`Scheme`](struct@Scheme) with static
 /// contents.
+///
+/// Compound types are written in a function-like form, e.g. `Array(Bytes)`.
 #[macro_export]
 macro_rules! Scheme {
-    ($($ns:ident $(. $field:ident)*: $ty:ident),* $(,)*) => {
+    ($($ns:ident $(. $field:ident)*: $ty:ident $(($($subty:tt)*))*),* $(,)*) => {
         $crate::Scheme::try_from_iter(
-            [$(
+            vec![$(
                 (
                     concat!(stringify!($ns) $(, ".", stringify!($field))*),
-                    $crate::Type::$ty
+                    $crate::__scheme_type!($ty $(($($subty)*))*)
                 )
             ),*]
-            .iter()
-            .map(|&(k, v)| (k.to_owned(), v)),
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v)),
         )
         // Treat duplciations in static schemes as a developer's mistake.
         .unwrap_or_else(|err| panic!("{}", err))
     };
 }
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scheme_type {
+    ($ty:ident ($($subty:tt)*)) => {
+        $crate::Type::$ty(Box::new($crate::__scheme_type!($($subty)*)))
+    };
+
+    ($ty:ident) => {
+        $crate::Type::$ty
+    };
+}
+
 #[test]
 fn test_parse_error() {
     use indoc::indoc;
@@ -377,19 +645,19 @@ fn test_field() {
 
     assert_ok!(
         Field::lex_with("x;", scheme),
-        scheme.get_field_index("x").unwrap(),
+        scheme.get_field("x").unwrap(),
         ";"
     );
 
     assert_ok!(
         Field::lex_with("x.y.z0-", scheme),
-        scheme.get_field_index("x.y.z0").unwrap(),
+        scheme.get_field("x.y.z0").unwrap(),
         "-"
     );
 
     assert_ok!(
         Field::lex_with("is_TCP", scheme),
-        scheme.get_field_index("is_TCP").unwrap(),
+        scheme.get_field("is_TCP").unwrap(),
         ""
     );
 
@@ -412,6 +680,40 @@ fn test_field() {
     );
 }
 
+#[test]
+fn test_list() {
+    let mut scheme = Scheme! { ip: Ip };
+    scheme
+        .add_list(
+            "blocklist".into(),
+            List::parse(Type::Ip, "{ 10.0.0.0/8 }").unwrap(),
+        )
+        .unwrap();
+
+    assert_ok!(
+        ListRef::lex_with("$blocklist;", &scheme),
+        scheme.get_list_ref("blocklist").unwrap(),
+        ";"
+    );
+
+    assert_err!(
+        ListRef::lex_with("blocklist", &scheme),
+        LexErrorKind::ExpectedLiteral("$"),
+        "blocklist"
+    );
+
+    assert_err!(
+        ListRef::lex_with("$allowlist", &scheme),
+        LexErrorKind::UnknownList(UnknownListError),
+        "allowlist"
+    );
+
+    assert_eq!(
+        scheme.add_list("blocklist".into(), List::parse(Type::Ip, "{}").unwrap()),
+        Err(ListRedefinitionError("blocklist".into()))
+    );
+}
+
 #[test]
 #[should_panic(expected = "attempt to redefine field foo")]
 fn test_static_field_type_override() {
@@ -0,0 +1,656 @@
+use functions::{
+    Function, FunctionArgKind, FunctionArgs, FunctionImpl, FunctionOptParam, FunctionParam,
+};
+use rhs_types::Timestamp;
+use std::{borrow::Cow, convert::TryFrom};
+use types::{LhsValue, Type};
+
+fn next_bytes<'a>(args: FunctionArgs<'_, 'a>) -> Cow<'a, [u8]> {
+    match args.next() {
+        Some(LhsValue::Bytes(bytes)) => bytes,
+        _ => unreachable!(),
+    }
+}
+
+fn next_int(args: FunctionArgs<'_, '_>) -> i64 {
+    match args.next() {
+        Some(LhsValue::Int(num)) => num,
+        _ => unreachable!(),
+    }
+}
+
+fn lower<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let mut bytes = next_bytes(args);
+    if bytes.iter().any(u8::is_ascii_uppercase) {
+        bytes.to_mut().make_ascii_lowercase();
+    }
+    Some(LhsValue::Bytes(bytes))
+}
+
+fn upper<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let mut bytes = next_bytes(args);
+    if bytes.iter().any(u8::is_ascii_lowercase) {
+        bytes.to_mut().make_ascii_uppercase();
+    }
+    Some(LhsValue::Bytes(bytes))
+}
+
+fn len<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let bytes = next_bytes(args);
+    Some(LhsValue::Int(bytes.len() as i64))
+}
+
+fn starts_with<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let bytes = next_bytes(args);
+    let prefix = next_bytes(args);
+    Some(LhsValue::Bool(bytes.starts_with(&prefix)))
+}
+
+fn ends_with<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let bytes = next_bytes(args);
+    let suffix = next_bytes(args);
+    Some(LhsValue::Bool(bytes.ends_with(&suffix)))
+}
+
+fn concat<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let mut bytes = next_bytes(args);
+    let other = next_bytes(args);
+    if !other.is_empty() {
+        bytes.to_mut().extend_from_slice(&other);
+    }
+    Some(LhsValue::Bytes(bytes))
+}
+
+// Negative indices are counted from the end, and out of bounds indices are
+// clamped to the bounds of the value.
+fn resolve_index(index: i64, len: usize) -> usize {
+    let offset = usize::try_from(index.unsigned_abs()).unwrap_or(usize::MAX);
+    if index < 0 {
+        len.saturating_sub(offset)
+    } else {
+        offset.min(len)
+    }
+}
+
+fn substring<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let bytes = next_bytes(args);
+    let start = resolve_index(next_int(args), bytes.len());
+    let end = resolve_index(next_int(args), bytes.len()).max(start);
+    Some(LhsValue::Bytes(match bytes {
+        Cow::Borrowed(bytes) => Cow::Borrowed(&bytes[start..end]),
+        Cow::Owned(bytes) => Cow::Owned(bytes[start..end].to_vec()),
+    }))
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
+
+// Decodes `%XX` sequences and `+` as a space, leaving malformed sequences
+// as-is.
+fn url_decode<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let bytes = next_bytes(args);
+
+    if !bytes.iter().any(|&c| c == b'%' || c == b'+') {
+        return Some(LhsValue::Bytes(bytes));
+    }
+
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut rest = &bytes[..];
+
+    while let Some((&c, tail)) = rest.split_first() {
+        rest = tail;
+        res.push(match c {
+            b'+' => b' ',
+            b'%' => match (
+                rest.first().cloned().and_then(hex_digit),
+                rest.get(1).cloned().and_then(hex_digit),
+            ) {
+                (Some(hi), Some(lo)) => {
+                    rest = &rest[2..];
+                    hi << 4 | lo
+                }
+                _ => c,
+            },
+            c => c,
+        });
+    }
+
+    Some(LhsValue::Bytes(Cow::Owned(res)))
+}
+
+fn remove_bytes<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let mut bytes = next_bytes(args);
+    let removed = next_bytes(args);
+    if bytes.iter().any(|c| removed.contains(c)) {
+        bytes.to_mut().retain(|c| !removed.contains(c));
+    }
+    Some(LhsValue::Bytes(bytes))
+}
+
+fn to_string<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let num = next_int(args);
+    Some(LhsValue::Bytes(Cow::Owned(num.to_string().into_bytes())))
+}
+
+fn next_timestamp(args: FunctionArgs<'_, '_>) -> Timestamp {
+    match args.next() {
+        Some(LhsValue::Timestamp(ts)) => ts,
+        _ => unreachable!(),
+    }
+}
+
+fn now<'a>(now: Timestamp, _: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    Some(LhsValue::Timestamp(now))
+}
+
+fn since<'a>(now: Timestamp, args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    Some(LhsValue::Duration(now - next_timestamp(args)))
+}
+
+fn until<'a>(now: Timestamp, args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    Some(LhsValue::Duration(next_timestamp(args) - now))
+}
+
+fn field(val_type: Type) -> FunctionParam {
+    FunctionParam {
+        arg_kind: FunctionArgKind::Field,
+        val_type,
+    }
+}
+
+fn literal(val_type: Type) -> FunctionParam {
+    FunctionParam {
+        arg_kind: FunctionArgKind::Literal,
+        val_type,
+    }
+}
+
+fn function<F>(params: Vec<FunctionParam>, return_type: Type, implementation: F) -> Function
+where
+    F: for<'a> Fn(FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> + Sync + Send + 'static,
+{
+    Function {
+        params,
+        opt_params: Vec::new(),
+        return_type,
+        implementation: FunctionImpl::new(implementation),
+    }
+}
+
+fn clocked_function<F>(params: Vec<FunctionParam>, return_type: Type, implementation: F) -> Function
+where
+    F: for<'a> Fn(Timestamp, FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> + Sync + Send + 'static,
+{
+    Function {
+        params,
+        opt_params: Vec::new(),
+        return_type,
+        implementation: FunctionImpl::with_clock(implementation),
+    }
+}
+
+/// Returns definitions of all the built-in functions.
+///
+/// See [`Scheme::add_std_functions`](::Scheme::add_std_functions) for their
+/// descriptions.
+pub(crate) fn std_functions() -> Vec<(&'static str, Function)> {
+    vec![
+        (
+            "lower",
+            function(vec![field(Type::Bytes)], Type::Bytes, lower),
+        ),
+        (
+            "upper",
+            function(vec![field(Type::Bytes)], Type::Bytes, upper),
+        ),
+        ("len", function(vec![field(Type::Bytes)], Type::Int, len)),
+        (
+            "starts_with",
+            function(
+                vec![field(Type::Bytes), literal(Type::Bytes)],
+                Type::Bool,
+                starts_with,
+            ),
+        ),
+        (
+            "ends_with",
+            function(
+                vec![field(Type::Bytes), literal(Type::Bytes)],
+                Type::Bool,
+                ends_with,
+            ),
+        ),
+        (
+            "concat",
+            function(
+                vec![field(Type::Bytes), field(Type::Bytes)],
+                Type::Bytes,
+                concat,
+            ),
+        ),
+        (
+            "substring",
+            Function {
+                params: vec![field(Type::Bytes), literal(Type::Int)],
+                opt_params: vec![FunctionOptParam {
+                    arg_kind: FunctionArgKind::Literal,
+                    default_value: LhsValue::Int(i64::MAX),
+                }],
+                return_type: Type::Bytes,
+                implementation: FunctionImpl::new(substring),
+            },
+        ),
+        (
+            "url_decode",
+            function(vec![field(Type::Bytes)], Type::Bytes, url_decode),
+        ),
+        (
+            "remove_bytes",
+            function(
+                vec![field(Type::Bytes), literal(Type::Bytes)],
+                Type::Bytes,
+                remove_bytes,
+            ),
+        ),
+        (
+            "to_string",
+            function(vec![field(Type::Int)], Type::Bytes, to_string),
+        ),
+        ("now", clocked_function(vec![], Type::Timestamp, now)),
+        (
+            "since",
+            clocked_function(vec![field(Type::Timestamp)], Type::Duration, since),
+        ),
+        (
+            "until",
+            clocked_function(vec![field(Type::Timestamp)], Type::Duration, until),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use execution_context::ExecutionContext;
+    use lazy_static::lazy_static;
+    use rhs_types::Timestamp;
+    use scheme::Scheme;
+
+    lazy_static! {
+        static ref SCHEME: Scheme = {
+            let mut scheme = Scheme! {
+                http.host: Bytes,
+                http.path: Bytes,
+                http.timestamp: Timestamp,
+                tcp.port: Int,
+                tls.not_after: Timestamp,
+            };
+            scheme.add_std_functions().unwrap();
+            scheme
+        };
+    }
+
+    fn ctx(host: &'static str, path: &'static str, port: i64) -> ExecutionContext<'static> {
+        let mut ctx = ExecutionContext::new(&SCHEME);
+        ctx.set_field_value("http.host", host).unwrap();
+        ctx.set_field_value("http.path", path).unwrap();
+        ctx.set_field_value("tcp.port", port).unwrap();
+        ctx
+    }
+
+    #[test]
+    fn test_opt_in() {
+        let scheme = Scheme! { http.host: Bytes };
+
+        assert!(scheme.parse("lower(http.host) == \"a\"").is_err());
+
+        let mut scheme = scheme;
+        scheme.add_std_functions().unwrap();
+
+        assert!(scheme.parse("lower(http.host) == \"a\"").is_ok());
+    }
+
+    #[test]
+    fn test_lower() {
+        let ast = SCHEME
+            .parse(r#"lower(http.host) == "example.org""#)
+            .unwrap();
+
+        assert_json!(
+            ast,
+            {
+                "function": {
+                    "name": "lower",
+                    "args": [{ "field": "http.host" }]
+                },
+                "op": "Equal",
+                "rhs": "example.org"
+            }
+        );
+
+        let filter = ast.compile();
+
+        assert_eq!(filter.execute(&ctx("ExAmPlE.org", "/", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("example.org", "/", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("example.com", "/", 80)), Ok(false));
+    }
+
+    #[test]
+    fn test_upper() {
+        let ast = SCHEME
+            .parse(r#"upper(http.path) contains "/ADMIN""#)
+            .unwrap();
+
+        assert_json!(
+            ast,
+            {
+                "function": {
+                    "name": "upper",
+                    "args": [{ "field": "http.path" }]
+                },
+                "op": "Contains",
+                "rhs": "/ADMIN"
+            }
+        );
+
+        let filter = ast.compile();
+
+        assert_eq!(filter.execute(&ctx("", "/Admin/login", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("", "/ADMIN", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("", "/adm/in", 80)), Ok(false));
+    }
+
+    #[test]
+    fn test_len() {
+        let ast = SCHEME.parse("len(http.path) > 5").unwrap();
+
+        assert_json!(
+            ast,
+            {
+                "function": {
+                    "name": "len",
+                    "args": [{ "field": "http.path" }]
+                },
+                "op": "GreaterThan",
+                "rhs": 5
+            }
+        );
+
+        let filter = ast.compile();
+
+        assert_eq!(filter.execute(&ctx("", "/index.html", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("", "/12345", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("", "/1234", 80)), Ok(false));
+    }
+
+    #[test]
+    fn test_starts_with() {
+        let ast = SCHEME.parse(r#"starts_with(http.path, "/api/")"#).unwrap();
+
+        assert_json!(
+            ast,
+            {
+                "function": {
+                    "name": "starts_with",
+                    "args": [{ "field": "http.path" }, "/api/"]
+                },
+                "op": "IsTrue"
+            }
+        );
+
+        let filter = ast.compile();
+
+        assert_eq!(filter.execute(&ctx("", "/api/v1", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("", "/v1/api/", 80)), Ok(false));
+        assert_eq!(filter.execute(&ctx("", "/api", 80)), Ok(false));
+    }
+
+    #[test]
+    fn test_ends_with() {
+        let ast = SCHEME
+            .parse(r#"not ends_with(http.host, ".example.org")"#)
+            .unwrap();
+
+        assert_json!(
+            ast,
+            {
+                "op": "Not",
+                "arg": {
+                    "function": {
+                        "name": "ends_with",
+                        "args": [{ "field": "http.host" }, ".example.org"]
+                    },
+                    "op": "IsTrue"
+                }
+            }
+        );
+
+        let filter = ast.compile();
+
+        assert_eq!(filter.execute(&ctx("www.example.org", "/", 80)), Ok(false));
+        assert_eq!(filter.execute(&ctx("example.org", "/", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("www.example.com", "/", 80)), Ok(true));
+    }
+
+    #[test]
+    fn test_concat() {
+        let ast = SCHEME
+            .parse(r#"concat(http.host, http.path) == "example.org/""#)
+            .unwrap();
+
+        assert_json!(
+            ast,
+            {
+                "function": {
+                    "name": "concat",
+                    "args": [{ "field": "http.host" }, { "field": "http.path" }]
+                },
+                "op": "Equal",
+                "rhs": "example.org/"
+            }
+        );
+
+        let filter = ast.compile();
+
+        assert_eq!(filter.execute(&ctx("example.org", "/", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("example.org/", "", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("example.org", "/a", 80)), Ok(false));
+    }
+
+    #[test]
+    fn test_substring() {
+        let ast = SCHEME
+            .parse(r#"substring(http.path, 1, 4) == "api""#)
+            .unwrap();
+
+        assert_json!(
+            ast,
+            {
+                "function": {
+                    "name": "substring",
+                    "args": [{ "field": "http.path" }, 1, 4]
+                },
+                "op": "Equal",
+                "rhs": "api"
+            }
+        );
+
+        let filter = ast.compile();
+
+        assert_eq!(filter.execute(&ctx("", "/api/v1", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("", "/ap", 80)), Ok(false));
+        assert_eq!(filter.execute(&ctx("", "", 80)), Ok(false));
+
+        // negative offsets are counted from the end
+        let filter = SCHEME
+            .parse(r#"substring(http.path, -5) == ".html""#)
+            .unwrap()
+            .compile();
+
+        assert_eq!(filter.execute(&ctx("", "/index.html", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("", "html", 80)), Ok(false));
+
+        let filter = SCHEME
+            .parse(r#"substring(http.path, 0, -1) == "/api""#)
+            .unwrap()
+            .compile();
+
+        assert_eq!(filter.execute(&ctx("", "/api/", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("", "/api", 80)), Ok(false));
+
+        // offsets beyond any length are clamped
+        let filter = SCHEME
+            .parse(r#"substring(http.path, -9223372036854775808, 9223372036854775807) == "/api""#)
+            .unwrap()
+            .compile();
+
+        assert_eq!(filter.execute(&ctx("", "/api", 80)), Ok(true));
+    }
+
+    #[test]
+    fn test_url_decode() {
+        let ast = SCHEME
+            .parse(r#"url_decode(http.path) contains "<script>""#)
+            .unwrap();
+
+        assert_json!(
+            ast,
+            {
+                "function": {
+                    "name": "url_decode",
+                    "args": [{ "field": "http.path" }]
+                },
+                "op": "Contains",
+                "rhs": "<script>"
+            }
+        );
+
+        let filter = ast.compile();
+
+        assert_eq!(filter.execute(&ctx("", "/?q=%3Cscript%3e", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("", "/?q=<script>", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("", "/?q=%3Cscript%3", 80)), Ok(false));
+
+        let filter = SCHEME
+            .parse(r#"url_decode(http.path) == "/a b%zz%""#)
+            .unwrap()
+            .compile();
+
+        assert_eq!(filter.execute(&ctx("", "/a+b%zz%", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("", "/a%20b%zz%25", 80)), Ok(true));
+    }
+
+    #[test]
+    fn test_remove_bytes() {
+        let ast = SCHEME
+            .parse(r#"remove_bytes(http.host, ".-") == "wwwexampleorg""#)
+            .unwrap();
+
+        assert_json!(
+            ast,
+            {
+                "function": {
+                    "name": "remove_bytes",
+                    "args": [{ "field": "http.host" }, ".-"]
+                },
+                "op": "Equal",
+                "rhs": "wwwexampleorg"
+            }
+        );
+
+        let filter = ast.compile();
+
+        assert_eq!(filter.execute(&ctx("www.example.org", "/", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("www-example.org", "/", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("www_example.org", "/", 80)), Ok(false));
+    }
+
+    #[test]
+    fn test_to_string() {
+        let ast = SCHEME
+            .parse(r#"to_string(tcp.port) in {"80" "443"}"#)
+            .unwrap();
+
+        assert_json!(
+            ast,
+            {
+                "function": {
+                    "name": "to_string",
+                    "args": [{ "field": "tcp.port" }]
+                },
+                "op": "OneOf",
+                "rhs": ["80", "443"]
+            }
+        );
+
+        let filter = ast.compile();
+
+        assert_eq!(filter.execute(&ctx("", "/", 80)), Ok(true));
+        assert_eq!(filter.execute(&ctx("", "/", 443)), Ok(true));
+        assert_eq!(filter.execute(&ctx("", "/", 8080)), Ok(false));
+    }
+
+    #[test]
+    fn test_now() {
+        let filter = SCHEME
+            .parse("now() >= 2019-04-01T00:00:00Z")
+            .unwrap()
+            .compile();
+
+        let mut ctx = ExecutionContext::new(&SCHEME);
+
+        ctx.set_now(Timestamp::from_unix_secs(1_554_076_800));
+        assert_eq!(filter.execute(&ctx), Ok(true));
+
+        ctx.set_now(Timestamp::from_unix_secs(1_554_076_799));
+        assert_eq!(filter.execute(&ctx), Ok(false));
+    }
+
+    #[test]
+    fn test_since_until() {
+        let ast = SCHEME.parse("since(http.timestamp) < 5m").unwrap();
+
+        assert_json!(
+            ast,
+            {
+                "function": {
+                    "name": "since",
+                    "args": [{ "field": "http.timestamp" }]
+                },
+                "op": "LessThan",
+                "rhs": "5m"
+            }
+        );
+
+        let filter = ast.compile();
+
+        let mut ctx = ExecutionContext::new(&SCHEME);
+        ctx.set_now(Timestamp::from_unix_secs(1_000_000));
+
+        ctx.set_field_value("http.timestamp", Timestamp::from_unix_secs(999_701))
+            .unwrap();
+        assert_eq!(filter.execute(&ctx), Ok(true));
+
+        ctx.set_field_value("http.timestamp", Timestamp::from_unix_secs(999_700))
+            .unwrap();
+        assert_eq!(filter.execute(&ctx), Ok(false));
+
+        // certificates that expire within 30 days, but haven't expired yet
+        let filter = SCHEME
+            .parse("until(tls.not_after) in { 0s..30d }")
+            .unwrap()
+            .compile();
+
+        ctx.set_field_value("tls.not_after", Timestamp::from_unix_secs(1_000_000))
+            .unwrap();
+        assert_eq!(filter.execute(&ctx), Ok(true));
+
+        ctx.set_field_value("tls.not_after", Timestamp::from_unix_secs(3_592_001))
+            .unwrap();
+        assert_eq!(filter.execute(&ctx), Ok(false));
+
+        ctx.set_field_value("tls.not_after", Timestamp::from_unix_secs(999_999))
+            .unwrap();
+        assert_eq!(filter.execute(&ctx), Ok(false));
+    }
+}
@@ -1,11 +1,19 @@
-use lex::{expect, skip_space, Lex, LexResult, LexWith};
-use rhs_types::{Bytes, IpRange, UninhabitedBool};
-use serde::{Deserialize, Serialize};
+use deserialize::{DeserializeError, FromRaw, FromRawWith, JsonPath, RawValue};
+use failure::Fail;
+use lex::{expect, skip_space, Lex, LexErrorKind, LexResult, LexWith};
+use lhs_types::{Array, Map};
+use rhs_types::{Bytes, Duration, Float, IpRange, Mac, MacRange, Timestamp, UninhabitedBool};
+use serde::{
+    de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 use std::{
+    borrow::Cow,
     cmp::Ordering,
-    fmt::{self, Debug, Formatter},
+    fmt::{self, Debug, Display, Formatter},
     net::IpAddr,
     ops::RangeInclusive,
+    str,
 };
 use strict_partial_ord::StrictPartialOrd;
 
@@ -25,6 +33,18 @@ fn lex_rhs_values<'i, T: Lex<'i>>(input: &'i str) -> LexResult<'i, Vec<T>> {
     }
 }
 
+fn from_raw_rhs_values<T: FromRaw>(
+    value: &RawValue,
+    path: &JsonPath<'_>,
+) -> Result<Vec<T>, DeserializeError> {
+    value
+        .as_array(path)?
+        .iter()
+        .enumerate()
+        .map(|(i, item)| T::from_raw(item, &path.index(i)))
+        .collect()
+}
+
 macro_rules! declare_types {
     ($(# $attrs:tt)* enum $name:ident $(<$lt:tt>)* { $($(# $vattrs:tt)* $variant:ident ( $ty:ty ) , )* }) => {
         $(# $attrs)*
@@ -50,12 +70,17 @@ macro_rules! declare_types {
         }
     };
 
-    ($($(# $attrs:tt)* $name:ident ( $lhs_ty:ty | $rhs_ty:ty | $multi_rhs_ty:ty ) , )*) => {
+    ($($(# $attrs:tt)* $name:ident ( $(#[$lhs_attrs:meta])* $lhs_ty:ty | $rhs_ty:ty | $multi_rhs_ty:ty ) , )*) => {
         /// Enumeration of supported types for field values.
-        #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
-        #[repr(u8)]
+        #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
         pub enum Type {
             $($(# $attrs)* $name,)*
+
+            /// An array of values of the same type.
+            Array(Box<Type>),
+
+            /// A map from byte keys to values of the same type.
+            Map(Box<Type>),
         }
 
         /// Provides a way to get a [`Type`] of the implementor.
@@ -66,20 +91,53 @@ macro_rules! declare_types {
 
         impl GetType for Type {
             fn get_type(&self) -> Type {
-                *self
+                self.clone()
             }
         }
 
-        declare_types! {
-            /// An LHS value provided for filter execution.
-            ///
-            /// These are passed to the [execution context](::ExecutionContext)
-            /// and are used by [filters](::Filter)
-            /// for execution and comparisons.
-            #[derive(PartialEq, Eq, Clone, Deserialize)]
-            #[serde(untagged)]
-            enum LhsValue<'a> {
-                $($(# $attrs)* $name($lhs_ty),)*
+        /// An LHS value provided for filter execution.
+        ///
+        /// These are passed to the [execution context](::ExecutionContext)
+        /// and are used by [filters](::Filter)
+        /// for execution and comparisons.
+        ///
+        /// The untagged `Deserialize` implementation guesses the type from
+        /// the value itself, so it never produces a `Mac`, `Timestamp` or
+        /// `Duration`: they are read as bytes or integers instead. Use
+        /// [`LhsValueSeed`](::LhsValueSeed), or deserialize an
+        /// [`ExecutionContext`](::ExecutionContext), to read values of the
+        /// types declared in a scheme.
+        #[derive(PartialEq, Eq, Clone, Deserialize)]
+        #[serde(untagged)]
+        pub enum LhsValue<'a> {
+            $($(# $attrs)* $(#[$lhs_attrs])* $name($lhs_ty),)*
+
+            /// An array of values of the same type.
+            #[serde(borrow)]
+            Array(Array<'a>),
+
+            /// A map from byte keys to values of the same type.
+            #[serde(borrow)]
+            Map(Map<'a>),
+        }
+
+        impl<'a> GetType for LhsValue<'a> {
+            fn get_type(&self) -> Type {
+                match self {
+                    $(LhsValue::$name(_) => Type::$name,)*
+                    LhsValue::Array(array) => array.get_type(),
+                    LhsValue::Map(map) => map.get_type(),
+                }
+            }
+        }
+
+        impl<'a> Debug for LhsValue<'a> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                match self {
+                    $(LhsValue::$name(inner) => Debug::fmt(inner, f),)*
+                    LhsValue::Array(array) => Debug::fmt(array, f),
+                    LhsValue::Map(map) => Debug::fmt(map, f),
+                }
             }
         }
 
@@ -98,13 +156,36 @@ macro_rules! declare_types {
             }
         }
 
-        impl<'i> LexWith<'i, Type> for RhsValue {
-            fn lex_with(input: &str, ty: Type) -> LexResult<'_, Self> {
+        impl<'i, 't> LexWith<'i, &'t Type> for RhsValue {
+            fn lex_with(input: &'i str, ty: &'t Type) -> LexResult<'i, Self> {
                 Ok(match ty {
                     $(Type::$name => {
                         let (value, input) = <$rhs_ty>::lex(input)?;
                         (RhsValue::$name(value), input)
                     })*
+                    Type::Array(_) | Type::Map(_) => {
+                        return Err((
+                            LexErrorKind::UnsupportedOp { field_type: ty.clone() },
+                            input,
+                        ));
+                    }
+                })
+            }
+        }
+
+        impl<'t> FromRawWith<&'t Type> for RhsValue {
+            fn from_raw_with(
+                value: &RawValue,
+                path: &JsonPath<'_>,
+                ty: &'t Type,
+            ) -> Result<Self, DeserializeError> {
+                Ok(match ty {
+                    $(Type::$name => RhsValue::$name(<$rhs_ty>::from_raw(value, path)?),)*
+                    Type::Array(_) | Type::Map(_) => {
+                        return Err(path.error(LexErrorKind::UnsupportedOp {
+                            field_type: ty.clone(),
+                        }));
+                    }
                 })
             }
         }
@@ -140,13 +221,36 @@ macro_rules! declare_types {
             }
         }
 
-        impl<'i> LexWith<'i, Type> for RhsValues {
-            fn lex_with(input: &str, ty: Type) -> LexResult<'_, Self> {
+        impl<'i, 't> LexWith<'i, &'t Type> for RhsValues {
+            fn lex_with(input: &'i str, ty: &'t Type) -> LexResult<'i, Self> {
                 Ok(match ty {
                     $(Type::$name => {
                         let (value, input) = lex_rhs_values(input)?;
                         (RhsValues::$name(value), input)
                     })*
+                    Type::Array(_) | Type::Map(_) => {
+                        return Err((
+                            LexErrorKind::UnsupportedOp { field_type: ty.clone() },
+                            input,
+                        ));
+                    }
+                })
+            }
+        }
+
+        impl<'t> FromRawWith<&'t Type> for RhsValues {
+            fn from_raw_with(
+                value: &RawValue,
+                path: &JsonPath<'_>,
+                ty: &'t Type,
+            ) -> Result<Self, DeserializeError> {
+                Ok(match ty {
+                    $(Type::$name => RhsValues::$name(from_raw_rhs_values(value, path)?),)*
+                    Type::Array(_) | Type::Map(_) => {
+                        return Err(path.error(LexErrorKind::UnsupportedOp {
+                            field_type: ty.clone(),
+                        }));
+                    }
                 })
             }
         }
@@ -154,12 +258,308 @@ macro_rules! declare_types {
 }
 
 // special case for simply passing strings
+// Strings are borrowed from the input when possible, and decoded into owned
+// bytes otherwise, e.g. when they contain escape sequences. Arrays of octets
+// aren't accepted here, as they would be ambiguous with arrays of integers.
+fn deserialize_bytes<'de: 'a, 'a, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Cow<'a, [u8]>, D::Error> {
+    deserializer.deserialize_str(BytesVisitor)
+}
+
+impl<'a> LhsValue<'a> {
+    /// Returns a cheap copy of the value that borrows bytes from the
+    /// original instead of cloning them.
+    pub(crate) fn as_ref(&self) -> LhsValue<'_> {
+        match self {
+            LhsValue::Bytes(bytes) => LhsValue::Bytes(Cow::Borrowed(bytes)),
+            _ => self.clone(),
+        }
+    }
+}
+
+// Narrower integers are widened, so that e.g. 32-bit ports and ASNs can be
+// set without casting them first.
+impl<'a> From<i32> for LhsValue<'a> {
+    fn from(value: i32) -> Self {
+        LhsValue::Int(value.into())
+    }
+}
+
+impl<'a> From<u32> for LhsValue<'a> {
+    fn from(value: u32) -> Self {
+        LhsValue::Int(value.into())
+    }
+}
+
+// Writes an RHS value in the filter syntax.
+impl Display for RhsValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RhsValue::Ip(ip) => Display::fmt(ip, f),
+            RhsValue::Bytes(bytes) => Display::fmt(bytes, f),
+            RhsValue::Int(num) => Display::fmt(num, f),
+            RhsValue::Float(num) => Display::fmt(num, f),
+            RhsValue::Timestamp(ts) => Display::fmt(ts, f),
+            RhsValue::Duration(duration) => Display::fmt(duration, f),
+            RhsValue::Mac(mac) => Display::fmt(mac, f),
+            RhsValue::Bool(b) => match *b {},
+        }
+    }
+}
+
+fn fmt_range<T: Display + PartialEq>(
+    range: &RangeInclusive<T>,
+    f: &mut Formatter<'_>,
+) -> fmt::Result {
+    if range.start() == range.end() {
+        write!(f, "{}", range.start())
+    } else {
+        write!(f, "{}..{}", range.start(), range.end())
+    }
+}
+
+fn fmt_rhs_values<T>(
+    values: &[T],
+    f: &mut Formatter<'_>,
+    fmt_value: impl Fn(&T, &mut Formatter<'_>) -> fmt::Result,
+) -> fmt::Result {
+    f.write_str("{")?;
+    for (i, value) in values.iter().enumerate() {
+        if i != 0 {
+            f.write_str(" ")?;
+        }
+        fmt_value(value, f)?;
+    }
+    f.write_str("}")
+}
+
+// Writes a list of RHS values in the filter syntax.
+impl Display for RhsValues {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RhsValues::Ip(ranges) => fmt_rhs_values(ranges, f, Display::fmt),
+            RhsValues::Bytes(values) => fmt_rhs_values(values, f, Display::fmt),
+            RhsValues::Int(ranges) => fmt_rhs_values(ranges, f, fmt_range),
+            RhsValues::Float(ranges) => fmt_rhs_values(ranges, f, fmt_range),
+            RhsValues::Timestamp(ranges) => fmt_rhs_values(ranges, f, fmt_range),
+            RhsValues::Duration(ranges) => fmt_rhs_values(ranges, f, fmt_range),
+            RhsValues::Mac(ranges) => fmt_rhs_values(ranges, f, Display::fmt),
+            RhsValues::Bool(values) => fmt_rhs_values(values, f, |b, _| match *b {}),
+        }
+    }
+}
+
+impl From<RhsValue> for LhsValue<'static> {
+    fn from(value: RhsValue) -> Self {
+        match value {
+            RhsValue::Ip(ip) => LhsValue::Ip(ip),
+            RhsValue::Bytes(bytes) => Box::<[u8]>::from(bytes).into_vec().into(),
+            RhsValue::Int(num) => LhsValue::Int(num),
+            RhsValue::Float(num) => LhsValue::Float(num),
+            RhsValue::Timestamp(ts) => LhsValue::Timestamp(ts),
+            RhsValue::Duration(duration) => LhsValue::Duration(duration),
+            RhsValue::Mac(mac) => LhsValue::Mac(mac),
+            RhsValue::Bool(b) => match b {},
+        }
+    }
+}
+
+impl<'a> From<&'a [u8]> for LhsValue<'a> {
+    fn from(bytes: &'a [u8]) -> Self {
+        LhsValue::Bytes(Cow::Borrowed(bytes))
+    }
+}
+
 impl<'a> From<&'a str> for LhsValue<'a> {
     fn from(s: &'a str) -> Self {
         s.as_bytes().into()
     }
 }
 
+impl<'a> From<Vec<u8>> for LhsValue<'a> {
+    fn from(bytes: Vec<u8>) -> Self {
+        LhsValue::Bytes(Cow::Owned(bytes))
+    }
+}
+
+impl<'a> From<String> for LhsValue<'a> {
+    fn from(s: String) -> Self {
+        s.into_bytes().into()
+    }
+}
+
+impl<'a> From<f64> for LhsValue<'a> {
+    fn from(num: f64) -> Self {
+        LhsValue::Float(Float(num))
+    }
+}
+
+impl<'a> From<Array<'a>> for LhsValue<'a> {
+    fn from(array: Array<'a>) -> Self {
+        LhsValue::Array(array)
+    }
+}
+
+impl<'a> From<Map<'a>> for LhsValue<'a> {
+    fn from(map: Map<'a>) -> Self {
+        LhsValue::Map(map)
+    }
+}
+
+// Bytes are serialized as strings when possible and as arrays of octets
+// otherwise. The untagged `Deserialize` implementation reads the latter back
+// as an array of integers, so use `LhsValueSeed` to read values of a known
+// type as-is.
+impl<'a> Serialize for LhsValue<'a> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        match self {
+            LhsValue::Ip(ip) => ip.serialize(ser),
+            LhsValue::Bytes(bytes) => match str::from_utf8(bytes) {
+                Ok(s) => ser.serialize_str(s),
+                Err(_) => ser.collect_seq(bytes.iter()),
+            },
+            LhsValue::Int(num) => num.serialize(ser),
+            LhsValue::Float(num) => num.serialize(ser),
+            LhsValue::Timestamp(ts) => ts.serialize(ser),
+            LhsValue::Duration(duration) => duration.serialize(ser),
+            LhsValue::Mac(mac) => mac.serialize(ser),
+            LhsValue::Bool(b) => b.serialize(ser),
+            LhsValue::Array(array) => array.serialize(ser),
+            LhsValue::Map(map) => map.serialize(ser),
+        }
+    }
+}
+
+/// A [`DeserializeSeed`] that reads an [`LhsValue`] of a given [`Type`].
+///
+/// Unlike the untagged `Deserialize` implementation, which guesses the type
+/// from the value itself, this one reads e.g. `"127.0.0.1"` as bytes when
+/// bytes are expected, and accepts empty arrays and maps. Strings are
+/// borrowed from the input when possible, and copied otherwise, e.g. when
+/// they contain escape sequences.
+#[derive(Debug, Clone, Copy)]
+pub struct LhsValueSeed<'t>(pub &'t Type);
+
+impl<'de, 't> DeserializeSeed<'de> for LhsValueSeed<'t> {
+    type Value = LhsValue<'de>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        Ok(match self.0 {
+            Type::Ip => LhsValue::Ip(IpAddr::deserialize(deserializer)?),
+            Type::Bytes => LhsValue::Bytes(deserializer.deserialize_bytes(BytesVisitor)?),
+            Type::Int => LhsValue::Int(i64::deserialize(deserializer)?),
+            Type::Float => LhsValue::Float(Float::deserialize(deserializer)?),
+            Type::Timestamp => LhsValue::Timestamp(Timestamp::deserialize(deserializer)?),
+            Type::Duration => LhsValue::Duration(Duration::deserialize(deserializer)?),
+            Type::Mac => LhsValue::Mac(Mac::deserialize(deserializer)?),
+            Type::Bool => LhsValue::Bool(bool::deserialize(deserializer)?),
+            Type::Array(value_type) => {
+                LhsValue::Array(deserializer.deserialize_seq(ArrayVisitor(value_type))?)
+            }
+            Type::Map(value_type) => {
+                LhsValue::Map(deserializer.deserialize_map(MapVisitor(value_type))?)
+            }
+        })
+    }
+}
+
+// Accepts both forms produced by the `Serialize` implementation: strings and
+// arrays of octets.
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Cow<'de, [u8]>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a string or an array of bytes")
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, value: &'de str) -> Result<Self::Value, E> {
+        Ok(Cow::Borrowed(value.as_bytes()))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(value.as_bytes().to_vec()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(value.into_bytes()))
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, value: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(Cow::Borrowed(value))
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(value.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(value))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::new();
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+struct ArrayVisitor<'t>(&'t Type);
+
+impl<'de, 't> Visitor<'de> for ArrayVisitor<'t> {
+    type Value = Array<'de>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "an array of {:?} values", self.0)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut array = Array::new(self.0.clone());
+        while let Some(value) = seq.next_element_seed(LhsValueSeed(self.0))? {
+            array.push(value).map_err(de::Error::custom)?;
+        }
+        Ok(array)
+    }
+}
+
+struct MapVisitor<'t>(&'t Type);
+
+impl<'de, 't> Visitor<'de> for MapVisitor<'t> {
+    type Value = Map<'de>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "a map of {:?} values", self.0)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut map = Map::new(self.0.clone());
+        while let Some(key) = access.next_key::<String>()? {
+            let value = access.next_value_seed(LhsValueSeed(self.0))?;
+            map.insert(key.as_bytes(), value)
+                .map_err(de::Error::custom)?;
+        }
+        Ok(map)
+    }
+}
+
+/// An error that occurs when a value of one type is used where a value of
+/// another type was expected.
+#[derive(Debug, PartialEq, Fail)]
+#[fail(
+    display = "expected value of type {:?}, but got {:?}",
+    expected, actual
+)]
+pub struct TypeMismatchError {
+    /// The type that was expected.
+    pub expected: Type,
+    /// The type of the provided value.
+    pub actual: Type,
+}
+
 declare_types!(
     /// An IPv4 or IPv6 field.
     ///
@@ -170,10 +570,25 @@ declare_types!(
     ///
     /// These are completely interchangeable in runtime and differ only in
     /// syntax representation, so we represent them as a single type.
-    Bytes(&'a [u8] | Bytes | Bytes),
+    Bytes(
+        #[serde(deserialize_with = "deserialize_bytes")]
+        Cow<'a, [u8]> | Bytes | Bytes
+    ),
+
+    /// A 64-bit integer number.
+    Int(i64 | i64 | RangeInclusive<i64>),
+
+    /// A 64-bit floating point number.
+    Float(Float | Float | RangeInclusive<Float>),
+
+    /// A point in time, e.g. `2019-04-01T12:30:00Z`.
+    Timestamp(Timestamp | Timestamp | RangeInclusive<Timestamp>),
+
+    /// A span of time, e.g. `5m`.
+    Duration(Duration | Duration | RangeInclusive<Duration>),
 
-    /// A 32-bit integer number.
-    Int(i32 | i32 | RangeInclusive<i32>),
+    /// A MAC address, e.g. `00:1a:2b:3c:4d:5e`.
+    Mac(Mac | Mac | MacRange),
 
     /// A boolean.
     Bool(bool | UninhabitedBool | UninhabitedBool),
@@ -192,25 +607,123 @@ fn test_lhs_value_deserialize() {
     let bytes: LhsValue<'_> = serde_json::from_str("\"a JSON string with unicode ❤\"").unwrap();
     assert_eq!(
         bytes,
-        LhsValue::Bytes(b"a JSON string with unicode \xE2\x9D\xA4")
+        LhsValue::from(&b"a JSON string with unicode \xE2\x9D\xA4"[..])
     );
 
-    // Does not work because unicode escapes can't be borrowed directly from
-    // string but require another temporary string in which they are decoded
-    // and that string needs to be owned by someone, while LhsValue can hold
-    // only borrowed bytes.
-    assert!(
-        serde_json::from_str::<LhsValue<'_>>("\"a JSON string with escaped-unicode \\u2764\"")
-            .is_err(),
-        "LhsValue can only handle borrowed bytes"
+    match bytes {
+        LhsValue::Bytes(Cow::Borrowed(_)) => {}
+        _ => panic!("expected borrowed bytes"),
+    }
+
+    // Unicode escapes can't be borrowed directly from the string, so they
+    // are decoded into owned bytes instead.
+    let bytes: LhsValue<'_> =
+        serde_json::from_str("\"a JSON string with escaped-unicode \\u2764\"").unwrap();
+    assert_eq!(
+        bytes,
+        LhsValue::from("a JSON string with escaped-unicode ❤".to_owned())
     );
+    match bytes {
+        LhsValue::Bytes(Cow::Owned(_)) => {}
+        _ => panic!("expected owned bytes"),
+    }
 
     let bytes: LhsValue<'_> = serde_json::from_str("\"1337\"").unwrap();
-    assert_eq!(bytes, LhsValue::Bytes(b"1337"));
+    assert_eq!(bytes, LhsValue::from(&b"1337"[..]));
 
     let integer: LhsValue<'_> = serde_json::from_str("1337").unwrap();
     assert_eq!(integer, LhsValue::Int(1337));
 
     let b: LhsValue<'_> = serde_json::from_str("false").unwrap();
     assert_eq!(b, LhsValue::Bool(false));
+
+    let array: LhsValue<'_> = serde_json::from_str("[\"a\", \"b\"]").unwrap();
+    let mut expected = Array::new(Type::Bytes);
+    expected.push(&b"a"[..]).unwrap();
+    expected.push(&b"b"[..]).unwrap();
+    assert_eq!(array, LhsValue::Array(expected));
+
+    assert!(serde_json::from_str::<LhsValue<'_>>("[1, \"b\"]").is_err());
+    assert!(serde_json::from_str::<LhsValue<'_>>("[]").is_err());
+
+    let map: LhsValue<'_> = serde_json::from_str("{\"a\": 1, \"b\": 2}").unwrap();
+    let mut expected = Map::new(Type::Int);
+    expected.insert(b"a", 1).unwrap();
+    expected.insert(b"b", 2).unwrap();
+    assert_eq!(map, LhsValue::Map(expected));
+
+    assert!(serde_json::from_str::<LhsValue<'_>>("{\"a\": 1, \"b\": \"2\"}").is_err());
+    assert!(serde_json::from_str::<LhsValue<'_>>("{}").is_err());
+}
+
+#[test]
+fn test_lhs_value_seed() {
+    use serde_json::Deserializer;
+
+    let deserialize = |ty: Type, json: &'static str| {
+        LhsValueSeed(&ty).deserialize(&mut Deserializer::from_str(json))
+    };
+
+    assert_eq!(
+        deserialize(Type::Bytes, "\"127.0.0.1\"").unwrap(),
+        LhsValue::from("127.0.0.1")
+    );
+
+    // escaped strings are decoded into owned bytes
+    let bytes = deserialize(Type::Bytes, "\"escaped \\u2764\"").unwrap();
+    assert_eq!(bytes, LhsValue::from("escaped ❤"));
+    match bytes {
+        LhsValue::Bytes(Cow::Owned(_)) => {}
+        _ => panic!("expected owned bytes"),
+    }
+
+    assert_eq!(
+        deserialize(Type::Bytes, "[255, 0]").unwrap(),
+        LhsValue::from(&b"\xFF\x00"[..])
+    );
+
+    let array = deserialize(Type::Array(Box::new(Type::Int)), "[]").unwrap();
+    assert_eq!(array, LhsValue::Array(Array::new(Type::Int)));
+
+    let map = deserialize(
+        Type::Map(Box::new(Type::Array(Box::new(Type::Bytes)))),
+        r#"{"user-agent": ["curl"]}"#,
+    )
+    .unwrap();
+    let mut expected = Map::new(Type::Array(Box::new(Type::Bytes)));
+    let mut values = Array::new(Type::Bytes);
+    values.push("curl").unwrap();
+    expected.insert(b"user-agent", values).unwrap();
+    assert_eq!(map, LhsValue::Map(expected));
+
+    assert!(deserialize(Type::Int, "\"1337\"").is_err());
+    assert!(deserialize(Type::Array(Box::new(Type::Int)), "[1, \"b\"]").is_err());
+}
+
+#[test]
+fn test_lhs_value_serialize() {
+    use std::str::FromStr;
+
+    let json = |value: LhsValue<'_>| serde_json::to_string(&value).unwrap();
+
+    assert_eq!(
+        json(LhsValue::Ip(IpAddr::from_str("127.0.0.1").unwrap())),
+        "\"127.0.0.1\""
+    );
+    assert_eq!(json(LhsValue::from(&b"1337"[..])), "\"1337\"");
+    assert_eq!(json(LhsValue::from(&b"\xFF\x00"[..])), "[255,0]");
+    assert_eq!(json(LhsValue::Int(1337)), "1337");
+    assert_eq!(json(LhsValue::Bool(true)), "true");
+
+    let mut headers = Map::new(Type::Array(Box::new(Type::Bytes)));
+    let mut values = Array::new(Type::Bytes);
+    values.push("curl").unwrap();
+    headers.insert(b"user-agent", values).unwrap();
+
+    let serialized = json(headers.into());
+    assert_eq!(serialized, r#"{"user-agent":["curl"]}"#);
+
+    // serialization round-trips through the untagged deserialization
+    let value: LhsValue<'_> = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(json(value), serialized);
 }
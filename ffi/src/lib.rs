@@ -12,6 +12,7 @@ extern crate indoc;
 pub mod transfer_types;
 
 use fnv::FnvHasher;
+use libc::c_void;
 use std::{
     hash::Hasher,
     io::{self, Write},
@@ -21,10 +22,88 @@ use transfer_types::{
     ExternallyAllocatedByteArr, ExternallyAllocatedStr, RustAllocatedString, RustBox,
     StaticRustAllocatedString,
 };
-use wirefilter::{ExecutionContext, Filter, FilterAst, ParseError, Scheme, Type};
+use wirefilter::{
+    ExecutionContext, Field, Filter, FilterAst, LhsValue, List, Mac, ParseError, Scheme,
+    SetFieldValueError, Type, UnknownFieldError,
+};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// ABI-stable representation of primitive field types.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CType {
+    Ip,
+    Bytes,
+    Int,
+    Bool,
+    Float,
+    Mac,
+}
+
+impl From<CType> for Type {
+    fn from(ty: CType) -> Self {
+        match ty {
+            CType::Ip => Type::Ip,
+            CType::Bytes => Type::Bytes,
+            CType::Int => Type::Int,
+            CType::Bool => Type::Bool,
+            CType::Float => Type::Float,
+            CType::Mac => Type::Mac,
+        }
+    }
+}
+
+/// Outcome of setting a field value in an execution context.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetFieldValueStatus {
+    Ok,
+    UnknownField,
+    TypeMismatch,
+    SchemeMismatch,
+}
+
+impl From<SetFieldValueError> for SetFieldValueStatus {
+    fn from(err: SetFieldValueError) -> Self {
+        match err {
+            SetFieldValueError::UnknownField(_) => SetFieldValueStatus::UnknownField,
+            SetFieldValueError::TypeMismatch(_) => SetFieldValueStatus::TypeMismatch,
+            SetFieldValueError::SchemeMismatch(_) => SetFieldValueStatus::SchemeMismatch,
+        }
+    }
+}
+
+impl From<UnknownFieldError> for SetFieldValueStatus {
+    fn from(_: UnknownFieldError) -> Self {
+        SetFieldValueStatus::UnknownField
+    }
+}
+
+impl<E: Into<SetFieldValueStatus>> From<Result<(), E>> for SetFieldValueStatus {
+    fn from(result: Result<(), E>) -> Self {
+        match result {
+            Ok(()) => SetFieldValueStatus::Ok,
+            Err(err) => err.into(),
+        }
+    }
+}
+
+#[repr(u8)]
+pub enum GetFieldResult<'s> {
+    Err,
+    Ok(Field<'s>),
+}
+
+impl<'s> GetFieldResult<'s> {
+    pub fn unwrap(self) -> Field<'s> {
+        match self {
+            GetFieldResult::Err => panic!("unknown field"),
+            GetFieldResult::Ok(field) => field,
+        }
+    }
+}
+
 #[repr(u8)]
 pub enum ParsingResult<'s> {
     Err(RustAllocatedString),
@@ -52,6 +131,50 @@ impl<'s> ParsingResult<'s> {
     }
 }
 
+#[repr(u8)]
+pub enum ListParsingResult {
+    Err(RustAllocatedString),
+    Ok(RustBox<List>),
+}
+
+impl From<List> for ListParsingResult {
+    fn from(list: List) -> Self {
+        ListParsingResult::Ok(list.into())
+    }
+}
+
+impl<'a> From<ParseError<'a>> for ListParsingResult {
+    fn from(err: ParseError<'a>) -> Self {
+        ListParsingResult::Err(RustAllocatedString::from(err.to_string()))
+    }
+}
+
+impl ListParsingResult {
+    pub fn unwrap(self) -> RustBox<List> {
+        match self {
+            ListParsingResult::Err(err) => panic!("{}", &err as &str),
+            ListParsingResult::Ok(list) => list,
+        }
+    }
+}
+
+/// Outcome of adding a list to a scheme.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddListStatus {
+    Ok,
+    Redefinition,
+}
+
+/// Outcome of replacing contents of a list in a scheme.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplaceListStatus {
+    Ok,
+    UnknownList,
+    TypeMismatch,
+}
+
 #[no_mangle]
 pub extern "C" fn wirefilter_create_scheme() -> RustBox<Scheme> {
     Default::default()
@@ -66,9 +189,72 @@ pub extern "C" fn wirefilter_free_scheme(scheme: RustBox<Scheme>) {
 pub extern "C" fn wirefilter_add_type_field_to_scheme(
     scheme: &mut Scheme,
     name: ExternallyAllocatedStr<'_>,
-    ty: Type,
+    ty: CType,
 ) {
-    scheme.add_field(name.into_ref().to_owned(), ty).unwrap();
+    scheme
+        .add_field(name.into_ref().to_owned(), ty.into())
+        .unwrap();
+}
+
+/// Looks up a field once, so that values can be set by the returned handle
+/// without looking up the name each time.
+#[no_mangle]
+pub extern "C" fn wirefilter_get_field<'s>(
+    scheme: &'s Scheme,
+    name: ExternallyAllocatedStr<'_>,
+) -> GetFieldResult<'s> {
+    match scheme.get_field(name.into_ref()) {
+        Ok(field) => GetFieldResult::Ok(field),
+        Err(_) => GetFieldResult::Err,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_parse_list(
+    ty: CType,
+    input: ExternallyAllocatedStr<'_>,
+) -> ListParsingResult {
+    match List::parse(ty.into(), input.into_ref()) {
+        Ok(list) => ListParsingResult::from(list),
+        Err(err) => ListParsingResult::from(err),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_free_list(list: RustBox<List>) {
+    drop(list);
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_list_to_scheme(
+    scheme: &mut Scheme,
+    name: ExternallyAllocatedStr<'_>,
+    list: RustBox<List>,
+) -> AddListStatus {
+    match scheme.add_list(name.into_ref().to_owned(), *list.into_real_box()) {
+        Ok(()) => AddListStatus::Ok,
+        Err(_) => AddListStatus::Redefinition,
+    }
+}
+
+/// Swaps contents of a list registered in the scheme with contents of the
+/// given one, so that compiled filters see new values without being
+/// recompiled.
+///
+/// This can be called while filters are being matched on other threads.
+#[no_mangle]
+pub extern "C" fn wirefilter_replace_list_in_scheme(
+    scheme: &Scheme,
+    name: ExternallyAllocatedStr<'_>,
+    list: RustBox<List>,
+) -> ReplaceListStatus {
+    match scheme.get_list(name.into_ref()) {
+        Ok(target) => match target.replace(&list) {
+            Ok(()) => ReplaceListStatus::Ok,
+            Err(_) => ReplaceListStatus::TypeMismatch,
+        },
+        Err(_) => ReplaceListStatus::UnknownList,
+    }
 }
 
 #[no_mangle]
@@ -149,15 +335,37 @@ pub extern "C" fn wirefilter_free_execution_context(exec_context: RustBox<Execut
     drop(exec_context);
 }
 
+/// Removes all values from the execution context, so that it can be reused
+/// for values from other buffers instead of allocating a new one.
+#[no_mangle]
+pub extern "C" fn wirefilter_clear_execution_context(exec_context: &mut ExecutionContext<'_>) {
+    exec_context.clear();
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_unset_value_in_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+) -> SetFieldValueStatus {
+    exec_context.unset_field_value(name.into_ref()).into()
+}
+
 #[no_mangle]
 pub extern "C" fn wirefilter_add_int_value_to_execution_context<'a>(
     exec_context: &mut ExecutionContext<'a>,
     name: ExternallyAllocatedStr<'_>,
     value: i32,
-) {
-    exec_context
-        .set_field_value(name.into_ref(), value)
-        .unwrap();
+) -> SetFieldValueStatus {
+    exec_context.set_field_value(name.into_ref(), value).into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_int64_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+    value: i64,
+) -> SetFieldValueStatus {
+    exec_context.set_field_value(name.into_ref(), value).into()
 }
 
 #[no_mangle]
@@ -165,11 +373,22 @@ pub extern "C" fn wirefilter_add_bytes_value_to_execution_context<'a>(
     exec_context: &mut ExecutionContext<'a>,
     name: ExternallyAllocatedStr<'_>,
     value: ExternallyAllocatedByteArr<'a>,
-) {
+) -> SetFieldValueStatus {
     let slice: &[u8] = value.into_ref();
-    exec_context
-        .set_field_value(name.into_ref(), slice)
-        .unwrap();
+    exec_context.set_field_value(name.into_ref(), slice).into()
+}
+
+/// Like `wirefilter_add_bytes_value_to_execution_context`, but copies the
+/// bytes into the execution context, so that the buffer can be freed or
+/// reused right after the call.
+#[no_mangle]
+pub extern "C" fn wirefilter_add_owned_bytes_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+    value: ExternallyAllocatedByteArr<'_>,
+) -> SetFieldValueStatus {
+    let bytes: Vec<u8> = value.into_ref().to_vec();
+    exec_context.set_field_value(name.into_ref(), bytes).into()
 }
 
 #[no_mangle]
@@ -177,10 +396,10 @@ pub extern "C" fn wirefilter_add_ipv6_value_to_execution_context(
     exec_context: &mut ExecutionContext<'_>,
     name: ExternallyAllocatedStr<'_>,
     value: &[u8; 16],
-) {
+) -> SetFieldValueStatus {
     exec_context
         .set_field_value(name.into_ref(), IpAddr::from(*value))
-        .unwrap();
+        .into()
 }
 
 #[no_mangle]
@@ -188,10 +407,10 @@ pub extern "C" fn wirefilter_add_ipv4_value_to_execution_context(
     exec_context: &mut ExecutionContext<'_>,
     name: ExternallyAllocatedStr<'_>,
     value: &[u8; 4],
-) {
+) -> SetFieldValueStatus {
     exec_context
         .set_field_value(name.into_ref(), IpAddr::from(*value))
-        .unwrap();
+        .into()
 }
 
 #[no_mangle]
@@ -199,10 +418,292 @@ pub extern "C" fn wirefilter_add_bool_value_to_execution_context(
     exec_context: &mut ExecutionContext<'_>,
     name: ExternallyAllocatedStr<'_>,
     value: bool,
-) {
+) -> SetFieldValueStatus {
+    exec_context.set_field_value(name.into_ref(), value).into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_float_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+    value: f64,
+) -> SetFieldValueStatus {
+    exec_context.set_field_value(name.into_ref(), value).into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_eui48_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+    value: &[u8; 6],
+) -> SetFieldValueStatus {
     exec_context
-        .set_field_value(name.into_ref(), value)
-        .unwrap();
+        .set_field_value(name.into_ref(), Mac::from(*value))
+        .into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_eui64_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+    value: &[u8; 8],
+) -> SetFieldValueStatus {
+    exec_context
+        .set_field_value(name.into_ref(), Mac::from(*value))
+        .into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_int_value_to_execution_context_by_field<'a>(
+    exec_context: &mut ExecutionContext<'a>,
+    field: Field<'a>,
+    value: i32,
+) -> SetFieldValueStatus {
+    exec_context.set_by_field(field, value).into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_int64_value_to_execution_context_by_field<'a>(
+    exec_context: &mut ExecutionContext<'a>,
+    field: Field<'a>,
+    value: i64,
+) -> SetFieldValueStatus {
+    exec_context.set_by_field(field, value).into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_bytes_value_to_execution_context_by_field<'a>(
+    exec_context: &mut ExecutionContext<'a>,
+    field: Field<'a>,
+    value: ExternallyAllocatedByteArr<'a>,
+) -> SetFieldValueStatus {
+    let slice: &[u8] = value.into_ref();
+    exec_context.set_by_field(field, slice).into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_owned_bytes_value_to_execution_context_by_field<'a>(
+    exec_context: &mut ExecutionContext<'a>,
+    field: Field<'a>,
+    value: ExternallyAllocatedByteArr<'_>,
+) -> SetFieldValueStatus {
+    let bytes: Vec<u8> = value.into_ref().to_vec();
+    exec_context.set_by_field(field, bytes).into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_ipv6_value_to_execution_context_by_field<'a>(
+    exec_context: &mut ExecutionContext<'a>,
+    field: Field<'a>,
+    value: &[u8; 16],
+) -> SetFieldValueStatus {
+    exec_context
+        .set_by_field(field, IpAddr::from(*value))
+        .into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_ipv4_value_to_execution_context_by_field<'a>(
+    exec_context: &mut ExecutionContext<'a>,
+    field: Field<'a>,
+    value: &[u8; 4],
+) -> SetFieldValueStatus {
+    exec_context
+        .set_by_field(field, IpAddr::from(*value))
+        .into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_bool_value_to_execution_context_by_field<'a>(
+    exec_context: &mut ExecutionContext<'a>,
+    field: Field<'a>,
+    value: bool,
+) -> SetFieldValueStatus {
+    exec_context.set_by_field(field, value).into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_float_value_to_execution_context_by_field<'a>(
+    exec_context: &mut ExecutionContext<'a>,
+    field: Field<'a>,
+    value: f64,
+) -> SetFieldValueStatus {
+    exec_context.set_by_field(field, value).into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_eui48_value_to_execution_context_by_field<'a>(
+    exec_context: &mut ExecutionContext<'a>,
+    field: Field<'a>,
+    value: &[u8; 6],
+) -> SetFieldValueStatus {
+    exec_context.set_by_field(field, Mac::from(*value)).into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_eui64_value_to_execution_context_by_field<'a>(
+    exec_context: &mut ExecutionContext<'a>,
+    field: Field<'a>,
+    value: &[u8; 8],
+) -> SetFieldValueStatus {
+    exec_context.set_by_field(field, Mac::from(*value)).into()
+}
+
+/// A callback that lazily provides a value of a field.
+///
+/// It receives an opaque `user_data` pointer given on registration, and should
+/// either write the value into `value` and return `true`, or return `false`
+/// if the field has no value.
+///
+/// The callback is invoked by whichever thread first executes a filter that
+/// reaches the field, so both it and `user_data` must be safe to use there.
+pub type LazyValueCallback<T> = extern "C" fn(user_data: *mut c_void, value: &mut T) -> bool;
+
+// The state of a lazy value captured by its provider.
+struct LazyValue<T> {
+    callback: LazyValueCallback<T>,
+    user_data: *mut c_void,
+    value: T,
+}
+
+// The raw pointers are owned by the caller, who is responsible for making them
+// usable from any thread, as documented on `LazyValueCallback`.
+unsafe impl<T> Send for LazyValue<T> {}
+
+fn add_lazy_value_to_execution_context<'e, T: 'e, V: 'e + Into<LhsValue<'e>>>(
+    exec_context: &mut ExecutionContext<'e>,
+    name: ExternallyAllocatedStr<'_>,
+    callback: LazyValueCallback<T>,
+    user_data: *mut c_void,
+    value: T,
+    convert: fn(T) -> V,
+) -> SetFieldValueStatus {
+    let mut lazy = LazyValue {
+        callback,
+        user_data,
+        value,
+    };
+
+    exec_context
+        .set_field_value_provider(name.into_ref(), move || {
+            if (lazy.callback)(lazy.user_data, &mut lazy.value) {
+                Some(convert(lazy.value).into())
+            } else {
+                None
+            }
+        })
+        .into()
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_lazy_int_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+    callback: LazyValueCallback<i32>,
+    user_data: *mut c_void,
+) -> SetFieldValueStatus {
+    add_lazy_value_to_execution_context(exec_context, name, callback, user_data, 0, i32::from)
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_lazy_int64_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+    callback: LazyValueCallback<i64>,
+    user_data: *mut c_void,
+) -> SetFieldValueStatus {
+    add_lazy_value_to_execution_context(exec_context, name, callback, user_data, 0, i64::from)
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_lazy_bytes_value_to_execution_context<'a>(
+    exec_context: &mut ExecutionContext<'a>,
+    name: ExternallyAllocatedStr<'_>,
+    callback: LazyValueCallback<ExternallyAllocatedByteArr<'a>>,
+    user_data: *mut c_void,
+) -> SetFieldValueStatus {
+    add_lazy_value_to_execution_context(
+        exec_context,
+        name,
+        callback,
+        user_data,
+        ExternallyAllocatedByteArr::from(&[][..]),
+        ExternallyAllocatedByteArr::into_ref,
+    )
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_lazy_ipv6_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+    callback: LazyValueCallback<[u8; 16]>,
+    user_data: *mut c_void,
+) -> SetFieldValueStatus {
+    add_lazy_value_to_execution_context(
+        exec_context,
+        name,
+        callback,
+        user_data,
+        [0; 16],
+        IpAddr::from,
+    )
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_lazy_ipv4_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+    callback: LazyValueCallback<[u8; 4]>,
+    user_data: *mut c_void,
+) -> SetFieldValueStatus {
+    add_lazy_value_to_execution_context(
+        exec_context,
+        name,
+        callback,
+        user_data,
+        [0; 4],
+        IpAddr::from,
+    )
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_lazy_bool_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+    callback: LazyValueCallback<bool>,
+    user_data: *mut c_void,
+) -> SetFieldValueStatus {
+    add_lazy_value_to_execution_context(exec_context, name, callback, user_data, false, bool::from)
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_lazy_float_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+    callback: LazyValueCallback<f64>,
+    user_data: *mut c_void,
+) -> SetFieldValueStatus {
+    add_lazy_value_to_execution_context(exec_context, name, callback, user_data, 0.0, f64::from)
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_lazy_eui48_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+    callback: LazyValueCallback<[u8; 6]>,
+    user_data: *mut c_void,
+) -> SetFieldValueStatus {
+    add_lazy_value_to_execution_context(exec_context, name, callback, user_data, [0; 6], Mac::from)
+}
+
+#[no_mangle]
+pub extern "C" fn wirefilter_add_lazy_eui64_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name: ExternallyAllocatedStr<'_>,
+    callback: LazyValueCallback<[u8; 8]>,
+    user_data: *mut c_void,
+) -> SetFieldValueStatus {
+    add_lazy_value_to_execution_context(exec_context, name, callback, user_data, [0; 8], Mac::from)
 }
 
 #[no_mangle]
@@ -250,34 +751,34 @@ mod ffi_test {
         wirefilter_add_type_field_to_scheme(
             &mut scheme,
             ExternallyAllocatedStr::from("ip1"),
-            Type::Ip,
+            CType::Ip,
         );
         wirefilter_add_type_field_to_scheme(
             &mut scheme,
             ExternallyAllocatedStr::from("ip2"),
-            Type::Ip,
+            CType::Ip,
         );
 
         wirefilter_add_type_field_to_scheme(
             &mut scheme,
             ExternallyAllocatedStr::from("str1"),
-            Type::Bytes,
+            CType::Bytes,
         );
         wirefilter_add_type_field_to_scheme(
             &mut scheme,
             ExternallyAllocatedStr::from("str2"),
-            Type::Bytes,
+            CType::Bytes,
         );
 
         wirefilter_add_type_field_to_scheme(
             &mut scheme,
             ExternallyAllocatedStr::from("num1"),
-            Type::Int,
+            CType::Int,
         );
         wirefilter_add_type_field_to_scheme(
             &mut scheme,
             ExternallyAllocatedStr::from("num2"),
-            Type::Int,
+            CType::Int,
         );
 
         scheme
@@ -329,10 +830,10 @@ mod ffi_test {
         wirefilter_parse_filter(scheme, ExternallyAllocatedStr::from(input))
     }
 
-    fn match_filter(
+    fn match_filter<'s>(
         input: &'static str,
-        scheme: &Scheme,
-        exec_context: &ExecutionContext<'_>,
+        scheme: &'s Scheme,
+        exec_context: &ExecutionContext<'s>,
     ) -> bool {
         let filter = parse_filter(scheme, input).unwrap();
         let filter = wirefilter_compile_filter(filter);
@@ -476,6 +977,498 @@ mod ffi_test {
         assert!(re.is_match(version.into_ref()));
     }
 
+    #[test]
+    fn lazy_values() {
+        extern "C" fn provide_num(calls: *mut c_void, value: &mut i32) -> bool {
+            unsafe { *(calls as *mut i32) += 1 };
+            *value = 42;
+            true
+        }
+
+        extern "C" fn provide_str<'a>(
+            _: *mut c_void,
+            value: &mut ExternallyAllocatedByteArr<'a>,
+        ) -> bool {
+            *value = ExternallyAllocatedByteArr::from("yo123");
+            true
+        }
+
+        extern "C" fn provide_nothing(_: *mut c_void, _: &mut [u8; 4]) -> bool {
+            false
+        }
+
+        let scheme = create_scheme();
+
+        {
+            let mut calls = 0i32;
+            let mut exec_context = wirefilter_create_execution_context(&scheme);
+
+            wirefilter_add_lazy_int_value_to_execution_context(
+                &mut exec_context,
+                ExternallyAllocatedStr::from("num1"),
+                provide_num,
+                &mut calls as *mut i32 as *mut c_void,
+            );
+
+            wirefilter_add_lazy_bytes_value_to_execution_context(
+                &mut exec_context,
+                ExternallyAllocatedStr::from("str2"),
+                provide_str,
+                std::ptr::null_mut(),
+            );
+
+            wirefilter_add_lazy_ipv4_value_to_execution_context(
+                &mut exec_context,
+                ExternallyAllocatedStr::from("ip1"),
+                provide_nothing,
+                std::ptr::null_mut(),
+            );
+
+            assert!(match_filter(
+                r#"num1 == 42 && str2 ~ "yo\d+""#,
+                &scheme,
+                &exec_context
+            ));
+
+            assert!(match_filter("num1 > 41", &scheme, &exec_context));
+
+            assert!(!match_filter("ip1 == 127.0.0.1", &scheme, &exec_context));
+
+            assert!(!match_filter("ip1 != 127.0.0.1", &scheme, &exec_context));
+
+            wirefilter_free_execution_context(exec_context);
+
+            assert_eq!(calls, 1);
+        }
+
+        wirefilter_free_scheme(scheme);
+    }
+
+    #[test]
+    fn set_field_value_status() {
+        extern "C" fn provide_num(_: *mut c_void, value: &mut i32) -> bool {
+            *value = 42;
+            true
+        }
+
+        let scheme = create_scheme();
+
+        {
+            let mut exec_context = wirefilter_create_execution_context(&scheme);
+
+            assert_eq!(
+                wirefilter_add_int_value_to_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("num1"),
+                    42,
+                ),
+                SetFieldValueStatus::Ok
+            );
+
+            assert_eq!(
+                wirefilter_add_int_value_to_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("num3"),
+                    42,
+                ),
+                SetFieldValueStatus::UnknownField
+            );
+
+            assert_eq!(
+                wirefilter_add_bool_value_to_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("num1"),
+                    true,
+                ),
+                SetFieldValueStatus::TypeMismatch
+            );
+
+            assert_eq!(
+                wirefilter_add_lazy_int_value_to_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("num3"),
+                    provide_num,
+                    std::ptr::null_mut(),
+                ),
+                SetFieldValueStatus::UnknownField
+            );
+
+            wirefilter_free_execution_context(exec_context);
+        }
+
+        wirefilter_free_scheme(scheme);
+    }
+
+    #[test]
+    fn values_by_field() {
+        let scheme = create_scheme();
+        let other_scheme = create_scheme();
+
+        {
+            let num1 = wirefilter_get_field(&scheme, ExternallyAllocatedStr::from("num1")).unwrap();
+            let str2 = wirefilter_get_field(&scheme, ExternallyAllocatedStr::from("str2")).unwrap();
+            let ip1 = wirefilter_get_field(&scheme, ExternallyAllocatedStr::from("ip1")).unwrap();
+
+            match wirefilter_get_field(&scheme, ExternallyAllocatedStr::from("num3")) {
+                GetFieldResult::Err => {}
+                GetFieldResult::Ok(_) => panic!("Error expected"),
+            }
+
+            let mut exec_context = wirefilter_create_execution_context(&scheme);
+
+            assert_eq!(
+                wirefilter_add_int_value_to_execution_context_by_field(&mut exec_context, num1, 42),
+                SetFieldValueStatus::Ok
+            );
+
+            assert_eq!(
+                wirefilter_add_bytes_value_to_execution_context_by_field(
+                    &mut exec_context,
+                    str2,
+                    ExternallyAllocatedByteArr::from("yo123"),
+                ),
+                SetFieldValueStatus::Ok
+            );
+
+            assert_eq!(
+                wirefilter_add_ipv4_value_to_execution_context_by_field(
+                    &mut exec_context,
+                    ip1,
+                    &[127, 0, 0, 1],
+                ),
+                SetFieldValueStatus::Ok
+            );
+
+            assert_eq!(
+                wirefilter_add_bool_value_to_execution_context_by_field(
+                    &mut exec_context,
+                    num1,
+                    true,
+                ),
+                SetFieldValueStatus::TypeMismatch
+            );
+
+            assert!(match_filter(
+                r#"num1 == 42 && str2 ~ "yo\d+" && ip1 == 127.0.0.1"#,
+                &scheme,
+                &exec_context
+            ));
+
+            let other_num1 =
+                wirefilter_get_field(&other_scheme, ExternallyAllocatedStr::from("num1")).unwrap();
+
+            assert_eq!(
+                wirefilter_add_int_value_to_execution_context_by_field(
+                    &mut exec_context,
+                    other_num1,
+                    42,
+                ),
+                SetFieldValueStatus::SchemeMismatch
+            );
+
+            wirefilter_free_execution_context(exec_context);
+        }
+
+        wirefilter_free_scheme(other_scheme);
+        wirefilter_free_scheme(scheme);
+    }
+
+    #[test]
+    fn int64_values() {
+        extern "C" fn provide_max(_: *mut c_void, value: &mut i64) -> bool {
+            *value = i64::MAX;
+            true
+        }
+
+        let scheme = create_scheme();
+
+        {
+            let num2 = wirefilter_get_field(&scheme, ExternallyAllocatedStr::from("num2")).unwrap();
+
+            let mut exec_context = wirefilter_create_execution_context(&scheme);
+
+            assert_eq!(
+                wirefilter_add_int64_value_to_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("num1"),
+                    0x1_0000_0000,
+                ),
+                SetFieldValueStatus::Ok
+            );
+
+            assert_eq!(
+                wirefilter_add_int64_value_to_execution_context_by_field(
+                    &mut exec_context,
+                    num2,
+                    -5_000_000_000,
+                ),
+                SetFieldValueStatus::Ok
+            );
+
+            assert!(match_filter(
+                "num1 == 4294967296 && num1 & 0x100000000 && num2 < -2147483648",
+                &scheme,
+                &exec_context
+            ));
+
+            assert_eq!(
+                wirefilter_add_lazy_int64_value_to_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("num1"),
+                    provide_max,
+                    std::ptr::null_mut(),
+                ),
+                SetFieldValueStatus::Ok
+            );
+
+            assert!(match_filter(
+                "num1 == 0x7fffffffffffffff",
+                &scheme,
+                &exec_context
+            ));
+
+            wirefilter_free_execution_context(exec_context);
+        }
+
+        wirefilter_free_scheme(scheme);
+    }
+
+    #[test]
+    fn float_values() {
+        extern "C" fn provide_nan(_: *mut c_void, value: &mut f64) -> bool {
+            *value = f64::NAN;
+            true
+        }
+
+        let mut scheme = create_scheme();
+
+        wirefilter_add_type_field_to_scheme(
+            &mut scheme,
+            ExternallyAllocatedStr::from("score1"),
+            CType::Float,
+        );
+        wirefilter_add_type_field_to_scheme(
+            &mut scheme,
+            ExternallyAllocatedStr::from("score2"),
+            CType::Float,
+        );
+
+        {
+            let score2 =
+                wirefilter_get_field(&scheme, ExternallyAllocatedStr::from("score2")).unwrap();
+
+            let mut exec_context = wirefilter_create_execution_context(&scheme);
+
+            assert_eq!(
+                wirefilter_add_float_value_to_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("score1"),
+                    0.75,
+                ),
+                SetFieldValueStatus::Ok
+            );
+
+            assert_eq!(
+                wirefilter_add_float_value_to_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("num1"),
+                    1.0,
+                ),
+                SetFieldValueStatus::TypeMismatch
+            );
+
+            assert_eq!(
+                wirefilter_add_float_value_to_execution_context_by_field(
+                    &mut exec_context,
+                    score2,
+                    -2.5e-3,
+                ),
+                SetFieldValueStatus::Ok
+            );
+
+            assert!(match_filter(
+                "score1 in { 0.5..1.0 } && score2 < 0",
+                &scheme,
+                &exec_context
+            ));
+
+            assert_eq!(
+                wirefilter_add_lazy_float_value_to_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("score1"),
+                    provide_nan,
+                    std::ptr::null_mut(),
+                ),
+                SetFieldValueStatus::Ok
+            );
+
+            assert!(!match_filter("score1 >= 0", &scheme, &exec_context));
+            assert!(!match_filter("score1 < 0", &scheme, &exec_context));
+            assert!(match_filter("score1 != 0", &scheme, &exec_context));
+
+            wirefilter_free_execution_context(exec_context);
+        }
+
+        wirefilter_free_scheme(scheme);
+    }
+
+    #[test]
+    fn mac_values() {
+        extern "C" fn provide_eui64(_: *mut c_void, value: &mut [u8; 8]) -> bool {
+            *value = [0x00, 0x00, 0x5e, 0xff, 0xfe, 0x00, 0x53, 0x01];
+            true
+        }
+
+        let mut scheme = create_scheme();
+
+        wirefilter_add_type_field_to_scheme(
+            &mut scheme,
+            ExternallyAllocatedStr::from("mac1"),
+            CType::Mac,
+        );
+        wirefilter_add_type_field_to_scheme(
+            &mut scheme,
+            ExternallyAllocatedStr::from("mac2"),
+            CType::Mac,
+        );
+
+        {
+            let mac2 = wirefilter_get_field(&scheme, ExternallyAllocatedStr::from("mac2")).unwrap();
+
+            let mut exec_context = wirefilter_create_execution_context(&scheme);
+
+            assert_eq!(
+                wirefilter_add_eui48_value_to_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("mac1"),
+                    &[0x00, 0x00, 0x5e, 0x00, 0x53, 0x01],
+                ),
+                SetFieldValueStatus::Ok
+            );
+
+            assert_eq!(
+                wirefilter_add_eui48_value_to_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("num1"),
+                    &[0; 6],
+                ),
+                SetFieldValueStatus::TypeMismatch
+            );
+
+            assert_eq!(
+                wirefilter_add_eui64_value_to_execution_context_by_field(
+                    &mut exec_context,
+                    mac2,
+                    &[0x02, 0, 0, 0, 0, 0, 0, 0x01],
+                ),
+                SetFieldValueStatus::Ok
+            );
+
+            assert!(match_filter(
+                "mac1 in { 00:00:5e:00:00:00/24 } && mac2 == 02-00-00-00-00-00-00-01",
+                &scheme,
+                &exec_context
+            ));
+
+            assert_eq!(
+                wirefilter_add_lazy_eui64_value_to_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("mac1"),
+                    provide_eui64,
+                    std::ptr::null_mut(),
+                ),
+                SetFieldValueStatus::Ok
+            );
+
+            // EUI-64 addresses don't match EUI-48 prefixes
+            assert!(!match_filter(
+                "mac1 in { 00:00:5e:00:00:00/24 }",
+                &scheme,
+                &exec_context
+            ));
+            assert!(match_filter(
+                "mac1 in { 00:00:5e:00:00:00:00:00/24 }",
+                &scheme,
+                &exec_context
+            ));
+
+            wirefilter_free_execution_context(exec_context);
+        }
+
+        wirefilter_free_scheme(scheme);
+    }
+
+    #[test]
+    fn reused_execution_context() {
+        let scheme = create_scheme();
+
+        {
+            let mut exec_context = create_execution_context(&scheme);
+
+            assert!(match_filter("num1 == 42", &scheme, &exec_context));
+
+            assert_eq!(
+                wirefilter_unset_value_in_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("num1"),
+                ),
+                SetFieldValueStatus::Ok
+            );
+
+            assert_eq!(
+                wirefilter_unset_value_in_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("num3"),
+                ),
+                SetFieldValueStatus::UnknownField
+            );
+
+            assert!(!match_filter("num1 == 42", &scheme, &exec_context));
+            assert!(match_filter("num2 == 1337", &scheme, &exec_context));
+
+            wirefilter_clear_execution_context(&mut exec_context);
+
+            assert!(!match_filter("num2 == 1337", &scheme, &exec_context));
+
+            wirefilter_add_int_value_to_execution_context(
+                &mut exec_context,
+                ExternallyAllocatedStr::from("num2"),
+                1337,
+            );
+
+            assert!(match_filter("num2 == 1337", &scheme, &exec_context));
+
+            wirefilter_free_execution_context(exec_context);
+        }
+
+        wirefilter_free_scheme(scheme);
+    }
+
+    #[test]
+    fn owned_values() {
+        let scheme = create_scheme();
+
+        {
+            let mut exec_context = wirefilter_create_execution_context(&scheme);
+
+            {
+                let buffer = String::from("yo123");
+
+                wirefilter_add_owned_bytes_value_to_execution_context(
+                    &mut exec_context,
+                    ExternallyAllocatedStr::from("str2"),
+                    ExternallyAllocatedByteArr::from(buffer.as_str()),
+                );
+            }
+
+            assert!(match_filter(r#"str2 ~ "yo\d+""#, &scheme, &exec_context));
+
+            wirefilter_free_execution_context(exec_context);
+        }
+
+        wirefilter_free_scheme(scheme);
+    }
+
     #[test]
     fn filter_uses() {
         let scheme = create_scheme();
@@ -517,4 +1510,87 @@ mod ffi_test {
 
         wirefilter_free_scheme(scheme);
     }
+
+    #[test]
+    fn replaced_list() {
+        let mut scheme = create_scheme();
+
+        let list = wirefilter_parse_list(CType::Ip, ExternallyAllocatedStr::from("{ 10.0.0.0/8 }"))
+            .unwrap();
+        assert_eq!(
+            wirefilter_add_list_to_scheme(
+                &mut scheme,
+                ExternallyAllocatedStr::from("blocklist"),
+                list
+            ),
+            AddListStatus::Ok
+        );
+
+        let list = wirefilter_parse_list(CType::Ip, ExternallyAllocatedStr::from("{}")).unwrap();
+        assert_eq!(
+            wirefilter_add_list_to_scheme(
+                &mut scheme,
+                ExternallyAllocatedStr::from("blocklist"),
+                list
+            ),
+            AddListStatus::Redefinition
+        );
+
+        {
+            let filter = parse_filter(&scheme, "ip1 in $blocklist").unwrap();
+            let filter = wirefilter_compile_filter(filter);
+
+            let exec_context = create_execution_context(&scheme);
+
+            assert!(!wirefilter_match(&filter, &exec_context));
+
+            let list =
+                wirefilter_parse_list(CType::Ip, ExternallyAllocatedStr::from("{ 127.0.0.0/8 }"))
+                    .unwrap();
+            assert_eq!(
+                wirefilter_replace_list_in_scheme(
+                    &scheme,
+                    ExternallyAllocatedStr::from("blocklist"),
+                    list
+                ),
+                ReplaceListStatus::Ok
+            );
+
+            assert!(wirefilter_match(&filter, &exec_context));
+
+            let list =
+                wirefilter_parse_list(CType::Int, ExternallyAllocatedStr::from("{ 1 }")).unwrap();
+            assert_eq!(
+                wirefilter_replace_list_in_scheme(
+                    &scheme,
+                    ExternallyAllocatedStr::from("blocklist"),
+                    list
+                ),
+                ReplaceListStatus::TypeMismatch
+            );
+
+            let list =
+                wirefilter_parse_list(CType::Ip, ExternallyAllocatedStr::from("{}")).unwrap();
+            assert_eq!(
+                wirefilter_replace_list_in_scheme(
+                    &scheme,
+                    ExternallyAllocatedStr::from("allowlist"),
+                    list
+                ),
+                ReplaceListStatus::UnknownList
+            );
+
+            match wirefilter_parse_list(CType::Int, ExternallyAllocatedStr::from("{ a }")) {
+                ListParsingResult::Ok(_) => panic!("Error expected"),
+                ListParsingResult::Err(err) => wirefilter_free_string(err),
+            }
+
+            assert!(wirefilter_match(&filter, &exec_context));
+
+            wirefilter_free_execution_context(exec_context);
+            wirefilter_free_compiled_filter(filter);
+        }
+
+        wirefilter_free_scheme(scheme);
+    }
 }